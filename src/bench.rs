@@ -0,0 +1,258 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{
+        canvas::Canvas, config::GameConfig, material::Material, math::{rng, Vec2},
+        SINGLE_STEP_DURATION,
+    },
+    rendering::glsl_types::Resolution,
+};
+
+// ticks each scenario runs for unless told otherwise
+pub const DEFAULT_TICKS: u64 = 300;
+
+// every scenario starts from this seed, so results are comparable across commits
+const BENCH_SEED: u64 = 47;
+// canvas size of every scenario, the same as a new game
+const BENCH_RESOLUTION: Resolution = Resolution {
+    width: 500,
+    height: 500,
+};
+// pixels per second of the bullet, fast enough to cross the whole canvas in a single tick
+const BULLET_SPEED: f64 = 30_000.;
+
+thread_local! {
+    // per thread, so allocations of other threads don't end up in a benchmark's count
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn count_allocation() {
+    // fails while the thread is being torn down, those allocations aren't worth counting
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+/// System allocator that counts allocations, so benchmarks can report allocations per tick
+pub struct CountingAllocator;
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Allocations the calling thread made since it started
+pub fn allocations() -> u64 {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Canvas set up to stress one part of physics or `Grid`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scenario {
+    /// No dots at all, the fixed cost of a tick
+    Empty,
+    /// 100k sand dots falling from the top of the canvas
+    Avalanche,
+    /// Heap of sand resting on the floor
+    SettledPile,
+    /// Bottom of the canvas packed with blue dots sloshing sideways, the closest the game has to a liquid
+    LiquidPool,
+    /// One dot fired across the whole canvas every tick
+    Bullet,
+}
+impl Scenario {
+    pub const ALL: [Scenario; 5] = [
+        Scenario::Empty,
+        Scenario::Avalanche,
+        Scenario::SettledPile,
+        Scenario::LiquidPool,
+        Scenario::Bullet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenario::Empty => "empty",
+            Scenario::Avalanche => "avalanche",
+            Scenario::SettledPile => "settled-pile",
+            Scenario::LiquidPool => "liquid-pool",
+            Scenario::Bullet => "bullet",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scenario| scenario.name() == name)
+    }
+
+    /// Fresh canvas for the scenario, the same one every time
+    pub fn build(&self) -> Canvas {
+        rng::set_seed(BENCH_SEED);
+        let mut canvas = Canvas::new(BENCH_RESOLUTION);
+        let Resolution { width, height } = BENCH_RESOLUTION;
+        match self {
+            Scenario::Empty | Scenario::Bullet => (),
+            Scenario::Avalanche => {
+                for x in 50..450 {
+                    for y in 0..250 {
+                        let velocity = Vec2::new(rng::rand_f64(-20. ..20.), rng::rand_f64(0. ..20.));
+                        spawn(&mut canvas, Material::Sand, x, y, velocity);
+                    }
+                }
+            }
+            Scenario::SettledPile => {
+                for x in 0..width {
+                    let pile_height = 200 - (x - width / 2).abs();
+                    for y in height - pile_height.max(0)..height {
+                        spawn(&mut canvas, Material::Sand, x, y, Vec2::new(0., 0.));
+                    }
+                }
+            }
+            Scenario::LiquidPool => {
+                for x in 0..width {
+                    for y in height - 200..height {
+                        let velocity = Vec2::new(rng::rand_f64(-10. ..10.), 0.);
+                        spawn(&mut canvas, Material::Blue, x, y, velocity);
+                    }
+                }
+            }
+        }
+        canvas.write_dots_to_grid();
+        canvas
+    }
+
+    /// Puts the scenario's moving parts back in place before each tick
+    fn prepare_tick(&self, canvas: &mut Canvas) {
+        if *self != Scenario::Bullet {
+            return;
+        }
+        let fired = canvas.dots().next().map(|dot| dot.id);
+        if let Some(id) = fired {
+            canvas.remove_dot(id);
+        }
+        spawn(
+            canvas,
+            Material::Orange,
+            0,
+            BENCH_RESOLUTION.height / 2,
+            Vec2::new(BULLET_SPEED, 0.),
+        );
+    }
+}
+
+fn spawn(canvas: &mut Canvas, material: Material, x: i32, y: i32, velocity: Vec2<f64>) {
+    let position = Vec2::new(x as f64, y as f64);
+    canvas
+        .spawn_dot(material, position, velocity)
+        .expect("Benchmark dots get fresh ids");
+}
+
+/// How a scenario performed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchResult {
+    pub scenario: Scenario,
+    pub dots: usize,
+    pub ticks: u64,
+    pub elapsed: Duration,
+    pub allocations: u64,
+    /// Canvas state hash after the last tick, changes when a refactor changes what physics does
+    pub state_hash: u64,
+}
+impl BenchResult {
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks as f64 / self.elapsed.as_secs_f64().max(f64::MIN_POSITIVE)
+    }
+
+    pub fn allocations_per_tick(&self) -> f64 {
+        self.allocations as f64 / self.ticks.max(1) as f64
+    }
+}
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<13} {:>7} dots {:>5} ticks {:>10.1} ticks/s {:>10.1} allocations/tick  hash {:016x}",
+            self.scenario.name(),
+            self.dots,
+            self.ticks,
+            self.ticks_per_second(),
+            self.allocations_per_tick(),
+            self.state_hash
+        )
+    }
+}
+
+/// Builds the scenario and runs `ticks` ticks of physics on it, the same way a game frame does.
+/// Only the ticks are timed, building the canvas is not.
+pub fn run(scenario: Scenario, ticks: u64) -> BenchResult {
+    let mut canvas = scenario.build();
+    let config = GameConfig::default();
+
+    let allocations_before = allocations();
+    let start = Instant::now();
+    for _ in 0..ticks {
+        scenario.prepare_tick(&mut canvas);
+        canvas.step(SINGLE_STEP_DURATION, &config);
+    }
+    let elapsed = start.elapsed();
+
+    BenchResult {
+        scenario,
+        dots: canvas.dots().count(),
+        ticks,
+        elapsed,
+        allocations: allocations() - allocations_before,
+        state_hash: canvas.state_hash(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{allocations, run, Scenario};
+
+    #[test]
+    fn test_scenarios_are_repeatable() {
+        for scenario in Scenario::ALL {
+            assert_eq!(Some(scenario), Scenario::parse(scenario.name()));
+            assert_eq!(
+                scenario.build().state_hash(),
+                scenario.build().state_hash(),
+                "{} built differently",
+                scenario.name()
+            );
+        }
+        assert_eq!(100_000, Scenario::Avalanche.build().dots().count());
+        assert_eq!(100_000, Scenario::LiquidPool.build().dots().count());
+        assert_eq!(None, Scenario::parse("sandstorm"));
+    }
+
+    #[test]
+    fn test_run_counts_ticks_and_allocations() {
+        let first = run(Scenario::Bullet, 3);
+        let second = run(Scenario::Bullet, 3);
+        assert_eq!((3, 1), (first.ticks, first.dots));
+        assert_eq!(first.state_hash, second.state_hash);
+
+        // tests run in parallel, only this thread's allocations are counted
+        let before = allocations();
+        let boxed = Box::new(5);
+        assert_eq!(before + 1, allocations());
+        drop(boxed);
+    }
+}
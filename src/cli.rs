@@ -0,0 +1,409 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use unknown_game::{
+    internal::{BenchScenario, Lighting, PostProcessing},
+    Resolution,
+};
+
+use crate::{
+    display::{
+        device_selector::DeviceSelector, present_mode::PresentModeSetting,
+        renderer_kind::RendererKind,
+    },
+    logging::LogFilter,
+};
+
+pub const HELP: &str = "\
+Usage: unknown-game [OPTIONS]
+
+Options:
+  --size <WIDTHxHEIGHT>    Canvas size to start with, e.g. 800x600
+  --seed <NUMBER>          Seed for every random number, the clock is used otherwise
+  --config <PATH>          File of `key = value` lines with tuning values of the simulation
+  --set <KEY=VALUE>        Overrides one config value, can be given more than once
+  --load <PATH>            Starts with a saved canvas, like the PNG a quick save writes
+  --palette <PATH>         Palette file that maps the colours of --load to materials exactly
+  --headless <TICKS>       Runs this many ticks without a window, then prints the canvas hash
+  --replay <PATH>          Replays a recorded log without a window and checks it ends up the same
+  --record <PATH>          Records every input of this session so it can be replayed
+  --export <PATH>          With --headless or --replay, writes every frame to a .gif or a directory of PNGs
+  --renderer <NAME>        vulkan, or software to draw on the CPU when there is no working graphics driver
+  --device <INDEX|NAME>    Graphics device to render with instead of the best one
+  --present-mode <MODE>    fifo (vsync), mailbox or immediate (no vsync), the closest supported mode is used
+  --frame-cap <FPS>        Frames per second the game loop doesn't go over
+  --post-processing <LIST> off, or a comma separated list of bloom, bright-pass and blur passes to run
+  --lighting <on|off|SIZE> Light and shadows, SIZE is the canvas pixels per side of a light map cell
+  --list-devices           Prints the graphics devices that can be used and exits
+  --log <FILTER>           What gets logged, a default level and module levels like warn,game::canvas=debug
+  --log-file <PATH>        Also writes everything that gets logged to this file
+  --bench <SCENARIO|all>   Times physics ticks of empty, avalanche, settled-pile, liquid-pool or bullet and exits
+  --bench-ticks <TICKS>    Ticks each benchmark scenario runs for, 300 by default
+  --profile <PATH>         Times each phase of every frame and writes a Chrome trace, or CSV for a .csv path, on exit
+  -h, --help               Prints this help and exits
+
+Most options can also be set with UNKNOWN_GAME_* environment variables, options given here win.
+
+Exit codes: 0 on success, 1 when the game fails while running, 2 when the arguments are wrong,
+3 when --list-devices can't find out which graphics devices there are.";
+
+/// Options the game binary was started with, anything not given is `None`
+#[derive(Debug, Default, PartialEq)]
+pub struct Cli {
+    pub canvas_size: Option<Resolution>,
+    pub seed: Option<u64>,
+    pub config: Option<PathBuf>,
+    /// `key=value` config overrides in the order they were given
+    pub overrides: Vec<String>,
+    pub load: Option<PathBuf>,
+    pub palette: Option<PathBuf>,
+    pub headless_ticks: Option<u64>,
+    pub replay: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub export: Option<PathBuf>,
+    pub renderer: Option<RendererKind>,
+    pub device: Option<DeviceSelector>,
+    pub present_mode: Option<PresentModeSetting>,
+    /// Most frames per second the window may draw
+    pub frame_cap: Option<f64>,
+    pub post_processing: Option<PostProcessing>,
+    pub lighting: Option<Lighting>,
+    pub list_devices: bool,
+    pub log_filter: Option<LogFilter>,
+    pub log_file: Option<PathBuf>,
+    pub profile: Option<PathBuf>,
+    /// Benchmark scenarios to run, every one of them for `all`
+    pub bench: Vec<BenchScenario>,
+    pub bench_ticks: Option<u64>,
+    pub help: bool,
+}
+impl Cli {
+    /// Parses arguments without the program name. Values follow their option either as the next argument
+    /// or after `=`, like `--seed 7` or `--seed=7`.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut cli = Cli::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ => (arg, None),
+            };
+            if let "-h" | "--help" | "--list-devices" = option.as_str() {
+                if inline_value.is_some() {
+                    return Err(CliError::UnexpectedValue(option));
+                }
+                match option.as_str() {
+                    "--list-devices" => cli.list_devices = true,
+                    _ => cli.help = true,
+                }
+                continue;
+            }
+
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => value,
+                None if option.starts_with('-') => return Err(CliError::MissingValue(option)),
+                None => return Err(CliError::UnknownArgument(option)),
+            };
+            match option.as_str() {
+                "--size" => cli.canvas_size = Some(parse_size(&option, &value)?),
+                "--seed" => cli.seed = Some(parse_value(&option, &value, "a whole number")?),
+                "--config" => cli.config = Some(PathBuf::from(value)),
+                "--set" => {
+                    if !value.contains('=') {
+                        return Err(CliError::InvalidValue {
+                            option,
+                            value,
+                            expected: "key=value",
+                        });
+                    }
+                    cli.overrides.push(value);
+                }
+                "--load" => cli.load = Some(PathBuf::from(value)),
+                "--palette" => cli.palette = Some(PathBuf::from(value)),
+                "--headless" => {
+                    cli.headless_ticks = Some(parse_value(&option, &value, "a number of ticks")?)
+                }
+                "--replay" => cli.replay = Some(PathBuf::from(value)),
+                "--record" => cli.record = Some(PathBuf::from(value)),
+                "--export" => cli.export = Some(PathBuf::from(value)),
+                "--renderer" => {
+                    cli.renderer = Some(RendererKind::parse(&value).ok_or(CliError::InvalidValue {
+                        option,
+                        value,
+                        expected: "vulkan or software",
+                    })?)
+                }
+                "--device" => cli.device = Some(DeviceSelector::parse(&value)),
+                "--present-mode" => {
+                    cli.present_mode = Some(PresentModeSetting::parse(&value).ok_or(
+                        CliError::InvalidValue {
+                            option,
+                            value,
+                            expected: "fifo, mailbox or immediate",
+                        },
+                    )?)
+                }
+                "--frame-cap" => cli.frame_cap = Some(parse_frame_cap(&option, &value)?),
+                "--post-processing" => {
+                    cli.post_processing = Some(PostProcessing::parse(&value).ok_or(
+                        CliError::InvalidValue {
+                            option,
+                            value,
+                            expected: "off or a list of bloom, bright-pass and blur",
+                        },
+                    )?)
+                }
+                "--lighting" => {
+                    cli.lighting = Some(Lighting::parse(&value).ok_or(CliError::InvalidValue {
+                        option,
+                        value,
+                        expected: "off, on or a light map cell size",
+                    })?)
+                }
+                "--log" => {
+                    cli.log_filter = Some(LogFilter::parse(&value).ok_or(CliError::InvalidValue {
+                        option,
+                        value,
+                        expected: "a level and module levels like warn,game::canvas=debug",
+                    })?)
+                }
+                "--log-file" => cli.log_file = Some(PathBuf::from(value)),
+                "--profile" => cli.profile = Some(PathBuf::from(value)),
+                "--bench" => {
+                    cli.bench = match BenchScenario::parse(&value) {
+                        Some(scenario) => vec![scenario],
+                        None if value == "all" => BenchScenario::ALL.to_vec(),
+                        None => {
+                            return Err(CliError::InvalidValue {
+                                option,
+                                value,
+                                expected: "all or a scenario like avalanche",
+                            })
+                        }
+                    }
+                }
+                "--bench-ticks" => {
+                    cli.bench_ticks = Some(parse_value(&option, &value, "a number of ticks")?)
+                }
+                _ => return Err(CliError::UnknownArgument(option)),
+            }
+        }
+
+        if cli.replay.is_some() && cli.headless_ticks.is_some() {
+            return Err(CliError::Conflict("--replay and --headless"));
+        }
+        if cli.replay.is_some() && cli.load.is_some() {
+            return Err(CliError::Conflict("--replay and --load"));
+        }
+        if !cli.bench.is_empty() && (cli.replay.is_some() || cli.headless_ticks.is_some()) {
+            return Err(CliError::Conflict("--bench and --replay or --headless"));
+        }
+        Ok(cli)
+    }
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &str, expected: &'static str) -> Result<T, CliError> {
+    value.trim().parse().map_err(|_| CliError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        expected,
+    })
+}
+
+fn parse_frame_cap(option: &str, value: &str) -> Result<f64, CliError> {
+    let expected = "a positive number of frames per second";
+    let max_fps: f64 = parse_value(option, value, expected)?;
+    if max_fps > 0. && max_fps.is_finite() {
+        Ok(max_fps)
+    } else {
+        Err(CliError::InvalidValue {
+            option: option.to_string(),
+            value: value.to_string(),
+            expected,
+        })
+    }
+}
+
+fn parse_size(option: &str, value: &str) -> Result<Resolution, CliError> {
+    let invalid = || CliError::InvalidValue {
+        option: option.to_string(),
+        value: value.to_string(),
+        expected: "WIDTHxHEIGHT",
+    };
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    Ok(Resolution {
+        width: width.trim().parse().map_err(|_| invalid())?,
+        height: height.trim().parse().map_err(|_| invalid())?,
+    })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CliError {
+    UnknownArgument(String),
+    MissingValue(String),
+    /// A flag like `--help` was given a value
+    UnexpectedValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        expected: &'static str,
+    },
+    /// Options that can't be used together
+    Conflict(&'static str),
+}
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::UnknownArgument(arg) => write!(f, "unknown argument '{}'", arg),
+            CliError::MissingValue(option) => write!(f, "{} needs a value", option),
+            CliError::UnexpectedValue(option) => write!(f, "{} doesn't take a value", option),
+            CliError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(f, "invalid value '{}' for {}, expected {}", value, option, expected),
+            CliError::Conflict(options) => write!(f, "{} can't be used together", options),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use unknown_game::{
+        internal::{BenchScenario, Lighting, PostProcessing},
+        Resolution,
+    };
+
+    use super::{Cli, CliError};
+    use crate::display::{
+        device_selector::DeviceSelector, present_mode::PresentModeSetting,
+        renderer_kind::RendererKind,
+    };
+
+    fn parse(args: &[&str]) -> Result<Cli, CliError> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_no_arguments_is_the_default() {
+        assert_eq!(Cli::default(), parse(&[]).unwrap());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let cli = parse(&[
+            "--size", "300x200", "--seed=42", "--set", "friction=0.5", "--set=gravity-y=10",
+            "--headless", "60", "--export", "frames", "--device", "1", "--profile=frames.csv",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            Some(Resolution {
+                width: 300,
+                height: 200
+            }),
+            cli.canvas_size
+        );
+        assert_eq!(Some(42), cli.seed);
+        assert_eq!(vec!["friction=0.5", "gravity-y=10"], cli.overrides);
+        assert_eq!(Some(60), cli.headless_ticks);
+        assert_eq!(Some(PathBuf::from("frames")), cli.export);
+        assert_eq!(Some(DeviceSelector::Index(1)), cli.device);
+        assert_eq!(Some(PathBuf::from("frames.csv")), cli.profile);
+        assert!(!cli.help);
+    }
+
+    #[test]
+    fn test_bad_arguments() {
+        assert_eq!(
+            Err(CliError::UnknownArgument("--sead".to_string())),
+            parse(&["--sead", "1"])
+        );
+        assert_eq!(Err(CliError::MissingValue("--seed".to_string())), parse(&["--seed"]));
+        assert_eq!(
+            Err(CliError::UnexpectedValue("--help".to_string())),
+            parse(&["--help=yes"])
+        );
+        assert!(matches!(parse(&["--size", "300"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--headless", "-1"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--set", "friction"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--log", "game=loud"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(
+            parse(&["--replay", "a.replay", "--headless", "5"]),
+            Err(CliError::Conflict(_))
+        ));
+    }
+
+    #[test]
+    fn test_renderer() {
+        assert_eq!(
+            Some(RendererKind::Software),
+            parse(&["--renderer", "software"]).unwrap().renderer
+        );
+        assert_eq!(Some(RendererKind::Vulkan), parse(&["--renderer=vulkan"]).unwrap().renderer);
+        assert!(matches!(parse(&["--renderer", "opengl"]), Err(CliError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_present_mode_and_frame_cap() {
+        let cli = parse(&["--present-mode", "immediate", "--frame-cap=144"]).unwrap();
+        assert_eq!(Some(PresentModeSetting::Immediate), cli.present_mode);
+        assert_eq!(Some(144.), cli.frame_cap);
+        assert!(matches!(parse(&["--present-mode", "triple"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--frame-cap", "0"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(parse(&["--frame-cap", "inf"]), Err(CliError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_post_processing() {
+        assert_eq!(
+            Some(PostProcessing::OFF),
+            parse(&["--post-processing", "off"]).unwrap().post_processing
+        );
+        assert_eq!(
+            Some(PostProcessing {
+                bloom: true,
+                bright_pass: false,
+                blur: true,
+            }),
+            parse(&["--post-processing=bloom,blur"]).unwrap().post_processing
+        );
+        assert!(matches!(
+            parse(&["--post-processing", "bloom,sharpen"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_lighting() {
+        assert_eq!(Some(Lighting::OFF), parse(&["--lighting", "off"]).unwrap().lighting);
+        assert_eq!(
+            Some(Lighting {
+                enabled: true,
+                cell_size: 8,
+            }),
+            parse(&["--lighting=8"]).unwrap().lighting
+        );
+        assert!(matches!(parse(&["--lighting", "0"]), Err(CliError::InvalidValue { .. })));
+    }
+
+    #[test]
+    fn test_help() {
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--list-devices", "--help"]).unwrap().help);
+    }
+
+    #[test]
+    fn test_bench_scenarios() {
+        assert_eq!(vec![BenchScenario::Bullet], parse(&["--bench", "bullet"]).unwrap().bench);
+        assert_eq!(BenchScenario::ALL.to_vec(), parse(&["--bench=all"]).unwrap().bench);
+        assert!(matches!(parse(&["--bench", "rain"]), Err(CliError::InvalidValue { .. })));
+        assert!(matches!(
+            parse(&["--bench", "all", "--headless", "5"]),
+            Err(CliError::Conflict(_))
+        ));
+    }
+}
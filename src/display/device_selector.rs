@@ -0,0 +1,58 @@
+use std::fmt;
+
+/// Physical device picked by the player instead of the one the game would choose
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// Position in the list printed when listing devices
+    Index(usize),
+    /// Case insensitive part of the device name
+    Name(String),
+}
+impl DeviceSelector {
+    /// Numbers select by index, anything else by name
+    pub fn parse(value: &str) -> Self {
+        match value.trim().parse() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(value.trim().to_string()),
+        }
+    }
+
+    // only the renderer picks devices, builds without a window just parse the option
+    #[cfg_attr(not(feature = "window"), allow(dead_code))]
+    pub fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            DeviceSelector::Index(i) => *i == index,
+            DeviceSelector::Name(n) => name.to_lowercase().contains(&n.to_lowercase()),
+        }
+    }
+}
+impl fmt::Display for DeviceSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceSelector::Index(i) => write!(f, "device #{}", i),
+            DeviceSelector::Name(n) => write!(f, "device named '{}'", n),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeviceSelector;
+
+    #[test]
+    fn test_parse_selector() {
+        assert_eq!(DeviceSelector::Index(1), DeviceSelector::parse(" 1"));
+        assert_eq!(
+            DeviceSelector::Name("GeForce".to_string()),
+            DeviceSelector::parse("GeForce")
+        );
+    }
+
+    #[test]
+    fn test_name_selector_is_case_insensitive_substring() {
+        let selector = DeviceSelector::parse("radeon");
+        assert!(selector.matches(3, "AMD Radeon RX 6600"));
+        assert!(!selector.matches(0, "llvmpipe"));
+        assert!(DeviceSelector::Index(3).matches(3, "anything"));
+    }
+}
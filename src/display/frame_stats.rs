@@ -0,0 +1,124 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Frame times over one reporting interval
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSummary {
+    pub frames: u32,
+    /// Average time between two presented frames
+    pub mean_frame_time: Duration,
+    pub max_frame_time: Duration,
+    /// Average time the CPU spent blocked on fences per frame
+    pub mean_wait_time: Duration,
+}
+impl FrameSummary {
+    pub fn fps(&self) -> f64 {
+        1. / self.mean_frame_time.as_secs_f64()
+    }
+}
+impl fmt::Display for FrameSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames, frame time mean {:.2}ms max {:.2}ms, waiting on GPU {:.2}ms per frame",
+            self.frames,
+            self.mean_frame_time.as_secs_f64() * 1000.,
+            self.max_frame_time.as_secs_f64() * 1000.,
+            self.mean_wait_time.as_secs_f64() * 1000.,
+        )
+    }
+}
+
+/// Collects how long frames take and how much of that is spent waiting for the GPU,
+/// and hands out a summary once per `interval`
+pub struct FrameStats {
+    interval: Duration,
+    interval_start: Option<Instant>,
+    last_frame: Option<Instant>,
+    frames: u32,
+    total_frame_time: Duration,
+    max_frame_time: Duration,
+    total_wait_time: Duration,
+}
+impl FrameStats {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            interval_start: None,
+            last_frame: None,
+            frames: 0,
+            total_frame_time: Duration::ZERO,
+            max_frame_time: Duration::ZERO,
+            total_wait_time: Duration::ZERO,
+        }
+    }
+
+    /// Call once per presented frame with the time it spent waiting on fences.
+    /// Returns a summary when the interval is over.
+    pub fn record_frame(&mut self, now: Instant, wait_time: Duration) -> Option<FrameSummary> {
+        let interval_start = *self.interval_start.get_or_insert(now);
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            let frame_time = now - last_frame;
+            self.frames += 1;
+            self.total_frame_time += frame_time;
+            self.max_frame_time = self.max_frame_time.max(frame_time);
+            self.total_wait_time += wait_time;
+        }
+
+        if now - interval_start < self.interval || self.frames == 0 {
+            return None;
+        }
+        let summary = FrameSummary {
+            frames: self.frames,
+            mean_frame_time: self.total_frame_time / self.frames,
+            max_frame_time: self.max_frame_time,
+            mean_wait_time: self.total_wait_time / self.frames,
+        };
+        *self = Self {
+            interval_start: Some(now),
+            last_frame: Some(now),
+            ..Self::new(self.interval)
+        };
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::FrameStats;
+
+    #[test]
+    fn test_summary_after_interval() {
+        let start = Instant::now();
+        let mut stats = FrameStats::new(Duration::from_millis(100));
+        let ms = Duration::from_millis;
+
+        assert_eq!(None, stats.record_frame(start, ms(0)));
+        assert_eq!(None, stats.record_frame(start + ms(40), ms(10)));
+        assert_eq!(None, stats.record_frame(start + ms(60), ms(2)));
+        let summary = stats.record_frame(start + ms(100), ms(0)).unwrap();
+
+        assert_eq!(3, summary.frames);
+        assert_eq!(ms(100) / 3, summary.mean_frame_time);
+        assert_eq!(ms(40), summary.max_frame_time);
+        assert_eq!(ms(4), summary.mean_wait_time);
+        assert_eq!(30, summary.fps().round() as u32);
+    }
+
+    #[test]
+    fn test_stats_reset_after_summary() {
+        let start = Instant::now();
+        let mut stats = FrameStats::new(Duration::from_millis(10));
+        let ms = Duration::from_millis;
+        stats.record_frame(start, ms(0));
+        stats.record_frame(start + ms(50), ms(0)).unwrap();
+
+        let summary = stats.record_frame(start + ms(60), ms(1)).unwrap();
+        assert_eq!(1, summary.frames);
+        assert_eq!(ms(10), summary.max_frame_time);
+    }
+}
@@ -1,4 +1,11 @@
-use super::glsl_types::Vertex;
+use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex as VertexMacro};
+
+#[derive(BufferContents, VertexMacro, Debug)]
+#[repr(C)]
+pub struct Vertex {
+    #[format(R32G32_SFLOAT)]
+    pub position: [f32; 2],
+}
 
 #[derive(Debug)]
 pub struct Triangle(Vertex, Vertex, Vertex);
@@ -20,12 +27,8 @@ impl Triangle {
 pub struct Model(Vec<Triangle>);
 #[allow(dead_code)]
 impl Model {
-    pub fn new(mut triangles: impl Iterator<Item = Triangle>) -> Self {
-        let mut model_triangles = Vec::new();
-        while let Some(t) = triangles.next() {
-            model_triangles.push(t);
-        }
-        Self(model_triangles)
+    pub fn new(triangles: impl Iterator<Item = Triangle>) -> Self {
+        Self(triangles.collect())
     }
 
     pub fn into_vec_of_verticies(self) -> Vec<Vertex> {
@@ -0,0 +1,24 @@
+vulkano_shaders::shader! {
+    shaders: {
+        vertex: {
+            ty: "vertex",
+            path: "src/display/shaders/shader.vert"
+        },
+        fragment: {
+            ty: "fragment",
+            path: "src/display/shaders/shader.frag"
+        },
+        bright_pass: {
+            ty: "fragment",
+            path: "src/display/shaders/bright_pass.frag"
+        },
+        blur: {
+            ty: "fragment",
+            path: "src/display/shaders/blur.frag"
+        },
+        composite: {
+            ty: "fragment",
+            path: "src/display/shaders/composite.frag"
+        }
+    }
+}
@@ -0,0 +1,17 @@
+pub mod device_selector;
+#[cfg(feature = "window")]
+pub mod frame_stats;
+#[cfg(feature = "window")]
+pub mod geometry;
+#[cfg(feature = "window")]
+mod load_shaders;
+pub mod present_mode;
+#[cfg(feature = "window")]
+pub mod render_engine;
+#[cfg(feature = "window")]
+pub mod render_error;
+#[cfg(feature = "window")]
+pub mod renderer;
+pub mod renderer_kind;
+#[cfg(feature = "window")]
+pub mod software_presenter;
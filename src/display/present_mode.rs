@@ -0,0 +1,117 @@
+use std::fmt;
+
+#[cfg(feature = "window")]
+use vulkano::swapchain::PresentMode;
+
+/// How finished frames are handed to the screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresentModeSetting {
+    /// Vsync, frames queue up and the game waits for the display. Always supported.
+    #[default]
+    Fifo,
+    /// Vsync without waiting, a newer frame replaces the queued one
+    Mailbox,
+    /// No vsync, frames are shown right away and may tear
+    Immediate,
+}
+impl PresentModeSetting {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "fifo" | "vsync" => Some(PresentModeSetting::Fifo),
+            "mailbox" => Some(PresentModeSetting::Mailbox),
+            "immediate" => Some(PresentModeSetting::Immediate),
+            _ => None,
+        }
+    }
+}
+#[cfg(feature = "window")]
+impl PresentModeSetting {
+    pub fn to_vulkan(self) -> PresentMode {
+        match self {
+            PresentModeSetting::Fifo => PresentMode::Fifo,
+            PresentModeSetting::Mailbox => PresentMode::Mailbox,
+            PresentModeSetting::Immediate => PresentMode::Immediate,
+        }
+    }
+
+    /// Modes to try in order, ending with `Fifo` which every device has to support
+    fn preference(self) -> &'static [PresentMode] {
+        match self {
+            PresentModeSetting::Fifo => &[PresentMode::Fifo],
+            PresentModeSetting::Mailbox => &[PresentMode::Mailbox, PresentMode::Fifo],
+            PresentModeSetting::Immediate => &[
+                PresentMode::Immediate,
+                PresentMode::Mailbox,
+                PresentMode::Fifo,
+            ],
+        }
+    }
+
+    /// Picks the preferred mode out of the ones the surface supports, falling back to the closest one
+    pub fn choose(self, supported: &[PresentMode]) -> PresentMode {
+        self.preference()
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(PresentMode::Fifo)
+    }
+}
+impl fmt::Display for PresentModeSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresentModeSetting::Fifo => write!(f, "fifo"),
+            PresentModeSetting::Mailbox => write!(f, "mailbox"),
+            PresentModeSetting::Immediate => write!(f, "immediate"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "window")]
+    use vulkano::swapchain::PresentMode;
+
+    use super::PresentModeSetting;
+
+    #[test]
+    fn test_parse_present_mode() {
+        assert_eq!(
+            Some(PresentModeSetting::Mailbox),
+            PresentModeSetting::parse(" Mailbox")
+        );
+        assert_eq!(
+            Some(PresentModeSetting::Fifo),
+            PresentModeSetting::parse("vsync")
+        );
+        assert_eq!(None, PresentModeSetting::parse("triple"));
+    }
+
+    #[test]
+    #[cfg(feature = "window")]
+    fn test_unsupported_mode_falls_back() {
+        let fifo_only = [PresentMode::Fifo];
+        let with_mailbox = [PresentMode::Fifo, PresentMode::Mailbox];
+        let everything = [
+            PresentMode::Fifo,
+            PresentMode::Mailbox,
+            PresentMode::Immediate,
+        ];
+
+        assert_eq!(
+            PresentMode::Immediate,
+            PresentModeSetting::Immediate.choose(&everything)
+        );
+        assert_eq!(
+            PresentMode::Mailbox,
+            PresentModeSetting::Immediate.choose(&with_mailbox)
+        );
+        assert_eq!(
+            PresentMode::Fifo,
+            PresentModeSetting::Mailbox.choose(&fifo_only)
+        );
+        assert_eq!(
+            PresentMode::Fifo,
+            PresentModeSetting::Fifo.choose(&everything)
+        );
+    }
+}
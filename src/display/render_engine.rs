@@ -0,0 +1,1500 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use unknown_game::{
+    internal::{
+        profile_span, LightMap, Lighting, PostProcessing, PostPushConstants, ViewUniform,
+        CELL_BYTES,
+    },
+    DirtyRect, Game, Resolution, Shading,
+};
+use super::geometry::{Model, Triangle, Vertex};
+
+use log::{info, warn};
+use vulkano::{
+    buffer::subbuffer::Subbuffer,
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+        AutoCommandBufferBuilder, CommandBufferExecFuture, CommandBufferUsage,
+        PrimaryAutoCommandBuffer, RenderPassBeginInfo, SubpassBeginInfo, SubpassEndInfo,
+    },
+    descriptor_set::{
+        allocator::{StandardDescriptorSetAllocator, StandardDescriptorSetAllocatorCreateInfo},
+        layout::DescriptorSetLayout,
+        PersistentDescriptorSet, WriteDescriptorSet,
+    },
+    device::{
+        physical::{PhysicalDevice, PhysicalDeviceType}, Device, DeviceCreateInfo, DeviceExtensions, Features, Queue,
+        QueueCreateInfo, QueueFlags,
+    },
+    format::{ClearValue, Format},
+    image::{view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    instance::{Instance, InstanceCreateInfo},
+    memory::allocator::{
+        AllocationCreateInfo, MemoryAllocatePreference, MemoryTypeFilter, StandardMemoryAllocator,
+    },
+    pipeline::{
+        graphics::{
+            color_blend::{ColorBlendAttachmentState, ColorBlendState},
+            input_assembly::InputAssemblyState,
+            multisample::MultisampleState,
+            rasterization::RasterizationState,
+            vertex_input::{Vertex as VertexMacro, VertexDefinition},
+            viewport::{Viewport, ViewportState},
+            GraphicsPipelineCreateInfo,
+        },
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        GraphicsPipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+    shader::ShaderModule,
+    swapchain::{
+        self, FullScreenExclusive, PresentFuture, PresentMode, Surface, Swapchain,
+        SwapchainAcquireFuture, SwapchainCreateInfo, SwapchainPresentInfo,
+    },
+    sync::{
+        self,
+        future::{FenceSignalFuture, JoinFuture},
+        GpuFuture,
+    },
+    Validated, Version, VulkanError, VulkanLibrary,
+};
+use winit::{dpi::PhysicalSize, event_loop::EventLoop, window::Window};
+
+use crate::windowing::dpi;
+
+use super::frame_stats::FrameStats;
+use super::present_mode::PresentModeSetting;
+use super::{device_selector::DeviceSelector, render_error::RenderError};
+use super::renderer::Renderer;
+use super::load_shaders;
+
+// set number of the available descriptor sets
+// these numbers must align with what is used in the shaders
+const DS_PER_FRAME_SET_NUM: u32 = 0;
+const DS_INFREQUENT_UNIFORM_SET_NUM: u32 = 1;
+
+// the scene and every post processing pass are drawn into images of this format
+// alpha of the scene holds how much light each pixel gives off
+const OFFSCREEN_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+pub type Fence = FenceSignalFuture<
+    PresentFuture<CommandBufferExecFuture<JoinFuture<Box<dyn GpuFuture>, SwapchainAcquireFuture>>>,
+>;
+
+struct AppliedDescriptorSet {
+    set: Arc<PersistentDescriptorSet>,
+    set_number: u32,
+}
+
+struct AppliedDescriptorSets {
+    /// One set per swapchain image, so a frame can be written while the GPU still reads another one
+    ds_per_frame: Vec<AppliedDescriptorSet>,
+    ds_infrequent_uniform: AppliedDescriptorSet,
+}
+impl AppliedDescriptorSets {
+    fn to_vec_of_sorted_sets(&self, image_i: usize) -> Vec<Arc<PersistentDescriptorSet>> {
+        let mut sets = [&self.ds_per_frame[image_i], &self.ds_infrequent_uniform]
+            .into_iter()
+            .collect::<Vec<_>>();
+        sets.sort_by_key(|ds| ds.set_number);
+        sets.into_iter().map(|ds| ds.set.clone()).collect()
+    }
+}
+
+/// Buffers that are written every frame, one of each per swapchain image
+struct FrameBuffers {
+    canvas_buffer: Subbuffer<[u8]>,
+    view_buffer: Subbuffer<[ViewUniform]>,
+    /// Packed `LightMap` texels, holds a single unused texel while lighting is off
+    light_map_buffer: Subbuffer<[u32]>,
+    /// Areas of the canvas that changed since this image's canvas buffer was last written
+    pending_uploads: Vec<DirtyRect>,
+    /// Set when the whole canvas buffer has to be written, e.g. because it was just created
+    full_upload: bool,
+}
+impl FrameBuffers {
+    fn queue_upload(&mut self, dirty_rects: &[DirtyRect], canvas_len: usize) {
+        if self.full_upload {
+            return;
+        }
+        self.pending_uploads.extend_from_slice(dirty_rects);
+        // copying everything at once is cheaper than lots of spans once most of the canvas changed
+        let pending_len: usize = self.pending_uploads.iter().map(DirtyRect::area).sum();
+        if pending_len * 2 > canvas_len {
+            self.full_upload = true;
+            self.pending_uploads.clear();
+        }
+    }
+}
+
+/// Image the scene or a post processing pass is drawn into, read by the passes after it
+struct OffscreenTarget {
+    view: Arc<ImageView>,
+    framebuffer: Arc<Framebuffer>,
+}
+
+/// Offscreen images the size of the window that one swapchain image's frame draws through
+struct PostTargets {
+    scene: OffscreenTarget,
+    bright: OffscreenTarget,
+    /// Horizontally blurred glow
+    blur_a: OffscreenTarget,
+    /// Glow blurred in both directions
+    blur_b: OffscreenTarget,
+}
+
+struct PostShaders {
+    bright_pass: Arc<ShaderModule>,
+    blur: Arc<ShaderModule>,
+    composite: Arc<ShaderModule>,
+}
+
+struct PostPipelines {
+    bright_pass: Arc<GraphicsPipeline>,
+    blur: Arc<GraphicsPipeline>,
+    composite: Arc<GraphicsPipeline>,
+}
+
+/// One fullscreen draw of the post processing chain before the composite
+struct PostPass {
+    framebuffer: Arc<Framebuffer>,
+    pipeline: Arc<GraphicsPipeline>,
+    descriptor_set: Arc<PersistentDescriptorSet>,
+    push_constants: Option<PostPushConstants>,
+}
+
+pub struct RenderEngine {
+    frame_buffers: Vec<FrameBuffers>,
+    swapchain: Arc<Swapchain>,
+    fences: Vec<Option<Arc<Fence>>>,
+    vertex_buffer: Subbuffer<[Vertex]>,
+    queue: Arc<Queue>,
+    device: Arc<Device>,
+    viewport: Viewport,
+    render_pass: Arc<RenderPass>,
+    framebuffers: Vec<Arc<Framebuffer>>,
+    offscreen_render_pass: Arc<RenderPass>,
+    /// One set per swapchain image, so a frame never draws over images a frame still in flight reads
+    post_targets: Vec<PostTargets>,
+    post_shaders: PostShaders,
+    post_pipelines: PostPipelines,
+    command_buffers: Vec<Arc<PrimaryAutoCommandBuffer>>,
+    vertex_shader: Arc<ShaderModule>,
+    fragment_shader: Arc<ShaderModule>,
+    descriptor_sets: AppliedDescriptorSets,
+    canvas_res_buffer: Subbuffer<[Resolution]>,
+    pipeline: Arc<GraphicsPipeline>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+    frame_stats: Option<FrameStats>,
+    shading: Shading,
+    post_processing: PostProcessing,
+    lighting: Lighting,
+}
+impl RenderEngine {
+    fn create_instance(
+        event_loop: &EventLoop<()>,
+    ) -> Result<Arc<Instance>, RenderError> {
+        let library = VulkanLibrary::new().map_err(RenderError::NoVulkanLoader)?;
+        let required_extensions = Surface::required_extensions(event_loop);
+        Instance::new(
+            library,
+            InstanceCreateInfo {
+                application_name: Some("Unknown Game".to_string()),
+                application_version: Version::major_minor(0, 0),
+                enabled_extensions: required_extensions,
+                ..Default::default()
+            },
+        )
+        .map_err(RenderError::vulkan("create a Vulkan instance"))
+    }
+
+    fn init_vulkan_and_window(
+        event_loop: &EventLoop<()>,
+        window: Arc<Window>,
+    ) -> Result<(Arc<Instance>, Arc<Surface>), RenderError> {
+        let vk_instance = Self::create_instance(event_loop)?;
+        let vk_surface = Surface::from_window(vk_instance.clone(), window)
+            .map_err(RenderError::vulkan("create a surface for the window"))?;
+
+        Ok((vk_instance, vk_surface))
+    }
+
+    /// Names of every physical device in the order that `DeviceSelector::Index` refers to
+    pub fn list_devices(event_loop: &EventLoop<()>) -> Result<Vec<String>, RenderError> {
+        let vk_instance = Self::create_instance(event_loop)?;
+        Ok(vk_instance
+            .enumerate_physical_devices()
+            .map_err(RenderError::vulkan("list graphics devices"))?
+            .map(|physical_device| Self::describe_device(&physical_device))
+            .collect())
+    }
+
+    fn describe_device(physical_device: &PhysicalDevice) -> String {
+        let properties = physical_device.properties();
+        format!("{} ({:?})", properties.device_name, properties.device_type)
+    }
+
+    fn get_graphics_device(
+        vk_instance: Arc<Instance>,
+        vk_surface: Arc<Surface>,
+        selector: Option<&DeviceSelector>,
+    ) -> Result<(Arc<Device>, Arc<Queue>), RenderError> {
+        let device_extensions = DeviceExtensions {
+            khr_swapchain: true,
+            ..Default::default()
+        };
+
+        let physical_devices: Vec<_> = vk_instance
+            .enumerate_physical_devices()
+            .map_err(RenderError::vulkan("list graphics devices"))?
+            .collect();
+        let device_names: Vec<_> = physical_devices.iter().map(|d| Self::describe_device(d)).collect();
+
+        let candidates: Vec<_> = match selector {
+            None => physical_devices,
+            Some(selector) => {
+                let selected: Vec<_> = physical_devices
+                    .into_iter()
+                    .zip(device_names.iter())
+                    .enumerate()
+                    .filter(|(i, (_, name))| selector.matches(*i, name))
+                    .map(|(_, (physical_device, _))| physical_device)
+                    .collect();
+                if selected.is_empty() {
+                    return Err(RenderError::DeviceNotFound {
+                        selector: selector.clone(),
+                        devices: device_names,
+                    });
+                }
+                selected
+            }
+        };
+
+        // devices that can draw at all, with the queue families that can draw
+        let graphics_devices: Vec<_> = candidates
+            .into_iter()
+            .filter(|physical_device| {
+                physical_device
+                    .supported_extensions()
+                    .contains(&device_extensions)
+            })
+            .filter_map(|physical_device| {
+                let families: Vec<u32> = physical_device
+                    .queue_family_properties()
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, queue_family_properties)| {
+                        queue_family_properties
+                            .queue_flags
+                            .contains(QueueFlags::GRAPHICS | QueueFlags::TRANSFER)
+                    })
+                    .map(|(i, _)| i as u32)
+                    .collect();
+                (!families.is_empty()).then_some((physical_device, families))
+            })
+            .collect();
+        if graphics_devices.is_empty() {
+            return Err(RenderError::NoSuitableDevice {
+                devices: device_names,
+            });
+        }
+
+        let (physical_device, queue_family_index) = graphics_devices
+            .iter()
+            .filter_map(|(physical_device, families)| {
+                families
+                    .iter()
+                    .find(|i| {
+                        physical_device
+                            .surface_support(**i, &vk_surface)
+                            .unwrap_or(false)
+                    })
+                    .map(|i| (physical_device.clone(), *i))
+            })
+            .min_by_key(
+                |(physical_device, _)| match physical_device.properties().device_type {
+                    PhysicalDeviceType::DiscreteGpu => 0,
+                    PhysicalDeviceType::IntegratedGpu => 1,
+                    PhysicalDeviceType::VirtualGpu => 2,
+                    PhysicalDeviceType::Cpu => 3,
+                    _ => 4,
+                },
+            )
+            .ok_or_else(|| RenderError::SurfaceUnsupported {
+                reason: format!(
+                    "none of these devices can present to it: {}",
+                    graphics_devices
+                        .iter()
+                        .map(|(d, _)| Self::describe_device(d))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })?;
+
+        let (device, mut queues) = Device::new(
+            physical_device,
+            DeviceCreateInfo {
+                queue_create_infos: vec![QueueCreateInfo {
+                    queue_family_index,
+                    ..Default::default()
+                }],
+                enabled_extensions: device_extensions,
+                enabled_features: Features {
+                    runtime_descriptor_array: true,
+                    ..Features::empty()
+                },
+                ..Default::default()
+            },
+        )
+        .map_err(RenderError::vulkan("open the graphics device"))?;
+
+        Ok((device, queues.next().unwrap()))
+    }
+
+    fn create_swapchain(
+        device: Arc<Device>,
+        vk_surface: Arc<Surface>,
+        window: Arc<Window>,
+        present_mode: PresentModeSetting,
+    ) -> Result<(Arc<Swapchain>, Vec<Arc<Image>>), RenderError> {
+        let capabilities = device
+            .physical_device()
+            .surface_capabilities(&vk_surface, Default::default())
+            .map_err(RenderError::vulkan("read the window surface capabilities"))?;
+        let (image_format, image_color_space) = *device
+            .physical_device()
+            .surface_formats(&vk_surface, Default::default())
+            .map_err(RenderError::vulkan("read the window surface formats"))?
+            .first()
+            .ok_or(RenderError::SurfaceUnsupported {
+                reason: "it has no image formats".to_string(),
+            })?;
+        let composite_alpha = capabilities
+            .supported_composite_alpha
+            .into_iter()
+            .next()
+            .ok_or(RenderError::SurfaceUnsupported {
+                reason: "it supports no way of compositing".to_string(),
+            })?;
+        let supported_present_modes: Vec<PresentMode> = device
+            .physical_device()
+            .surface_present_modes(&vk_surface, Default::default())
+            .map_err(RenderError::vulkan("read the window surface present modes"))?
+            .collect();
+        let chosen_present_mode = present_mode.choose(&supported_present_modes);
+        if chosen_present_mode != present_mode.to_vulkan() {
+            warn!(
+                "Present mode {} is not supported, using {:?} instead",
+                present_mode, chosen_present_mode
+            );
+        }
+        Swapchain::new(
+            device.clone(),
+            vk_surface.clone(),
+            SwapchainCreateInfo {
+                min_image_count: capabilities.min_image_count + 1,
+                image_format,
+                image_color_space,
+                image_extent: window.inner_size().into(),
+                image_usage: ImageUsage::COLOR_ATTACHMENT,
+                present_mode: chosen_present_mode,
+                full_screen_exclusive: FullScreenExclusive::Default, // TODO: add full screen mode in the future, potentially
+                composite_alpha,
+                ..Default::default()
+            },
+        )
+        .map_err(RenderError::vulkan("create the swapchain"))
+    }
+
+    fn create_resolution_buffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        resolution: Resolution,
+    ) -> Result<Subbuffer<[Resolution]>, RenderError> {
+        Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            [resolution],
+        )
+        .map_err(RenderError::resource(
+            "allocate the canvas resolution buffer",
+        ))
+    }
+
+    fn create_view_buffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        view: ViewUniform,
+    ) -> Result<Subbuffer<[ViewUniform]>, RenderError> {
+        Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::UNIFORM_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            [view],
+        )
+        .map_err(RenderError::resource("allocate a view buffer"))
+    }
+
+    fn create_vertex_buffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: Vec<Vertex>,
+    ) -> Result<Subbuffer<[Vertex]>, RenderError> {
+        Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data,
+        )
+        .map_err(RenderError::resource("allocate the vertex buffer"))
+    }
+
+    fn create_canvas_buffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        mut data: Vec<u8>,
+    ) -> Result<Subbuffer<[u8]>, RenderError> {
+        // shader reads the canvas as an array of uints, so the buffer must be a multiple of 4 bytes
+        data.resize(data.len().next_multiple_of(4), 0);
+
+        Buffer::from_iter(
+            memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                allocate_preference: MemoryAllocatePreference::AlwaysAllocate,
+                ..Default::default()
+            },
+            data,
+        )
+        .map_err(RenderError::resource("allocate a canvas buffer"))
+    }
+
+    fn create_light_map_buffer(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        texel_count: usize,
+    ) -> Result<Subbuffer<[u32]>, RenderError> {
+        // buffers can't be empty
+        Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            vec![u32::MAX; texel_count.max(1)],
+        )
+        .map_err(RenderError::resource("allocate a light map buffer"))
+    }
+
+    fn create_canvas_model() -> Model {
+        // Creates a model the size of the screen
+        // calculate 4 corners
+        let norm_corner1 = [-1., -1.];
+        let norm_corner2 = [-1., 1.];
+        let norm_corner3 = [1., -1.];
+        let norm_corner4 = [1., 1.];
+
+        // generate triangles and model from corners
+        let triangle1 = Triangle::new(norm_corner1, norm_corner2, norm_corner3);
+        let triangle2 = Triangle::new(norm_corner2, norm_corner3, norm_corner4);
+        Model::new([triangle1, triangle2].into_iter())
+    }
+
+    fn create_render_pass(
+        device: Arc<Device>,
+        swapchain: Arc<Swapchain>,
+    ) -> Result<Arc<RenderPass>, RenderError> {
+        vulkano::single_pass_renderpass!(
+            device,
+            attachments: {
+                clear_color: {
+                    format: swapchain.image_format(),
+                    samples: 1,
+                    load_op: Clear,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [clear_color],
+                depth_stencil: {}
+            },
+        )
+        .map_err(RenderError::vulkan("create the render pass"))
+    }
+
+    fn create_framebuffers(
+        images: &[Arc<Image>],
+        render_pass: Arc<RenderPass>,
+    ) -> Result<Vec<Arc<Framebuffer>>, RenderError> {
+        images
+            .iter()
+            .map(|i| {
+                let view = ImageView::new_default(i.clone())
+                    .map_err(RenderError::vulkan("create a view of a swapchain image"))?;
+                Framebuffer::new(
+                    render_pass.clone(),
+                    FramebufferCreateInfo {
+                        attachments: vec![view],
+                        ..Default::default()
+                    },
+                )
+                .map_err(RenderError::vulkan(
+                    "create a framebuffer for a swapchain image",
+                ))
+            })
+            .collect()
+    }
+
+    // every pixel of an offscreen image is drawn each frame, so there is nothing to clear
+    fn create_offscreen_render_pass(device: Arc<Device>) -> Result<Arc<RenderPass>, RenderError> {
+        vulkano::single_pass_renderpass!(
+            device,
+            attachments: {
+                color: {
+                    format: OFFSCREEN_FORMAT,
+                    samples: 1,
+                    load_op: DontCare,
+                    store_op: Store,
+                },
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            },
+        )
+        .map_err(RenderError::vulkan("create the offscreen render pass"))
+    }
+
+    fn create_offscreen_target(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        render_pass: Arc<RenderPass>,
+        window_size: PhysicalSize<u32>,
+    ) -> Result<OffscreenTarget, RenderError> {
+        let image = Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: OFFSCREEN_FORMAT,
+                // a minimized window has no size, but images can't be empty
+                extent: [window_size.width.max(1), window_size.height.max(1), 1],
+                // drawn to as an attachment, read by the next passes as a storage image
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::STORAGE,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_DEVICE,
+                ..Default::default()
+            },
+        )
+        .map_err(RenderError::resource("allocate an offscreen image"))?;
+        let view = ImageView::new_default(image)
+            .map_err(RenderError::vulkan("create a view of an offscreen image"))?;
+        let framebuffer = Framebuffer::new(
+            render_pass,
+            FramebufferCreateInfo {
+                attachments: vec![view.clone()],
+                ..Default::default()
+            },
+        )
+        .map_err(RenderError::vulkan(
+            "create a framebuffer for an offscreen image",
+        ))?;
+        Ok(OffscreenTarget { view, framebuffer })
+    }
+
+    fn create_post_targets(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        render_pass: Arc<RenderPass>,
+        window_size: PhysicalSize<u32>,
+        count: usize,
+    ) -> Result<Vec<PostTargets>, RenderError> {
+        let create = || {
+            Self::create_offscreen_target(
+                memory_allocator.clone(),
+                render_pass.clone(),
+                window_size,
+            )
+        };
+        (0..count)
+            .map(|_| {
+                Ok(PostTargets {
+                    scene: create()?,
+                    bright: create()?,
+                    blur_a: create()?,
+                    blur_b: create()?,
+                })
+            })
+            .collect()
+    }
+
+    fn create_post_pipelines(
+        device: Arc<Device>,
+        vertex_shader: Arc<ShaderModule>,
+        shaders: &PostShaders,
+        offscreen_render_pass: Arc<RenderPass>,
+        render_pass: Arc<RenderPass>,
+        viewport: Viewport,
+    ) -> Result<PostPipelines, RenderError> {
+        let create = |fragment_shader: &Arc<ShaderModule>, render_pass: &Arc<RenderPass>| {
+            Self::create_graphics_pipeline(
+                device.clone(),
+                vertex_shader.clone(),
+                fragment_shader.clone(),
+                render_pass.clone(),
+                viewport.clone(),
+            )
+        };
+        Ok(PostPipelines {
+            bright_pass: create(&shaders.bright_pass, &offscreen_render_pass)?,
+            blur: create(&shaders.blur, &offscreen_render_pass)?,
+            // the composite writes the finished frame to the swapchain image
+            composite: create(&shaders.composite, &render_pass)?,
+        })
+    }
+
+    fn create_graphics_pipeline(
+        device: Arc<Device>,
+        vertex_shader: Arc<ShaderModule>,
+        fragment_shader: Arc<ShaderModule>,
+        render_pass: Arc<RenderPass>,
+        viewport: Viewport,
+    ) -> Result<Arc<GraphicsPipeline>, RenderError> {
+        let missing_main = || RenderError::Resource {
+            action: "create a graphics pipeline",
+            reason: "a shader has no main function".to_string(),
+        };
+        let vs_entry_point = vertex_shader.entry_point("main").ok_or_else(missing_main)?;
+        let fs_entry_point = fragment_shader
+            .entry_point("main")
+            .ok_or_else(missing_main)?;
+
+        let vertex_input_state = Vertex::per_vertex()
+            .definition(&vs_entry_point.info().input_interface)
+            .map_err(RenderError::vulkan(
+                "match the vertex layout to the vertex shader",
+            ))?;
+
+        let stages = [
+            PipelineShaderStageCreateInfo::new(vs_entry_point),
+            PipelineShaderStageCreateInfo::new(fs_entry_point),
+        ];
+
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
+                .into_pipeline_layout_create_info(device.clone())
+                .map_err(RenderError::resource("lay out the shader resources"))?,
+        )
+        .map_err(RenderError::vulkan("create a pipeline layout"))?;
+
+        let subpass = Subpass::from(render_pass, 0).ok_or_else(|| RenderError::Resource {
+            action: "create a graphics pipeline",
+            reason: "the render pass has no subpass".to_string(),
+        })?;
+
+        GraphicsPipeline::new(
+            device,
+            None,
+            GraphicsPipelineCreateInfo {
+                stages: stages.into_iter().collect(),
+                vertex_input_state: Some(vertex_input_state),
+                input_assembly_state: Some(InputAssemblyState::default()),
+                viewport_state: Some(ViewportState {
+                    viewports: [viewport].into_iter().collect(),
+                    ..Default::default()
+                }),
+                rasterization_state: Some(RasterizationState::default()),
+                multisample_state: Some(MultisampleState::default()),
+                color_blend_state: Some(ColorBlendState::with_attachment_states(
+                    subpass.num_color_attachments(),
+                    ColorBlendAttachmentState::default(),
+                )),
+                subpass: Some(subpass.into()),
+                ..GraphicsPipelineCreateInfo::layout(layout)
+            },
+        )
+        .map_err(RenderError::vulkan("create a graphics pipeline"))
+    }
+
+    // contents don't matter, every frame fills in its buffers before they are drawn
+    fn create_frame_buffers(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        canvas_resolution: Resolution,
+        lighting: Lighting,
+        count: usize,
+    ) -> Result<Vec<FrameBuffers>, RenderError> {
+        let buffer_len = (canvas_resolution.width * canvas_resolution.height) as usize * CELL_BYTES;
+        let light_map_len = if lighting.enabled {
+            let (width, height) = LightMap::dimensions(canvas_resolution, lighting.cell_size);
+            width * height
+        } else {
+            0
+        };
+        (0..count)
+            .map(|_| {
+                Ok(FrameBuffers {
+                    canvas_buffer: Self::create_canvas_buffer(
+                        memory_allocator.clone(),
+                        vec![0; buffer_len],
+                    )?,
+                    view_buffer: Self::create_view_buffer(
+                        memory_allocator.clone(),
+                        ViewUniform {
+                            origin: [0., 0.],
+                            scale: 1.,
+                            shading: 0,
+                            light_cell_size: 0,
+                        },
+                    )?,
+                    light_map_buffer: Self::create_light_map_buffer(
+                        memory_allocator.clone(),
+                        light_map_len,
+                    )?,
+                    pending_uploads: Vec::new(),
+                    full_upload: true,
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the per frame buffers and their descriptor sets, the command buffers must be rebuilt afterwards
+    fn rebuild_frame_buffers(
+        &mut self,
+        canvas_resolution: Resolution,
+        count: usize,
+    ) -> Result<(), RenderError> {
+        self.frame_buffers = Self::create_frame_buffers(
+            self.memory_allocator.clone(),
+            canvas_resolution,
+            self.lighting,
+            count,
+        )?;
+        self.descriptor_sets.ds_per_frame = self
+            .frame_buffers
+            .iter()
+            .map(|buffers| {
+                Self::create_ds_per_frame(
+                    &self.descriptor_set_allocator,
+                    self.pipeline.clone(),
+                    DS_PER_FRAME_SET_NUM,
+                    buffers,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// Layout of descriptor set `set_number` in `pipeline`
+    fn set_layout(
+        pipeline: &GraphicsPipeline,
+        set_number: u32,
+    ) -> Result<Arc<DescriptorSetLayout>, RenderError> {
+        pipeline
+            .layout()
+            .set_layouts()
+            .get(set_number as usize)
+            .cloned()
+            .ok_or_else(|| RenderError::Resource {
+                action: "create a descriptor set",
+                reason: format!("the shaders have no descriptor set {}", set_number),
+            })
+    }
+
+    // descriptor set with the canvas and view of a single frame
+    // the buffers here get updated every frame
+    fn create_ds_per_frame(
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        pipeline: Arc<GraphicsPipeline>,
+        set_number: u32,
+        frame_buffers: &FrameBuffers,
+    ) -> Result<AppliedDescriptorSet, RenderError> {
+        Ok(AppliedDescriptorSet {
+            set: PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                Self::set_layout(&pipeline, set_number)?,
+                [
+                    WriteDescriptorSet::buffer(0, frame_buffers.canvas_buffer.clone()),
+                    WriteDescriptorSet::buffer(1, frame_buffers.view_buffer.clone()),
+                    WriteDescriptorSet::buffer(2, frame_buffers.light_map_buffer.clone()),
+                ],
+                [],
+            )
+            .map_err(RenderError::vulkan("create the per frame descriptor set"))?,
+            set_number,
+        })
+    }
+
+    // descriptor set where the buffers get updated infrequently
+    fn create_ds_infrequent_uniform(
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        pipeline: Arc<GraphicsPipeline>,
+        set_number: u32,
+        canvas_res_buffer: &Subbuffer<[Resolution]>,
+    ) -> Result<AppliedDescriptorSet, RenderError> {
+        Ok(AppliedDescriptorSet {
+            set: PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                Self::set_layout(&pipeline, set_number)?,
+                [WriteDescriptorSet::buffer(0, canvas_res_buffer.clone())],
+                [],
+            )
+            .map_err(RenderError::vulkan(
+                "create the canvas resolution descriptor set",
+            ))?,
+            set_number,
+        })
+    }
+
+    // descriptor set with the offscreen images a post processing pass reads, bound in order
+    fn create_post_descriptor_set(
+        &self,
+        pipeline: &Arc<GraphicsPipeline>,
+        images: &[&OffscreenTarget],
+    ) -> Result<Arc<PersistentDescriptorSet>, RenderError> {
+        PersistentDescriptorSet::new(
+            &self.descriptor_set_allocator,
+            Self::set_layout(pipeline, 0)?,
+            images
+                .iter()
+                .enumerate()
+                .map(|(i, target)| WriteDescriptorSet::image_view(i as u32, target.view.clone())),
+            [],
+        )
+        .map_err(RenderError::vulkan(
+            "create a post processing descriptor set",
+        ))
+    }
+
+    /// Passes that turn the scene into glow, same order as `post_processing::apply`.
+    /// Returns them with the image holding the finished glow, which is the scene itself when no pass runs.
+    fn create_post_passes<'a>(
+        &self,
+        targets: &'a PostTargets,
+    ) -> Result<(Vec<PostPass>, &'a OffscreenTarget), RenderError> {
+        let pipelines = &self.post_pipelines;
+        let mut passes = Vec::new();
+        let mut glow = &targets.scene;
+        if self.post_processing.bloom {
+            if self.post_processing.bright_pass {
+                passes.push(PostPass {
+                    framebuffer: targets.bright.framebuffer.clone(),
+                    pipeline: pipelines.bright_pass.clone(),
+                    descriptor_set: self
+                        .create_post_descriptor_set(&pipelines.bright_pass, &[&targets.scene])?,
+                    push_constants: None,
+                });
+                glow = &targets.bright;
+            }
+            if self.post_processing.blur {
+                for (source, target, direction) in [
+                    (glow, &targets.blur_a, [1, 0]),
+                    (&targets.blur_a, &targets.blur_b, [0, 1]),
+                ] {
+                    passes.push(PostPass {
+                        framebuffer: target.framebuffer.clone(),
+                        pipeline: pipelines.blur.clone(),
+                        descriptor_set: self
+                            .create_post_descriptor_set(&pipelines.blur, &[source])?,
+                        push_constants: Some(PostPushConstants::blur(direction)),
+                    });
+                }
+                glow = &targets.blur_b;
+            }
+        }
+        Ok((passes, glow))
+    }
+
+    /// Draws the canvas quad with `pipeline` into `framebuffer`
+    fn record_fullscreen_pass(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        framebuffer: Arc<Framebuffer>,
+        clear_value: Option<ClearValue>,
+        pipeline: Arc<GraphicsPipeline>,
+        descriptor_sets: Vec<Arc<PersistentDescriptorSet>>,
+        push_constants: Option<PostPushConstants>,
+    ) -> Result<(), RenderError> {
+        let record_error = RenderError::vulkan("record a render pass");
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![clear_value],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo::default(),
+            )
+            .map_err(&record_error)?
+            .bind_pipeline_graphics(pipeline.clone())
+            .map_err(&record_error)?
+            .bind_vertex_buffers(0, self.vertex_buffer.clone())
+            .map_err(&record_error)?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                descriptor_sets,
+            )
+            .map_err(&record_error)?;
+        if let Some(push_constants) = push_constants {
+            builder
+                .push_constants(pipeline.layout().clone(), 0, push_constants)
+                .map_err(&record_error)?;
+        }
+        builder
+            .draw(self.vertex_buffer.len() as u32, 1, 0, 0)
+            .map_err(&record_error)?
+            .end_render_pass(SubpassEndInfo::default())
+            .map_err(&record_error)?;
+        Ok(())
+    }
+
+    /// Records the scene, the post processing passes and the composite to the swapchain for every swapchain image
+    fn create_command_buffers(&self) -> Result<Vec<Arc<PrimaryAutoCommandBuffer>>, RenderError> {
+        let command_buffer_allocator = StandardCommandBufferAllocator::new(
+            self.device.clone(),
+            StandardCommandBufferAllocatorCreateInfo::default(),
+        );
+
+        self.framebuffers
+            .iter()
+            .zip(self.post_targets.iter())
+            .enumerate()
+            .map(|(image_i, (framebuffer, targets))| {
+                let (post_passes, glow) = self.create_post_passes(targets)?;
+                let composite_set = self.create_post_descriptor_set(
+                    &self.post_pipelines.composite,
+                    &[&targets.scene, glow],
+                )?;
+
+                let mut builder = AutoCommandBufferBuilder::primary(
+                    &command_buffer_allocator,
+                    self.queue.queue_family_index(),
+                    CommandBufferUsage::MultipleSubmit,
+                )
+                .map_err(RenderError::vulkan("start a command buffer"))?;
+
+                self.record_fullscreen_pass(
+                    &mut builder,
+                    targets.scene.framebuffer.clone(),
+                    None,
+                    self.pipeline.clone(),
+                    self.descriptor_sets.to_vec_of_sorted_sets(image_i),
+                    None,
+                )?;
+                for pass in post_passes.iter() {
+                    self.record_fullscreen_pass(
+                        &mut builder,
+                        pass.framebuffer.clone(),
+                        None,
+                        pass.pipeline.clone(),
+                        vec![pass.descriptor_set.clone()],
+                        pass.push_constants,
+                    )?;
+                }
+                self.record_fullscreen_pass(
+                    &mut builder,
+                    framebuffer.clone(),
+                    Some(ClearValue::Float([1., 1., 1., 1.])),
+                    self.post_pipelines.composite.clone(),
+                    vec![composite_set],
+                    Some(PostPushConstants::composite(
+                        self.post_processing.composite_intensity(),
+                    )),
+                )?;
+
+                builder
+                    .build()
+                    .map_err(RenderError::vulkan("finish a command buffer"))
+            })
+            .collect()
+    }
+
+    fn flush_swapchain(&mut self) -> Result<(), RenderError> {
+        for fence in self.fences.iter_mut() {
+            if let Some(f) = fence.take() {
+                f.wait(None)
+                    .map_err(RenderError::vulkan("wait for the GPU to finish a frame"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints a summary of frame times and time spent waiting on the GPU once per `interval`
+    pub fn enable_frame_stats(&mut self, interval: Duration) {
+        self.frame_stats = Some(FrameStats::new(interval));
+    }
+
+    pub fn recreate_swapchain(
+        &mut self,
+        window_size: PhysicalSize<u32>,
+    ) -> Result<Vec<Arc<Image>>, RenderError> {
+        let (new_swapchain, new_images) = self
+            .swapchain
+            .recreate(SwapchainCreateInfo {
+                image_extent: window_size.into(),
+                ..self.swapchain.create_info()
+            })
+            .map_err(RenderError::vulkan("recreate the swapchain"))?;
+        self.swapchain = new_swapchain;
+        Ok(new_images)
+    }
+
+    /// Canvas resolution the frame buffers are currently sized for
+    fn canvas_resolution(&self) -> Result<Resolution, RenderError> {
+        Ok(self
+            .canvas_res_buffer
+            .read()
+            .map_err(RenderError::resource("read the canvas resolution buffer"))?[0])
+    }
+
+    /// Sets up Vulkan for the window, `device` forces a physical device instead of picking the best one
+    pub fn new(
+        event_loop: &EventLoop<()>,
+        window: Arc<Window>,
+        game: &Game,
+        device: Option<&DeviceSelector>,
+        present_mode: PresentModeSetting,
+    ) -> Result<Self, RenderError> {
+        // init vulkan and window
+        let (vk_instance, vk_surface) = Self::init_vulkan_and_window(event_loop, window.clone())?;
+
+        // get graphics device
+        let (device, queue) =
+            Self::get_graphics_device(vk_instance.clone(), vk_surface.clone(), device)?;
+
+        // create memory allocator
+        let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+
+        // create swapchain
+        let (swapchain, images) =
+            Self::create_swapchain(
+                device.clone(),
+                vk_surface.clone(),
+                window.clone(),
+                present_mode,
+            )?;
+
+        // setup viewport
+        let viewport = Viewport {
+            offset: [0.0, 0.0],
+            extent: window.inner_size().into(),
+            depth_range: 0.0..=1.0,
+        };
+
+        // vertex setup
+        let canvas_model = Self::create_canvas_model();
+        let vertex_buffer = Self::create_vertex_buffer(
+            memory_allocator.clone(),
+            canvas_model.into_vec_of_verticies(),
+        )?;
+
+        // canvas and view setup, one of each per swapchain image
+        let frame_buffers = Self::create_frame_buffers(
+            memory_allocator.clone(),
+            game.canvas().resolution(),
+            Lighting::default(),
+            images.len(),
+        )?;
+
+        // resolutions_setup
+        let canvas_res_buffer =
+            Self::create_resolution_buffer(memory_allocator.clone(), game.canvas().resolution())?;
+
+        // setup render pass
+        let render_pass = Self::create_render_pass(device.clone(), swapchain.clone())?;
+
+        // create image view
+        let framebuffers = Self::create_framebuffers(&images, render_pass.clone())?;
+
+        // offscreen images for the scene and post processing
+        let offscreen_render_pass = Self::create_offscreen_render_pass(device.clone())?;
+        let post_targets = Self::create_post_targets(
+            memory_allocator.clone(),
+            offscreen_render_pass.clone(),
+            window.inner_size(),
+            images.len(),
+        )?;
+
+        // load shaders
+        let vertex_shader = load_shaders::load_vertex(device.clone())
+            .map_err(RenderError::vulkan("load the vertex shader"))?;
+        let fragment_shader = load_shaders::load_fragment(device.clone())
+            .map_err(RenderError::vulkan("load the fragment shader"))?;
+        let post_shaders = PostShaders {
+            bright_pass: load_shaders::load_bright_pass(device.clone())
+                .map_err(RenderError::vulkan("load the bright pass shader"))?,
+            blur: load_shaders::load_blur(device.clone())
+                .map_err(RenderError::vulkan("load the blur shader"))?,
+            composite: load_shaders::load_composite(device.clone())
+                .map_err(RenderError::vulkan("load the composite shader"))?,
+        };
+
+        // graphics pipelines, the canvas is drawn offscreen and composited to the swapchain
+        let pipeline = Self::create_graphics_pipeline(
+            device.clone(),
+            vertex_shader.clone(),
+            fragment_shader.clone(),
+            offscreen_render_pass.clone(),
+            viewport.clone(),
+        )?;
+        let post_pipelines = Self::create_post_pipelines(
+            device.clone(),
+            vertex_shader.clone(),
+            &post_shaders,
+            offscreen_render_pass.clone(),
+            render_pass.clone(),
+            viewport.clone(),
+        )?;
+
+        // create descriptor set allocator
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(
+            device.clone(),
+            StandardDescriptorSetAllocatorCreateInfo {
+                update_after_bind: true,
+                ..Default::default()
+            },
+        );
+
+        // create descriptor sets
+        let ds_per_frame = frame_buffers
+            .iter()
+            .map(|buffers| {
+                Self::create_ds_per_frame(
+                    &descriptor_set_allocator,
+                    pipeline.clone(),
+                    DS_PER_FRAME_SET_NUM,
+                    buffers,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        let ds_infrequent_uniform = Self::create_ds_infrequent_uniform(
+            &descriptor_set_allocator,
+            pipeline.clone(),
+            DS_INFREQUENT_UNIFORM_SET_NUM,
+            &canvas_res_buffer,
+        )?;
+        let descriptor_sets = AppliedDescriptorSets {
+            ds_per_frame,
+            ds_infrequent_uniform,
+        };
+
+        // setup fences vector so CPU doesn't have to wait for GPU
+        let fences: Vec<Option<Arc<FenceSignalFuture<_>>>> = vec![None; images.len()];
+
+        let mut render_engine = Self {
+            device,
+            fences,
+            queue,
+            swapchain,
+            viewport,
+            render_pass,
+            // recorded below, once everything they use is in place
+            command_buffers: Vec::new(),
+            vertex_shader,
+            vertex_buffer,
+            frame_buffers,
+            fragment_shader,
+            descriptor_sets,
+            canvas_res_buffer,
+            pipeline,
+            framebuffers,
+            offscreen_render_pass,
+            post_targets,
+            post_shaders,
+            post_pipelines,
+            memory_allocator,
+            descriptor_set_allocator,
+            frame_stats: None,
+            shading: Shading::default(),
+            post_processing: PostProcessing::default(),
+            lighting: Lighting::default(),
+        };
+
+        // create command buffers
+        render_engine.command_buffers = render_engine.create_command_buffers()?;
+        Ok(render_engine)
+    }
+}
+impl Renderer for RenderEngine {
+    fn resize_window(&mut self, window: Arc<Window>) -> Result<(), RenderError> {
+        self.flush_swapchain()?;
+
+        let new_images = self.recreate_swapchain(window.inner_size())?;
+        let new_framebuffers = Self::create_framebuffers(&new_images, self.render_pass.clone())?;
+
+        self.viewport.extent = window.inner_size().into();
+
+        let new_pipeline = Self::create_graphics_pipeline(
+            self.device.clone(),
+            self.vertex_shader.clone(),
+            self.fragment_shader.clone(),
+            self.offscreen_render_pass.clone(),
+            self.viewport.clone(),
+        )?;
+
+        self.pipeline = new_pipeline;
+        self.framebuffers = new_framebuffers;
+
+        // offscreen images have to match the window size and the number of swapchain images
+        self.post_targets = Self::create_post_targets(
+            self.memory_allocator.clone(),
+            self.offscreen_render_pass.clone(),
+            window.inner_size(),
+            new_images.len(),
+        )?;
+        self.post_pipelines = Self::create_post_pipelines(
+            self.device.clone(),
+            self.vertex_shader.clone(),
+            &self.post_shaders,
+            self.offscreen_render_pass.clone(),
+            self.render_pass.clone(),
+            self.viewport.clone(),
+        )?;
+
+        // the new swapchain may have a different amount of images
+        if new_images.len() != self.frame_buffers.len() {
+            self.rebuild_frame_buffers(self.canvas_resolution()?, new_images.len())?;
+            self.fences = vec![None; new_images.len()];
+        }
+
+        self.command_buffers = self.create_command_buffers()?;
+        Ok(())
+    }
+
+    /// Rebuilds the canvas buffers and their descriptor sets after the canvas resolution changed
+    fn resize_canvas(&mut self, game: &Game) -> Result<(), RenderError> {
+        self.flush_swapchain()?;
+
+        for res in self
+            .canvas_res_buffer
+            .write()
+            .map_err(RenderError::resource("write the canvas resolution buffer"))?
+            .iter_mut()
+        {
+            *res = game.canvas().resolution();
+        }
+        self.rebuild_frame_buffers(game.canvas().resolution(), self.frame_buffers.len())?;
+
+        self.command_buffers = self.create_command_buffers()?;
+        Ok(())
+    }
+
+    fn set_shading(&mut self, shading: Shading) {
+        // picked up by the view uniform of the next frame
+        self.shading = shading;
+    }
+
+    /// Rebuilds the command buffers with only the passes that are turned on
+    fn set_post_processing(&mut self, post_processing: PostProcessing) -> Result<(), RenderError> {
+        if post_processing == self.post_processing {
+            return Ok(());
+        }
+        self.flush_swapchain()?;
+        self.post_processing = post_processing;
+        self.command_buffers = self.create_command_buffers()?;
+        Ok(())
+    }
+
+    /// The light map buffers are sized for the cell size, so they are rebuilt along with the command buffers
+    fn set_lighting(&mut self, lighting: Lighting) -> Result<(), RenderError> {
+        if lighting == self.lighting {
+            return Ok(());
+        }
+        self.flush_swapchain()?;
+        self.lighting = lighting;
+        self.rebuild_frame_buffers(self.canvas_resolution()?, self.frame_buffers.len())?;
+        self.command_buffers = self.create_command_buffers()?;
+        Ok(())
+    }
+
+    fn display_next_frame(&mut self, game: &Game, window: Arc<Window>) -> Result<(), RenderError> {
+        // if set to true any time during this function call, the swapchain and everything
+        // built on it are rebuilt after presenting, the next frame draws to the new swapchain
+        let mut recreate_swapchain_after_presentation = false;
+        let _span = profile_span("display_next_frame");
+
+        // every image's canvas buffer has to catch up on what changed since the last frame, not just the next one
+        let dirty_rects = game.canvas().grid().dirty_rects();
+        let canvas_resolution = game.canvas().resolution();
+        let canvas_len = (canvas_resolution.width * canvas_resolution.height) as usize;
+        for buffers in self.frame_buffers.iter_mut() {
+            buffers.queue_upload(&dirty_rects, canvas_len);
+        }
+
+        // aquire current image index and time that the image finishes being created
+        let (image_i, suboptimal, acquire_image_future) =
+            match swapchain::acquire_next_image(self.swapchain.clone(), None)
+                .map_err(Validated::unwrap)
+            {
+                Ok(r) => r,
+                Err(VulkanError::OutOfDate) => {
+                    // window changed since the swapchain was made, skip this frame and draw the next one to the new swapchain
+                    return self.resize_window(window);
+                }
+                Err(e) => return Err(RenderError::vulkan("acquire the next swapchain image")(e)),
+            };
+        let previous_image_i = if image_i == 0 {
+            self.command_buffers.len() as u32 - 1
+        } else {
+            image_i - 1
+        };
+
+        // suboptimal if properties of swapchain and image differ, image will still display
+        if suboptimal {
+            recreate_swapchain_after_presentation = true;
+            warn!("swapchain function is suboptimal");
+        }
+
+        // wait for image in current position to finish displaying
+        let wait_start = Instant::now();
+        if let Some(image_fence) = self.fences[image_i as usize].clone() {
+            let _span = profile_span("fence_wait");
+            image_fence
+                .wait(None)
+                .map_err(RenderError::vulkan("wait for the GPU to finish a frame"))?;
+        }
+        let wait_time = wait_start.elapsed();
+
+        // the buffers of this image are free once its fence is signaled, while other images can still be in flight
+        let frame_buffers = &mut self.frame_buffers[image_i as usize];
+
+        // write the parts of the canvas that changed to buffer
+        {
+            let _span = profile_span("canvas_upload");
+            let mut canvas_bytes = frame_buffers
+                .canvas_buffer
+                .write()
+                .map_err(RenderError::resource("write the canvas buffer"))?;
+            if frame_buffers.full_upload {
+                for (byte, new_byte) in canvas_bytes
+                    .iter_mut()
+                    .zip(game.canvas().grid().iter_cells_as_bytes())
+                {
+                    *byte = new_byte;
+                }
+            } else {
+                let canvas_width = canvas_resolution.width as usize;
+                for rect in frame_buffers.pending_uploads.iter() {
+                    for span in rect.row_spans(canvas_width) {
+                        let byte_span = span.start * CELL_BYTES..span.end * CELL_BYTES;
+                        for (byte, new_byte) in canvas_bytes[byte_span]
+                            .iter_mut()
+                            .zip(game.canvas().grid().iter_span_cells_as_bytes(span))
+                        {
+                            *byte = new_byte;
+                        }
+                    }
+                }
+            }
+        }
+        frame_buffers.full_upload = false;
+        frame_buffers.pending_uploads.clear();
+
+        // light changes with every dot that moves, so the whole light map is worked out again
+        if self.lighting.enabled {
+            let _span = profile_span("light_map");
+            let light_map = LightMap::generate(game.canvas(), self.lighting.cell_size);
+            for (texel, new_texel) in frame_buffers
+                .light_map_buffer
+                .write()
+                .map_err(RenderError::resource("write the light map buffer"))?
+                .iter_mut()
+                .zip(light_map.iter_packed())
+            {
+                *texel = new_texel;
+            }
+        }
+
+        // write mapping from window to canvas to buffer
+        for view in frame_buffers
+            .view_buffer
+            .write()
+            .map_err(RenderError::resource("write the view buffer"))?
+            .iter_mut()
+        {
+            *view = game
+                .view_transform(dpi::size(window.inner_size()))
+                .to_uniform(self.shading, self.lighting);
+        }
+
+        // get time that previous image finishes displaying (or now if there is no previous image)
+        let previous_display_future = match self.fences[previous_image_i as usize].clone() {
+            None => {
+                let mut now = sync::now(self.device.clone());
+                now.cleanup_finished();
+                now.boxed()
+            }
+            Some(fence) => fence.boxed(),
+        };
+
+        // execute displaying image
+        // also get time that this image finishes displaying merged with time that previous image finished displaying
+        let current_display_future = previous_display_future
+            .join(acquire_image_future)
+            .then_execute(
+                self.queue.clone(),
+                self.command_buffers[image_i as usize].clone(),
+            )
+            .map_err(RenderError::resource("execute the command buffer"))?
+            .then_swapchain_present(
+                self.queue.clone(),
+                SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_i),
+            )
+            .then_signal_fence_and_flush();
+
+        self.fences[image_i as usize] = match current_display_future.map_err(Validated::unwrap) {
+            // vulkano only chains fences that are behind an `Arc`, they never leave this thread
+            #[allow(clippy::arc_with_non_send_sync)]
+            Ok(value) => Some(Arc::new(value)),
+            Err(VulkanError::OutOfDate) => {
+                recreate_swapchain_after_presentation = true;
+                None
+            }
+            Err(e) => return Err(RenderError::vulkan("present the frame")(e)),
+        };
+
+        if let Some(stats) = self.frame_stats.as_mut() {
+            if let Some(summary) = stats.record_frame(Instant::now(), wait_time) {
+                info!("Frame stats: {}", summary);
+            }
+        }
+
+        if recreate_swapchain_after_presentation {
+            return self.resize_window(window);
+        }
+        Ok(())
+    }
+}
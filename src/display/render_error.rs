@@ -0,0 +1,93 @@
+use std::fmt;
+
+use softbuffer::SoftBufferError;
+use vulkano::{LoadingError, Validated, VulkanError};
+
+use super::device_selector::DeviceSelector;
+
+#[derive(Debug)]
+pub enum RenderError {
+    /// Vulkan loader library is missing, usually because no Vulkan driver is installed
+    NoVulkanLoader(LoadingError),
+    /// A Vulkan call failed, `action` describes what the renderer was doing at the time
+    Vulkan {
+        action: &'static str,
+        error: Validated<VulkanError>,
+    },
+    /// No device supports graphics and swapchains, `devices` lists every device that was found
+    NoSuitableDevice { devices: Vec<String> },
+    /// Devices can render, but none of them can present to the window's surface
+    SurfaceUnsupported { reason: String },
+    /// The selected device does not exist
+    DeviceNotFound {
+        selector: DeviceSelector,
+        devices: Vec<String>,
+    },
+    /// Something around a Vulkan call failed, like allocating GPU memory or writing to a mapped buffer
+    Resource {
+        action: &'static str,
+        reason: String,
+    },
+    /// The software renderer could not copy its frame to the window
+    SoftwareSurface(SoftBufferError),
+}
+impl RenderError {
+    /// Returns a closure that wraps a Vulkan error for `map_err`
+    pub fn vulkan<E: Into<Validated<VulkanError>>>(action: &'static str) -> impl Fn(E) -> Self {
+        move |error| RenderError::Vulkan {
+            action,
+            error: error.into(),
+        }
+    }
+
+    /// Returns a closure that wraps any other error for `map_err`
+    pub fn resource<E: fmt::Display>(action: &'static str) -> impl Fn(E) -> Self {
+        move |error| RenderError::Resource {
+            action,
+            reason: error.to_string(),
+        }
+    }
+}
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::NoVulkanLoader(e) => write!(
+                f,
+                "could not load Vulkan ({}), make sure a Vulkan capable graphics driver is installed",
+                e
+            ),
+            RenderError::Vulkan { action, error } => match error {
+                Validated::Error(e) => write!(f, "failed to {}: {}", action, e),
+                Validated::ValidationError(e) => {
+                    write!(f, "failed to {}, invalid Vulkan usage: {}", action, e)
+                }
+            },
+            RenderError::NoSuitableDevice { devices } if devices.is_empty() => {
+                write!(f, "no graphics device was found")
+            }
+            RenderError::NoSuitableDevice { devices } => write!(
+                f,
+                "none of the graphics devices support drawing to a window: {}",
+                devices.join(", ")
+            ),
+            RenderError::SurfaceUnsupported { reason } => {
+                write!(f, "the window can't be drawn to: {}", reason)
+            }
+            RenderError::DeviceNotFound { selector, devices } => write!(
+                f,
+                "{} does not exist, available devices are: {}",
+                selector,
+                devices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| format!("#{} {}", i, name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            RenderError::Resource { action, reason } => write!(f, "failed to {}: {}", action, reason),
+            RenderError::SoftwareSurface(e) => {
+                write!(f, "could not draw the software rendered frame to the window: {}", e)
+            }
+        }
+    }
+}
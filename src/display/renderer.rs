@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use unknown_game::{
+    internal::{Lighting, PostProcessing},
+    Game, Shading,
+};
+use winit::window::Window;
+
+use super::render_error::RenderError;
+
+/// Something that can draw the game to a window. The game loop only talks to renderers through this trait,
+/// so the Vulkan `RenderEngine` and the CPU `SoftwarePresenter` are interchangeable.
+pub trait Renderer {
+    /// Draws the current state of the game
+    fn display_next_frame(&mut self, game: &Game, window: Arc<Window>) -> Result<(), RenderError>;
+
+    /// Called after the window changed size or scale factor
+    fn resize_window(&mut self, window: Arc<Window>) -> Result<(), RenderError>;
+
+    /// Called after the canvas resolution changed
+    fn resize_canvas(&mut self, game: &Game) -> Result<(), RenderError>;
+
+    /// Turns colour effects of the canvas on or off
+    fn set_shading(&mut self, shading: Shading);
+
+    /// Picks the post processing passes that run after the canvas is drawn
+    fn set_post_processing(&mut self, post_processing: PostProcessing) -> Result<(), RenderError>;
+
+    /// Turns lighting of the canvas on or off and sets the resolution of its light map
+    fn set_lighting(&mut self, lighting: Lighting) -> Result<(), RenderError>;
+}
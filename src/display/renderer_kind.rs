@@ -0,0 +1,45 @@
+use std::fmt;
+
+/// Which renderer draws the game in the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RendererKind {
+    /// Draws on the GPU with Vulkan
+    #[default]
+    Vulkan,
+    /// Draws on the CPU and copies each frame to the window, for machines without a working Vulkan driver
+    Software,
+}
+impl RendererKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "vulkan" => Some(RendererKind::Vulkan),
+            "software" => Some(RendererKind::Software),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for RendererKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererKind::Vulkan => write!(f, "vulkan"),
+            RendererKind::Software => write!(f, "software"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RendererKind;
+
+    #[test]
+    fn test_parse_round_trips() {
+        for kind in [RendererKind::Vulkan, RendererKind::Software] {
+            assert_eq!(Some(kind), RendererKind::parse(&kind.to_string()));
+        }
+        assert_eq!(
+            Some(RendererKind::Software),
+            RendererKind::parse(" Software")
+        );
+        assert_eq!(None, RendererKind::parse("opengl"));
+    }
+}
@@ -0,0 +1,34 @@
+#version 460
+
+// one direction of a separable gaussian blur, `blur` in post_processing.rs
+
+// have to match the constants in post_processing.rs
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+const int SPREAD = 2;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D source;
+
+// `PostPushConstants`
+layout(push_constant) uniform Post {
+    ivec2 direction;
+    float intensity;
+} post;
+
+
+vec3 tap(ivec2 coord) {
+    return imageLoad(source, clamp(coord, ivec2(0), imageSize(source) - 1)).rgb;
+}
+
+
+void main() {
+    ivec2 coord = ivec2(gl_FragCoord.xy);
+    vec3 sum = tap(coord) * WEIGHTS[0];
+    for (int i = 1; i < 5; i++) {
+        ivec2 offset = post.direction * i * SPREAD;
+        sum += tap(coord + offset) * WEIGHTS[i];
+        sum += tap(coord - offset) * WEIGHTS[i];
+    }
+    f_color = vec4(sum, 1.);
+}
@@ -0,0 +1,14 @@
+#version 460
+
+// keeps only the light that emissive pixels give off, `bright_pass` in post_processing.rs
+
+layout(location = 0) out vec4 f_color;
+
+// scene drawn by shader.frag, alpha is emission
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D scene;
+
+
+void main() {
+    vec4 pixel = imageLoad(scene, ivec2(gl_FragCoord.xy));
+    f_color = vec4(pixel.rgb * pixel.a, 1.);
+}
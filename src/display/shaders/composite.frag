@@ -0,0 +1,21 @@
+#version 460
+
+// adds the glow on top of the scene and writes the final frame, `composite` in post_processing.rs
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0, rgba8) uniform readonly image2D scene;
+layout(set = 0, binding = 1, rgba8) uniform readonly image2D glow;
+
+// `PostPushConstants`
+layout(push_constant) uniform Post {
+    ivec2 direction;
+    float intensity;
+} post;
+
+
+void main() {
+    ivec2 coord = ivec2(gl_FragCoord.xy);
+    vec3 rgb = imageLoad(scene, coord).rgb + imageLoad(glow, coord).rgb * post.intensity;
+    f_color = vec4(rgb, 1.);
+}
@@ -0,0 +1,145 @@
+#version 460
+
+struct Resolution {
+    int width;
+    int height;
+};
+
+// have to match the flags of `Shading` in shading.rs
+const uint SHADING_VARIATION = 1;
+const uint SHADING_VELOCITY_TINT = 2;
+
+// rgb is the colour of the pixel, alpha how much light it gives off for the post processing passes
+layout(location = 0) out vec4 f_color;
+
+// two 16 bit cells per uint, the layout of a cell is described by `Cell` in shading.rs
+layout(set = 0, binding = 0) buffer CellBuffer {
+    highp uint cells[];
+};
+
+// light reaching the canvas as one RGBA8 texel per uint, worked out by `LightMap` on the CPU
+layout(set = 0, binding = 2) buffer LightMapBuffer {
+    highp uint light_map[];
+};
+
+// mapping from window pixels to canvas pixels, calculated by `ViewTransform` on the CPU
+layout(std140, set = 0, binding = 1) uniform View {
+    // window position of the top left corner of the canvas
+    vec2 origin;
+    // window pixels per canvas pixel
+    float scale;
+    // SHADING_ flags of the effects that are turned on
+    uint shading;
+    // canvas pixels per side of a light map texel, 0 when lighting is off
+    uint light_cell_size;
+} view;
+
+layout(std140, set = 1, binding = 0) uniform CanvasRes {
+    Resolution res;
+} canvas;
+
+
+vec3 hex_to_vec3(uint hex) {
+    float r = float((hex & 0xff0000) >> 16) / 255.;
+    float g = float((hex & 0x00ff00) >> 8) / 255.;
+    float b = float(hex & 0x0000ff) / 255.;
+    return vec3(r, g, b);
+}
+
+
+vec3 get_color(uint material) {
+    switch (material) {
+    case 1: // sand
+        return hex_to_vec3(0xd7c9aa);
+    case 2: // dirt
+        return hex_to_vec3(0x564138);
+    case 3: // blue
+        return hex_to_vec3(0x36C9C6);
+    case 4: // blue
+        return hex_to_vec3(0xC03221);
+    default:
+        return hex_to_vec3(0x000000);
+    }
+}
+
+
+// 1 for materials that glow, has to match `emissive` in the material properties
+// none of the current materials give off light
+float get_emission(uint material) {
+    switch (material) {
+    default:
+        return 0.;
+    }
+}
+
+
+// hue shift and lightness change a dot's colour can stray from its material colour by
+vec2 get_variation(uint material) {
+    switch (material) {
+    case 1: // sand
+        return vec2(0.03, 0.08);
+    case 2: // dirt
+        return vec2(0.02, 0.12);
+    case 3: // blue
+        return vec2(0.02, 0.05);
+    case 4: // orange
+        return vec2(0.03, 0.05);
+    default:
+        return vec2(0.);
+    }
+}
+
+
+uint get_cell(uint flat_coord) {
+    uint cell_pair = cells[flat_coord / 2];
+    return (cell_pair >> ((flat_coord % 2) * 16)) & 0xffff;
+}
+
+
+vec3 shade(vec3 rgb, uint material, uint seed, uint speed_level) {
+    if ((view.shading & SHADING_VARIATION) != 0) {
+        vec2 variation = get_variation(material);
+        // low 3 bits of the seed pick the hue shift and the high 3 bits the lightness, both from -1 to 1
+        float hue = float(seed & 7) * (2. / 7.) - 1.;
+        float lightness = float(seed >> 3) * (2. / 7.) - 1.;
+        rgb += hue * variation.x * vec3(1., 0., -1.);
+        rgb *= 1. + lightness * variation.y;
+    }
+    if ((view.shading & SHADING_VELOCITY_TINT) != 0 && material != 0) {
+        rgb += (vec3(1.) - rgb) * (float(speed_level) * 0.1);
+    }
+    return rgb;
+}
+
+
+vec3 get_light(ivec2 canvas_coord) {
+    uint light_width = (uint(canvas.res.width) + view.light_cell_size - 1) / view.light_cell_size;
+    uvec2 texel = uvec2(canvas_coord) / view.light_cell_size;
+    return unpackUnorm4x8(light_map[texel.y * light_width + texel.x]).rgb;
+}
+
+
+void main() {
+    ivec2 canvas_coord = ivec2(floor((gl_FragCoord.xy - view.origin) / view.scale));
+
+    if (canvas_coord.x < 0 || canvas_coord.x >= canvas.res.width) {
+        f_color = vec4(vec3(0.05), 0.);
+        return;
+    } else if (canvas_coord.y < 0 || canvas_coord.y >= canvas.res.height) {
+        f_color = vec4(vec3(0.05), 0.);
+        return;
+    }
+
+    int flat_coord = canvas_coord.x + (canvas.res.width * canvas_coord.y);
+    uint cell = get_cell(flat_coord);
+    uint material = cell & 0xff;
+    uint seed = (cell >> 8) & 0x3f;
+    uint speed_level = cell >> 14;
+
+    vec3 rgb = shade(get_color(material), material, seed, speed_level);
+    if (view.light_cell_size != 0) {
+        rgb *= get_light(canvas_coord);
+    }
+
+    f_color = vec4(rgb, get_emission(material));
+}
@@ -0,0 +1,156 @@
+use std::{num::NonZeroU32, sync::Arc};
+
+use raw_window_handle::{
+    AppKitDisplayHandle, AppKitWindowHandle, DisplayHandle, HandleError, HasDisplayHandle,
+    HasWindowHandle, RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+    Win32WindowHandle, WindowHandle, WindowsDisplayHandle, XcbDisplayHandle, XcbWindowHandle,
+    XlibDisplayHandle, XlibWindowHandle,
+};
+use raw_window_handle_05::{HasRawDisplayHandle, HasRawWindowHandle};
+use softbuffer::{Context, Surface};
+use unknown_game::{
+    internal::{profile_span, Lighting, PostProcessing, SoftwareRenderer},
+    Game, Shading,
+};
+use winit::window::Window;
+
+use crate::windowing::dpi;
+
+use super::{render_error::RenderError, renderer::Renderer};
+
+/// Hands winit's window handles to softbuffer, which expects a newer version of raw-window-handle than winit has
+#[derive(Clone)]
+pub struct SoftbufferWindow(Arc<Window>);
+impl HasWindowHandle for SoftbufferWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        let raw = match self.0.raw_window_handle() {
+            raw_window_handle_05::RawWindowHandle::Xlib(handle) => {
+                let mut new_handle = XlibWindowHandle::new(handle.window);
+                new_handle.visual_id = handle.visual_id;
+                RawWindowHandle::Xlib(new_handle)
+            }
+            raw_window_handle_05::RawWindowHandle::Xcb(handle) => {
+                let window = NonZeroU32::new(handle.window).ok_or(HandleError::Unavailable)?;
+                let mut new_handle = XcbWindowHandle::new(window);
+                new_handle.visual_id = NonZeroU32::new(handle.visual_id);
+                RawWindowHandle::Xcb(new_handle)
+            }
+            raw_window_handle_05::RawWindowHandle::Wayland(handle) => {
+                let surface =
+                    std::ptr::NonNull::new(handle.surface).ok_or(HandleError::Unavailable)?;
+                RawWindowHandle::Wayland(WaylandWindowHandle::new(surface))
+            }
+            raw_window_handle_05::RawWindowHandle::Win32(handle) => {
+                let hwnd = std::num::NonZeroIsize::new(handle.hwnd as isize)
+                    .ok_or(HandleError::Unavailable)?;
+                let mut new_handle = Win32WindowHandle::new(hwnd);
+                new_handle.hinstance = std::num::NonZeroIsize::new(handle.hinstance as isize);
+                RawWindowHandle::Win32(new_handle)
+            }
+            raw_window_handle_05::RawWindowHandle::AppKit(handle) => {
+                let ns_view =
+                    std::ptr::NonNull::new(handle.ns_view).ok_or(HandleError::Unavailable)?;
+                RawWindowHandle::AppKit(AppKitWindowHandle::new(ns_view))
+            }
+            _ => return Err(HandleError::NotSupported),
+        };
+        // the handle stays valid as long as the window, which this struct keeps alive
+        Ok(unsafe { WindowHandle::borrow_raw(raw) })
+    }
+}
+impl HasDisplayHandle for SoftbufferWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        let raw = match self.0.raw_display_handle() {
+            raw_window_handle_05::RawDisplayHandle::Xlib(handle) => RawDisplayHandle::Xlib(
+                XlibDisplayHandle::new(std::ptr::NonNull::new(handle.display), handle.screen),
+            ),
+            raw_window_handle_05::RawDisplayHandle::Xcb(handle) => RawDisplayHandle::Xcb(
+                XcbDisplayHandle::new(std::ptr::NonNull::new(handle.connection), handle.screen),
+            ),
+            raw_window_handle_05::RawDisplayHandle::Wayland(handle) => {
+                let display =
+                    std::ptr::NonNull::new(handle.display).ok_or(HandleError::Unavailable)?;
+                RawDisplayHandle::Wayland(WaylandDisplayHandle::new(display))
+            }
+            raw_window_handle_05::RawDisplayHandle::Windows(_) => {
+                RawDisplayHandle::Windows(WindowsDisplayHandle::new())
+            }
+            raw_window_handle_05::RawDisplayHandle::AppKit(_) => {
+                RawDisplayHandle::AppKit(AppKitDisplayHandle::new())
+            }
+            _ => return Err(HandleError::NotSupported),
+        };
+        // the display outlives every window on it
+        Ok(unsafe { DisplayHandle::borrow_raw(raw) })
+    }
+}
+
+/// Draws the game on the CPU with a `SoftwareRenderer` and copies each frame into the window
+pub struct SoftwarePresenter {
+    renderer: SoftwareRenderer,
+    surface: Surface<SoftbufferWindow, SoftbufferWindow>,
+}
+impl SoftwarePresenter {
+    pub fn new(window: Arc<Window>) -> Result<Self, RenderError> {
+        let window = SoftbufferWindow(window);
+        let context = Context::new(window.clone()).map_err(RenderError::SoftwareSurface)?;
+        let surface = Surface::new(&context, window).map_err(RenderError::SoftwareSurface)?;
+        Ok(SoftwarePresenter {
+            renderer: SoftwareRenderer::default(),
+            surface,
+        })
+    }
+}
+impl Renderer for SoftwarePresenter {
+    fn display_next_frame(&mut self, game: &Game, window: Arc<Window>) -> Result<(), RenderError> {
+        let size = window.inner_size();
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            // minimised, there is nothing to draw to
+            return Ok(());
+        };
+        let _span = profile_span("display_next_frame");
+
+        self.surface
+            .resize(width, height)
+            .map_err(RenderError::SoftwareSurface)?;
+        self.renderer.render_game(game, dpi::resolution(size));
+        let mut buffer = self
+            .surface
+            .buffer_mut()
+            .map_err(RenderError::SoftwareSurface)?;
+        // softbuffer pixels are 0RGB in a u32
+        for (pixel, rgba) in buffer
+            .iter_mut()
+            .zip(self.renderer.frame().pixels.chunks_exact(4))
+        {
+            *pixel = (rgba[0] as u32) << 16 | (rgba[1] as u32) << 8 | rgba[2] as u32;
+        }
+        buffer.present().map_err(RenderError::SoftwareSurface)
+    }
+
+    fn resize_window(&mut self, _window: Arc<Window>) -> Result<(), RenderError> {
+        // the surface and frame are sized to the window every time a frame is drawn
+        Ok(())
+    }
+
+    fn resize_canvas(&mut self, _game: &Game) -> Result<(), RenderError> {
+        // nothing is cached between frames
+        Ok(())
+    }
+
+    fn set_shading(&mut self, shading: Shading) {
+        self.renderer.set_shading(shading);
+    }
+
+    fn set_post_processing(&mut self, post_processing: PostProcessing) -> Result<(), RenderError> {
+        self.renderer.set_post_processing(post_processing);
+        Ok(())
+    }
+
+    fn set_lighting(&mut self, lighting: Lighting) -> Result<(), RenderError> {
+        self.renderer.set_lighting(lighting);
+        Ok(())
+    }
+}
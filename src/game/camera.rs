@@ -0,0 +1,162 @@
+use super::{math::Vec2, view_transform::ViewTransform, MAX_CAMERA_SCALE};
+
+/// View into the canvas, controlled by the player. Turn it into a `ViewTransform` to map between
+/// window pixels and canvas pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// Offset of the center of the view from the center of the canvas, in canvas pixels
+    pub offset: Vec2<f64>,
+    /// Multiplier on top of the scale that fits the whole canvas in the window
+    /// 1.0 shows the whole canvas, higher numbers zoom in
+    pub zoom: f64,
+}
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            offset: Vec2::new(0., 0.),
+            zoom: 1.,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Moves the view so the canvas follows a cursor that moved by `window_delta` window pixels
+    pub fn pan(&mut self, window_delta: Vec2<f64>, window_res: Vec2<f64>, canvas_res: Vec2<f64>) {
+        let scale = ViewTransform::new(window_res, canvas_res, self).scale;
+        self.offset -= window_delta / scale;
+        self.clamp_offset(canvas_res);
+    }
+
+    /// Zooms in (positive steps) or out (negative steps) while keeping the canvas point under
+    /// `window_pos` in place. Once zoomed in, every canvas pixel covers a whole number of window pixels.
+    pub fn zoom_at(
+        &mut self,
+        steps: i32,
+        window_pos: Vec2<f64>,
+        window_res: Vec2<f64>,
+        canvas_res: Vec2<f64>,
+    ) {
+        let fit = ViewTransform::fit_scale(window_res, canvas_res);
+        let view = ViewTransform::new(window_res, canvas_res, self);
+        let focus = view.window_to_canvas(window_pos);
+
+        let mut scale = view.scale;
+        for _ in 0..steps.abs() {
+            scale = Self::next_snapped_scale(scale, fit, steps > 0);
+        }
+        self.zoom = scale / fit;
+
+        // move view so the focus point is back under the cursor
+        self.offset = focus - (window_pos - window_res / 2.) / scale - canvas_res / 2.;
+        self.clamp_offset(canvas_res);
+    }
+
+    fn next_snapped_scale(scale: f64, fit: f64, zoom_in: bool) -> f64 {
+        // remove floating point noise so an integer scale is treated as one
+        let scale = if (scale - scale.round()).abs() < 1e-6 {
+            scale.round()
+        } else {
+            scale
+        };
+        let next = if zoom_in {
+            scale.floor() + 1.
+        } else {
+            scale.ceil() - 1.
+        };
+        next.clamp(fit, MAX_CAMERA_SCALE.max(fit))
+    }
+
+    fn clamp_offset(&mut self, canvas_res: Vec2<f64>) {
+        let half = canvas_res / 2.;
+        self.offset = self.offset.clamp(Some(half.to_negative()), Some(half));
+    }
+}
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Camera;
+    use crate::game::{math::Vec2, view_transform::ViewTransform};
+
+    fn window_to_canvas(camera: &Camera, pos: Vec2<f64>, win: Vec2<f64>, can: Vec2<f64>) -> Vec2<f64> {
+        ViewTransform::new(win, can, camera).window_to_canvas(pos)
+    }
+
+    fn assert_close(expected: Vec2<f64>, actual: Vec2<f64>) {
+        assert!(
+            (expected - actual).abs().pythagorean_theorem() < 1e-9,
+            "Expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    fn assert_scale(expected: f64, camera: &Camera, win: Vec2<f64>, can: Vec2<f64>) {
+        let actual = ViewTransform::new(win, can, camera).scale;
+        assert!(
+            (expected - actual).abs() < 1e-9,
+            "Expected scale {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_zoom_snaps_to_integer_scale() {
+        let win = Vec2::new(1000., 700.);
+        let can = Vec2::new(500., 500.);
+        let mut camera = Camera::new();
+
+        camera.zoom_at(1, win / 2., win, can);
+        assert_scale(2., &camera, win, can);
+
+        camera.zoom_at(2, win / 2., win, can);
+        assert_scale(4., &camera, win, can);
+
+        camera.zoom_at(-1, win / 2., win, can);
+        assert_scale(3., &camera, win, can);
+    }
+
+    #[test]
+    fn test_zoom_out_stops_at_whole_canvas() {
+        let win = Vec2::new(1000., 700.);
+        let can = Vec2::new(500., 500.);
+        let mut camera = Camera::new();
+
+        camera.zoom_at(3, win / 2., win, can);
+        camera.zoom_at(-10, win / 2., win, can);
+        assert_eq!(1., camera.zoom);
+    }
+
+    #[test]
+    fn test_zoom_keeps_point_under_cursor() {
+        let win = Vec2::new(1000., 1000.);
+        let can = Vec2::new(500., 500.);
+        let cursor = Vec2::new(300., 420.);
+        let mut camera = Camera::new();
+
+        let before = window_to_canvas(&camera, cursor, win, can);
+        camera.zoom_at(2, cursor, win, can);
+        assert_close(before, window_to_canvas(&camera, cursor, win, can));
+    }
+
+    #[test]
+    fn test_pan_moves_canvas_with_cursor() {
+        let win = Vec2::new(1000., 1000.);
+        let can = Vec2::new(500., 500.);
+        let mut camera = Camera {
+            offset: Vec2::new(0., 0.),
+            zoom: 2.,
+        };
+
+        let grabbed = window_to_canvas(&camera, Vec2::new(500., 500.), win, can);
+        camera.pan(Vec2::new(40., -20.), win, can);
+        assert_close(grabbed, window_to_canvas(&camera, Vec2::new(540., 480.), win, can));
+    }
+}
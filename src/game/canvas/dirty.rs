@@ -0,0 +1,192 @@
+use std::ops::Range;
+
+/// Rectangle of grid cells whose contents changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+impl DirtyRect {
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Ranges of flat cell indices covered by each row of the rectangle, for a grid that is `grid_width` wide
+    pub fn row_spans(&self, grid_width: usize) -> impl Iterator<Item = Range<usize>> + '_ {
+        (self.y..self.y + self.height).map(move |y| {
+            let start = y * grid_width + self.x;
+            start..start + self.width
+        })
+    }
+}
+
+/// Remembers which cells were written since the grid was last marked clean.
+/// Writes are only recorded, whether a cell actually changed is decided by comparing it to the clean cells,
+/// so clearing the grid and writing the same dots back leaves nothing dirty.
+pub struct DirtyTracker {
+    width: usize,
+    /// Packed cells as they were when the grid was last marked clean
+    clean: Vec<u16>,
+    /// Flat indices written since then, may contain duplicates
+    touched: Vec<usize>,
+    /// Set instead of growing `touched` past the number of cells, when nobody marks the grid clean
+    all_touched: bool,
+}
+impl DirtyTracker {
+    pub fn new(width: usize, clean: Vec<u16>) -> Self {
+        Self {
+            width,
+            clean,
+            touched: Vec::new(),
+            all_touched: false,
+        }
+    }
+
+    pub fn touch(&mut self, index: usize) {
+        if self.all_touched {
+            return;
+        }
+        if self.touched.len() >= self.clean.len() {
+            self.all_touched = true;
+            self.touched.clear();
+            return;
+        }
+        self.touched.push(index);
+    }
+
+    /// Rectangles around every cell whose current value, as given by `current`, differs from the clean one.
+    /// Each row's changes become one span, and spans of neighbouring rows that overlap are merged.
+    pub fn dirty_rects(&self, current: impl Fn(usize) -> u16) -> Vec<DirtyRect> {
+        let mut dirty: Vec<usize> = if self.all_touched {
+            (0..self.clean.len()).collect()
+        } else {
+            self.touched.clone()
+        };
+        dirty.sort_unstable();
+        dirty.dedup();
+        dirty.retain(|i| current(*i) != self.clean[*i]);
+
+        let mut rects: Vec<DirtyRect> = Vec::new();
+        let mut cells = dirty.into_iter().peekable();
+        while let Some(first) = cells.next() {
+            let y = first / self.width;
+            let mut last = first;
+            while let Some(next) = cells.next_if(|i| i / self.width == y) {
+                last = next;
+            }
+            let (start_x, end_x) = (first % self.width, last % self.width + 1);
+
+            match rects.last_mut() {
+                Some(rect)
+                    if rect.y + rect.height == y
+                        && start_x < rect.x + rect.width
+                        && rect.x < end_x =>
+                {
+                    let merged_x = rect.x.min(start_x);
+                    rect.width = (rect.x + rect.width).max(end_x) - merged_x;
+                    rect.x = merged_x;
+                    rect.height += 1;
+                }
+                _ => rects.push(DirtyRect {
+                    x: start_x,
+                    y,
+                    width: end_x - start_x,
+                    height: 1,
+                }),
+            }
+        }
+        rects
+    }
+
+    /// Takes the current cells as the new clean state
+    pub fn mark_clean(&mut self, current: impl Fn(usize) -> u16) {
+        if self.all_touched {
+            for (i, byte) in self.clean.iter_mut().enumerate() {
+                *byte = current(i);
+            }
+        } else {
+            for i in self.touched.iter() {
+                self.clean[*i] = current(*i);
+            }
+        }
+        self.touched.clear();
+        self.all_touched = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirtyRect, DirtyTracker};
+
+    #[test]
+    fn test_rewriting_same_bytes_is_clean() {
+        let mut tracker = DirtyTracker::new(4, vec![0, 1, 0, 0]);
+        tracker.touch(1);
+        tracker.touch(1);
+        assert!(tracker.dirty_rects(|i| [0, 1, 0, 0][i]).is_empty());
+    }
+
+    #[test]
+    fn test_changes_are_merged_into_rects() {
+        let mut tracker = DirtyTracker::new(4, vec![0; 16]);
+        let mut current = [0; 16];
+        for i in [1, 2, 6, 15] {
+            current[i] = 1;
+            tracker.touch(i);
+        }
+
+        assert_eq!(
+            vec![
+                DirtyRect {
+                    x: 1,
+                    y: 0,
+                    width: 2,
+                    height: 2
+                },
+                DirtyRect {
+                    x: 3,
+                    y: 3,
+                    width: 1,
+                    height: 1
+                },
+            ],
+            tracker.dirty_rects(|i| current[i])
+        );
+
+        tracker.mark_clean(|i| current[i]);
+        assert!(tracker.dirty_rects(|i| current[i]).is_empty());
+    }
+
+    #[test]
+    fn test_too_many_writes_fall_back_to_checking_everything() {
+        let mut tracker = DirtyTracker::new(2, vec![0; 4]);
+        for _ in 0..10 {
+            tracker.touch(0);
+        }
+        let current = [0, 0, 0, 1];
+        assert_eq!(
+            vec![DirtyRect {
+                x: 1,
+                y: 1,
+                width: 1,
+                height: 1
+            }],
+            tracker.dirty_rects(|i| current[i])
+        );
+        tracker.mark_clean(|i| current[i]);
+        assert!(tracker.dirty_rects(|i| current[i]).is_empty());
+    }
+
+    #[test]
+    fn test_row_spans() {
+        let rect = DirtyRect {
+            x: 1,
+            y: 2,
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(vec![9..11, 13..15], rect.row_spans(4).collect::<Vec<_>>());
+    }
+}
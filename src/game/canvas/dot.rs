@@ -1,11 +1,10 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use crate::game::{
     material::Material,
-    math::rng,
-    Vec2, GRAVITY,
+    Vec2,
 };
-use super::{DotIdGen, DotId};
+use super::DotId;
 
 /// Special version of `Dot` that contains less data
 #[derive(Debug, Clone, Copy)]
@@ -58,11 +57,9 @@ impl Dot {
         self.velocity * delta_time.as_secs_f64() + self.position
     }
 
-    pub fn find_next_velocity(&self, delta_time: Duration) -> Vec2<f64> {
+    pub fn find_next_velocity(&self, delta_time: Duration, gravity: Vec2<f64>) -> Vec2<f64> {
         let real_drag = self.velocity * 2. * self.material.properties().drag;
-        let accel = GRAVITY - real_drag;
-        let new_velocity = self.velocity + (accel * delta_time.as_secs_f64());
-
-        new_velocity
+        let accel = gravity - real_drag;
+        self.velocity + (accel * delta_time.as_secs_f64())
     }
 }
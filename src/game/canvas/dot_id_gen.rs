@@ -4,6 +4,7 @@ pub type DotId = u32;
 pub enum DotIdGenError {
     RanOutOfIds,
     DeletedNonExistentId,
+    IdInUse,
 }
 
 pub struct DotIdGen {
@@ -36,6 +37,24 @@ impl DotIdGen {
         }
     }
 
+    /// Takes a specific id instead of the next free one, used to bring back a dot that was deleted
+    pub fn claim_id(&mut self, id: DotId) -> Result<(), DotIdGenError> {
+        if let Some(i) = self.deleted_ids.iter().position(|deleted| *deleted == id) {
+            self.deleted_ids.swap_remove(i);
+            return Ok(());
+        }
+        match self.largest_id {
+            Some(largest_id) if id <= largest_id => Err(DotIdGenError::IdInUse),
+            _ => {
+                // ids skipped over are still free to be used later
+                let first_skipped = self.largest_id.map_or(0, |largest_id| largest_id + 1);
+                self.deleted_ids.extend(first_skipped..id);
+                self.largest_id = Some(id);
+                Ok(())
+            }
+        }
+    }
+
     pub fn mark_id_deleted(&mut self, id: DotId) -> Result<(), DotIdGenError> {
         if let Some(largest_id) = self.largest_id {
             if id <= largest_id {
@@ -51,6 +70,8 @@ impl DotIdGen {
 }
 
 #[cfg(test)]
+// the error tests spell out the expected outcome with `assert!(true)` and `assert!(false)`
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::{DotId, DotIdGenError, DotIdGen};
 
@@ -114,6 +135,40 @@ mod tests {
         assert_eq!(id_gen.new_id().unwrap(), 30);
     }
 
+    #[test]
+    fn test_claim_deleted_id() {
+        let mut id_gen = DotIdGen {
+            largest_id: Some(80),
+            deleted_ids: vec![30, 12],
+        };
+        id_gen.claim_id(30).unwrap();
+        assert_eq!(id_gen.deleted_ids, vec![12]);
+    }
+
+    #[test]
+    fn test_cant_claim_id_in_use() {
+        let mut id_gen = DotIdGen {
+            largest_id: Some(80),
+            deleted_ids: vec![30],
+        };
+        match id_gen.claim_id(31) {
+            Err(DotIdGenError::IdInUse) => (),
+            result => assert!(false, "Claimed an id that is in use -> {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_claim_id_past_largest_frees_skipped_ids() {
+        let mut id_gen = DotIdGen {
+            largest_id: Some(2),
+            deleted_ids: Vec::new(),
+        };
+        id_gen.claim_id(5).unwrap();
+        assert_eq!(id_gen.largest_id, Some(5));
+        assert_eq!(id_gen.deleted_ids, vec![3, 4]);
+        assert_eq!(id_gen.new_id().unwrap(), 4);
+    }
+
     #[test]
     fn test_cant_delete_id_before_any_are_made() {
         let mut id_gen = DotIdGen {
@@ -1,20 +1,86 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, ops::Range};
 
-use crate::{game::{material::Material, math::Vec2}, rendering::glsl_types::Resolution};
+use crate::{
+    game::{material::Material, math::Vec2},
+    rendering::{glsl_types::Resolution, shading::Cell},
+};
 
-use super::{dot::{CanvasDot, CollisionReport, DotModification}, CanvasError, Dot, RayPoint, TriDirection};
+use super::{
+    dirty::{DirtyRect, DirtyTracker},
+    dot::{CanvasDot, CollisionReport, DotModification},
+    CanvasError, Dot, RayPoint, TriDirection,
+};
 
-pub struct Grid(Vec<Vec<Option<CanvasDot>>>);
+fn material_byte(maybe_dot: &Option<CanvasDot>) -> u8 {
+    maybe_dot.map_or(Material::EmptySpace as u8, |dot| dot.material as u8)
+}
+
+fn packed_cell(maybe_dot: &Option<CanvasDot>) -> u16 {
+    Cell::from_dot(maybe_dot).pack()
+}
+
+pub struct Grid {
+    rows: Vec<Vec<Option<CanvasDot>>>,
+    dirty: DirtyTracker,
+}
 impl Grid {
-    pub fn new_from(inner_grid: Vec<Vec<Option<CanvasDot>>>) -> Self {
-        Self(inner_grid)
+    pub(crate) fn new_from(inner_grid: Vec<Vec<Option<CanvasDot>>>) -> Self {
+        let width = inner_grid.first().map_or(0, |row| row.len());
+        let clean = inner_grid.iter().flatten().map(packed_cell).collect();
+        Self {
+            rows: inner_grid,
+            dirty: DirtyTracker::new(width, clean),
+        }
+    }
+
+    pub fn new_empty(resolution: Resolution) -> Self {
+        let Resolution { height, width } = resolution;
+        Self::new_from(
+            (0..height)
+                .map(|_| (0..width).map(|_| None).collect())
+                .collect(),
+        )
     }
 
     pub fn iter_materials_as_bytes<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
-        self.0
+        self.rows.iter().flatten().map(material_byte)
+    }
+
+    /// Every cell packed the way the canvas buffer stores it, see `Cell`
+    pub fn iter_cells_as_bytes<'a>(&'a self) -> impl Iterator<Item = u8> + 'a {
+        self.rows
             .iter()
             .flatten()
-            .map(|maybe_dot| maybe_dot.map_or(Material::EmptySpace as u8, |dot| dot.material as u8))
+            .flat_map(|maybe_dot| packed_cell(maybe_dot).to_le_bytes())
+    }
+
+    /// Packed cells of a span of flat cell indices within a single row
+    pub fn iter_span_cells_as_bytes<'a>(&'a self, span: Range<usize>) -> impl Iterator<Item = u8> + 'a {
+        let width = self.width();
+        let x = span.start % width;
+        self.rows[span.start / width][x..x + span.len()]
+            .iter()
+            .flat_map(|maybe_dot| packed_cell(maybe_dot).to_le_bytes())
+    }
+
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+
+    fn packed_cell_at(rows: &[Vec<Option<CanvasDot>>], index: usize) -> u16 {
+        let width = rows[0].len();
+        packed_cell(&rows[index / width][index % width])
+    }
+
+    /// Areas whose cells changed since the grid was last marked clean
+    pub fn dirty_rects(&self) -> Vec<DirtyRect> {
+        self.dirty.dirty_rects(|i| Self::packed_cell_at(&self.rows, i))
+    }
+
+    /// Call once the changes have been picked up, e.g. after they were uploaded to the GPU
+    pub(crate) fn mark_clean(&mut self) {
+        let rows = &self.rows;
+        self.dirty.mark_clean(|i| Self::packed_cell_at(rows, i));
     }
 
     pub fn get(&self, coord: Vec2<isize>) -> Result<&Option<CanvasDot>, CanvasError> {
@@ -23,31 +89,37 @@ impl Grid {
         } else {
             Vec2::new(coord.x as usize, coord.y as usize)
         };
-        Ok(self
-            .0
+        self
+            .rows
             .get(coord.y)
             .ok_or(CanvasError::CoordOutOfBounds)?
             .get(coord.x)
-            .ok_or(CanvasError::CoordOutOfBounds)?)
+            .ok_or(CanvasError::CoordOutOfBounds)
     }
 
-    pub fn get_mut(&mut self, coord: Vec2<isize>) -> Result<&mut Option<CanvasDot>, CanvasError> {
+    pub(crate) fn get_mut(&mut self, coord: Vec2<isize>) -> Result<&mut Option<CanvasDot>, CanvasError> {
         let coord = if coord.x < 0 || coord.y < 0 {
             return Err(CanvasError::CoordOutOfBounds);
         } else {
             Vec2::new(coord.x as usize, coord.y as usize)
         };
-        Ok(self
-            .0
+        let width = self.width();
+        let cell = self
+            .rows
             .get_mut(coord.y)
             .ok_or(CanvasError::CoordOutOfBounds)?
             .get_mut(coord.x)
-            .ok_or(CanvasError::CoordOutOfBounds)?)
+            .ok_or(CanvasError::CoordOutOfBounds)?;
+        // the cell may be changed through the reference
+        self.dirty.touch(coord.y * width + coord.x);
+        Ok(cell)
     }
 
-    pub fn clear(&mut self) {
-        for maybe_dot in self.0.iter_mut().flatten() {
-            *maybe_dot = None;
+    pub(crate) fn clear(&mut self) {
+        for (i, maybe_dot) in self.rows.iter_mut().flatten().enumerate() {
+            if maybe_dot.take().is_some() {
+                self.dirty.touch(i);
+            }
         }
     }
 
@@ -56,7 +128,7 @@ impl Grid {
         ray_start: Vec2<f64>,
         direction_in_degrees: f64,
         resolution: Resolution,
-    ) -> VecDeque<RayPoint> {
+    ) -> VecDeque<RayPoint<'_>> {
         let ray_end = Vec2::new(
             direction_in_degrees.to_radians().cos() * resolution.width as f64 + ray_start.x,
             direction_in_degrees.to_radians().sin() * resolution.height as f64 + ray_start.y,
@@ -72,7 +144,7 @@ impl Grid {
         ray_start: Vec2<f64>,
         ray_end: Vec2<f64>,
         resolution: Resolution,
-    ) -> (VecDeque<RayPoint>, Option<TriDirection>) {
+    ) -> (VecDeque<RayPoint<'_>>, Option<TriDirection>) {
         let mut path = VecDeque::new();
 
         let diff = ray_end - ray_start;
@@ -132,15 +204,13 @@ impl Grid {
             if coord.y < 0 {
                 return (path, Some(TriDirection::Vertical));
             }
-            match self.get(coord.into()) {
+            match self.get(coord) {
                 Ok(dot_maybe) => {
-                    if let None = collision_direction {
-                        if let Some(_) = dot_maybe {
-                            collision_direction = Some(direction);
-                        }
+                    if collision_direction.is_none() && dot_maybe.is_some() {
+                        collision_direction = Some(direction);
                     }
                     path.push_back(RayPoint {
-                        coord: coord.into(),
+                        coord,
                         dot: dot_maybe.as_ref(),
                     });
                 }
@@ -161,7 +231,26 @@ impl Grid {
         (path, collision_direction)
     }
 
-    pub fn check_for_dot_collision(
+    /// Share of light that makes it from `from` to `to` through the dots in between, 1 when nothing is in the way.
+    /// The cells at both ends don't block anything, so the side of a dot facing a light is lit.
+    pub fn transmittance(&self, from: Vec2<f64>, to: Vec2<f64>, resolution: Resolution) -> f64 {
+        if from.to_rounded_isize() == to.to_rounded_isize() {
+            return 1.;
+        }
+        let (ray, _) = self.cast_ray(from, to, resolution);
+        let mut transmittance = 1.;
+        for point in ray.iter().take(ray.len().saturating_sub(1)) {
+            if let Some(dot) = point.dot {
+                transmittance *= 1. - dot.material.properties().opacity;
+                if transmittance <= 0. {
+                    return 0.;
+                }
+            }
+        }
+        transmittance
+    }
+
+    pub(crate) fn check_for_dot_collision(
         &self,
         this_dot: &Dot,
         next_pos: Vec2<f64>,
@@ -236,5 +325,181 @@ impl Grid {
             }
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        game::{
+            canvas::{dirty::DirtyRect, Canvas, DotId},
+            material::Material,
+            math::Vec2,
+        },
+        rendering::glsl_types::Resolution,
+    };
+
+    const RESOLUTION: Resolution = Resolution {
+        width: 10,
+        height: 10,
+    };
 
+    /// Canvas with a sand dot at each position, returns the ids in the same order
+    fn setup_canvas(positions: &[Vec2<f64>]) -> (Canvas, Vec<DotId>) {
+        let mut canvas = Canvas::new(RESOLUTION);
+        let ids = positions
+            .iter()
+            .map(|position| {
+                canvas
+                    .spawn_dot(Material::Sand, *position, Vec2::new(0., 0.))
+                    .expect("Test data was not setup correctly")
+            })
+            .collect();
+        canvas.write_dots_to_grid();
+        (canvas, ids)
+    }
+
+    #[test]
+    fn test_cast_finds_end_point() {
+        let ray_start = Vec2::new(5., 5.);
+        let ray_end = Vec2::new(6., 5.);
+        let (canvas, ids) = setup_canvas(&[ray_start, ray_end]);
+        let path = canvas.grid.cast_ray(ray_start, ray_end, RESOLUTION).0;
+
+        assert_eq!(1, path.len(), "Ray cast did not stop at end point:\n{:?}", path);
+        assert_eq!(ids[1], path[0].dot.unwrap().id, "Could not find correct ray end dot");
+        assert_eq!(Vec2::new(6, 5), path[0].coord, "End point was not the correct coordinate");
+    }
+
+    #[test]
+    fn test_cast_includes_empty_points() {
+        let ray_start = Vec2::new(4., 5.);
+        let ray_end = Vec2::new(7., 5.);
+        let (canvas, _) = setup_canvas(&[ray_start, ray_end]);
+        let path = canvas.grid.cast_ray(ray_start, ray_end, RESOLUTION).0;
+
+        assert_eq!(3, path.len());
+        assert!(path[0].dot.is_none(), "First point was not empty:\n{:?}", path[0]);
+        assert!(path[1].dot.is_none(), "Second point was not empty:\n{:?}", path[1]);
+    }
+
+    #[test]
+    fn test_cast_includes_dots_in_middle_of_ray_and_empty_end_point() {
+        let ray_start = Vec2::new(4., 5.);
+        let ray_end = Vec2::new(7., 5.);
+        let (canvas, ids) = setup_canvas(&[ray_start, Vec2::new(5., 5.), Vec2::new(6., 5.)]);
+        let path = canvas.grid.cast_ray(ray_start, ray_end, RESOLUTION).0;
+
+        assert_eq!(3, path.len());
+        assert_eq!(ids[1], path[0].dot.unwrap().id, "First middle dot had incorrect id");
+        assert_eq!(Vec2::new(5, 5), path[0].coord);
+        assert_eq!(ids[2], path[1].dot.unwrap().id, "Second middle dot had incorrect id");
+        assert_eq!(Vec2::new(6, 5), path[1].coord);
+        assert!(path[2].dot.is_none(), "End point was not empty:\n{:?}", path[2]);
+    }
+
+    #[test]
+    fn test_cast_between_dots_doesnt_capture_them() {
+        let ray_start = Vec2::new(4., 5.);
+        let ray_end = Vec2::new(6., 5.);
+        let (canvas, _) = setup_canvas(&[ray_start, Vec2::new(5., 4.), Vec2::new(5., 6.)]);
+        let path = canvas.grid.cast_ray(ray_start, ray_end, RESOLUTION).0;
+
+        assert_eq!(2, path.len());
+        assert!(
+            path.iter().all(|point| point.dot.is_none()),
+            "Ray cast found a dot when it should have found empty space:\n{:?}",
+            path
+        );
+    }
+
+    #[test]
+    fn test_cast_to_edge() {
+        // n = nothing (null)
+        // d = dot
+        // * = starting point
+        // # = wall
+        // *------> = 0 degrees
+        //
+        // # # # # #
+        // # n d
+        // #   *
+        let ray_start = Vec2::new(1., 1.);
+        let (canvas, _) = setup_canvas(&[Vec2::new(1., 0.)]);
+        let path = canvas.grid.cast_ray_to_edge(ray_start, 247.5, RESOLUTION);
+
+        assert_eq!(2, path.len(), "PATH: {:?}", path);
+        assert_eq!(Vec2::new(1, 0), path[0].coord, "First dot point had incorrect coordinates");
+        assert!(
+            path[1].dot.is_none(),
+            "Ray cast should have captured the dot as the first point in the ray, not the second:\n{:?}",
+            path[1]
+        );
+    }
+
+    #[test]
+    fn test_only_changed_cells_are_dirty() {
+        let mut canvas = Canvas::new(Resolution {
+            width: 8,
+            height: 8,
+        });
+        canvas
+            .spawn_dot(Material::Sand, Vec2::new(2., 3.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas
+            .spawn_dot(Material::Dirt, Vec2::new(6., 6.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas.write_dots_to_grid();
+        assert_eq!(2, canvas.grid.dirty_rects().len());
+        canvas.grid.mark_clean();
+
+        // rebuilding the grid with the same dots changes nothing
+        canvas.write_dots_to_grid();
+        assert!(canvas.grid.dirty_rects().is_empty());
+
+        canvas.erase_circle_of_dots(0., Vec2::new(6., 6.));
+        canvas.write_dots_to_grid();
+        assert_eq!(
+            vec![DirtyRect {
+                x: 6,
+                y: 6,
+                width: 1,
+                height: 1
+            }],
+            canvas.grid.dirty_rects()
+        );
+        assert_eq!(
+            vec![0, 0, 0, 0],
+            canvas
+                .grid
+                .iter_span_cells_as_bytes(54..56)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_transmittance_through_dots() {
+        let resolution = Resolution {
+            width: 8,
+            height: 8,
+        };
+        let mut canvas = Canvas::new(resolution);
+        canvas
+            .spawn_dot(Material::Dirt, Vec2::new(3., 0.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas
+            .spawn_dot(Material::Blue, Vec2::new(0., 3.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas.write_dots_to_grid();
+        let grid = &canvas.grid;
+        let blue_opacity = Material::Blue.properties().opacity;
+
+        // dirt blocks everything behind it, but not itself
+        assert_eq!(0., grid.transmittance(Vec2::new(0., 0.), Vec2::new(6., 0.), resolution));
+        assert_eq!(1., grid.transmittance(Vec2::new(0., 0.), Vec2::new(3., 0.), resolution));
+        assert_eq!(
+            1. - blue_opacity,
+            grid.transmittance(Vec2::new(0., 0.), Vec2::new(0., 6.), resolution)
+        );
+        assert_eq!(1., grid.transmittance(Vec2::new(1., 1.), Vec2::new(6., 6.), resolution));
+    }
 }
@@ -0,0 +1,36 @@
+use crate::game::math::Vec2;
+
+// reach in canvas pixels of lights placed in the world
+const POINT_LIGHT_RADIUS: f64 = 48.;
+// warm white, a bit like a torch
+const POINT_LIGHT_COLOR: [f32; 3] = [1., 0.9, 0.7];
+
+/// Light source in the world that lights every cell within `radius` it has a line of sight to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Vec2<f64>,
+    /// Linear RGB, multiplied with the colour of everything the light reaches
+    pub color: [f32; 3],
+    pub radius: f64,
+    /// Brightness right at the light, fades out towards `radius`
+    pub intensity: f32,
+}
+impl PointLight {
+    pub fn new(position: Vec2<f64>) -> Self {
+        Self {
+            position,
+            color: POINT_LIGHT_COLOR,
+            radius: POINT_LIGHT_RADIUS,
+            intensity: 1.,
+        }
+    }
+
+    /// Brightness at `distance` from the light before anything blocks it, 0 from `radius` on
+    pub fn falloff(&self, distance: f64) -> f32 {
+        if distance >= self.radius {
+            return 0.;
+        }
+        let remaining = (1. - distance / self.radius) as f32;
+        self.intensity * remaining * remaining
+    }
+}
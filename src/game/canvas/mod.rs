@@ -1,17 +1,29 @@
-use std::collections::{hash_map::OccupiedError, HashMap, VecDeque};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    hash::{BuildHasherDefault, Hasher},
+    time::Duration,
+};
 
-use crate::rendering::glsl_types::Resolution;
+use log::{debug, warn};
+
+use crate::{profiler, rendering::glsl_types::Resolution};
+pub mod dirty;
 pub mod dot;
 mod grid;
+pub mod light;
 mod dot_id_gen;
 
 use self::dot_id_gen::DotIdGen;
-use super::{material::Material, Vec2};
+use super::{config::GameConfig, material::Material, math::Fnv1a, Vec2};
 pub use dot::Dot;
 pub use grid::Grid;
+pub use light::PointLight;
 use dot::CanvasDot;
 pub use dot_id_gen::DotId;
 mod physics;
+mod resize;
+pub use resize::{Anchor, ResizeMode, ResizeReport};
 
 #[derive(Debug)]
 enum TriDirection {
@@ -25,50 +37,122 @@ pub enum CanvasError {
     CoordOutOfBounds,
 }
 
+/// Why `Canvas::spawn_dot` could not add a dot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnError {
+    /// Another dot is already on the pixel
+    CellOccupied(Vec2<isize>),
+    /// The new id already belongs to a dot, so the id generator and the palette disagree
+    IdCollision(DotId),
+    /// The pixel is outside of the canvas
+    OutOfBounds(Vec2<isize>),
+    /// Every id is taken by a dot
+    IdsExhausted,
+}
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::CellOccupied(coord) => {
+                write!(f, "pixel ({}, {}) already has a dot", coord.x, coord.y)
+            }
+            SpawnError::IdCollision(id) => write!(f, "id {} is already used by another dot", id),
+            SpawnError::OutOfBounds(coord) => {
+                write!(f, "pixel ({}, {}) is outside of the canvas", coord.x, coord.y)
+            }
+            SpawnError::IdsExhausted => write!(f, "every dot id is in use"),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct RayPoint<'a> {
     pub coord: Vec2<isize>,
     pub dot: Option<&'a CanvasDot>,
 }
 
+// hasher that never changes, so dots are always visited in the same order and physics is repeatable
+type Palette = HashMap<DotId, Dot, BuildHasherDefault<Fnv1a>>;
+
 pub struct Canvas {
-    pub resolution: Resolution,
-    pub grid: Grid,
-    palette: HashMap<DotId, Dot>,
+    pub(crate) resolution: Resolution,
+    pub(crate) grid: Grid,
+    /// Lights placed in the world, they don't take part in physics
+    pub(crate) lights: Vec<PointLight>,
+    palette: Palette,
     dot_id_gen: DotIdGen,
 }
 impl Canvas {
     pub fn new(resolution: Resolution) -> Self {
-        let Resolution { height, width } = resolution;
         Self {
             resolution,
-            grid: Grid::new_from((0..height)
-                .map(|_| (0..width).map(|_| None).collect())
-                .collect()),
-            palette: HashMap::new(),
+            grid: Grid::new_empty(resolution),
+            lights: Vec::new(),
+            palette: Palette::default(),
             dot_id_gen: DotIdGen::new(),
         }
     }
 
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Cells of the canvas as of the last step, renderers read it and queries go through it
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn lights(&self) -> &[PointLight] {
+        &self.lights
+    }
+
+    /// Moves every dot forward by `delta_time` and writes the result to the grid
+    pub fn step(&mut self, delta_time: Duration, config: &GameConfig) {
+        self.calculate_physics(delta_time, config);
+        self.write_dots_to_grid();
+    }
+
+    /// Adds a dot on the pixel `position` rounds to and returns its id.
+    /// The pixel is taken right away, so spawning twice on it fails without writing the grid in between.
     pub fn spawn_dot(
         &mut self,
         material: Material,
         position: Vec2<f64>,
         velocity: Vec2<f64>,
-    ) -> Result<(), OccupiedError<DotId, Dot>> {
-        let id = self.dot_id_gen.new_id().expect("Ran out of ids");
+    ) -> Result<DotId, SpawnError> {
+        let coord = position.to_rounded_isize();
+        match self.grid.get(coord) {
+            Ok(None) => (),
+            Ok(Some(_)) => return Err(SpawnError::CellOccupied(coord)),
+            Err(CanvasError::CoordOutOfBounds) => return Err(SpawnError::OutOfBounds(coord)),
+        }
+        let id = self
+            .dot_id_gen
+            .new_id()
+            .map_err(|_| SpawnError::IdsExhausted)?;
         let dot = Dot {
             id,
             material,
             velocity,
             position,
         };
-        self.palette.try_insert(id, dot)?;
+        match self.palette.entry(id) {
+            Entry::Occupied(_) => return Err(SpawnError::IdCollision(id)),
+            Entry::Vacant(entry) => entry.insert(dot),
+        };
+        *self.grid.get_mut(coord).unwrap() = Some(CanvasDot::from(&dot));
 
-        Ok(())
+        Ok(id)
     }
 
-    pub fn spawn_circle_of_dots(&mut self, radius: f64, coord: Vec2<f64>, material: Material) {
+    /// Fills empty pixels within `radius` of `coord` with new dots and returns the dots that were spawned.
+    /// Stops early when the canvas runs out of dot ids.
+    pub fn spawn_circle_of_dots(
+        &mut self,
+        radius: f64,
+        coord: Vec2<f64>,
+        material: Material,
+    ) -> Vec<Dot> {
+        let mut spawned = Vec::new();
         let top_left = (coord - radius)
             .clamp_to_resolution(self.resolution)
             .to_rounded_isize();
@@ -85,19 +169,106 @@ impl Canvas {
                     continue;
                 }
 
-                // dot can't spawn if another dot is already there
-                if self.grid.get(point).unwrap().is_some() {
+                match self.spawn_dot(material, point.into_f64(), Vec2::new(0., 0.)) {
+                    Ok(id) => spawned.push(self.palette[&id]),
+                    // dot can't spawn if another dot is already there
+                    Err(SpawnError::CellOccupied(_) | SpawnError::OutOfBounds(_)) => (),
+                    Err(e @ SpawnError::IdCollision(_)) => warn!("Skipped a dot of the brush, {}", e),
+                    Err(e @ SpawnError::IdsExhausted) => {
+                        warn!("Stopped the brush, {}", e);
+                        return spawned;
+                    }
+                }
+            }
+        }
+        spawned
+    }
+
+    /// Removes every dot within `radius` of `coord` and returns the dots that were removed
+    pub fn erase_circle_of_dots(&mut self, radius: f64, coord: Vec2<f64>) -> Vec<Dot> {
+        let mut erased = Vec::new();
+        let top_left = (coord - radius)
+            .clamp_to_resolution(self.resolution)
+            .to_rounded_isize();
+        let bottom_right = (coord + radius)
+            .clamp_to_resolution(self.resolution)
+            .to_rounded_isize();
+        for x in top_left.x..=bottom_right.x {
+            for y in top_left.y..=bottom_right.y {
+                let point = Vec2::new(x, y);
+                if (point.into_f64() - coord).pythagorean_theorem() > radius {
                     continue;
                 }
+                if let Some(dot) = self.grid.get_mut(point).unwrap().take() {
+                    erased.extend(self.remove_dot(dot.id));
+                }
+            }
+        }
+        erased
+    }
+
+    pub fn dots(&self) -> impl Iterator<Item = &Dot> {
+        self.palette.values()
+    }
+
+    pub fn contains_dot(&self, id: DotId) -> bool {
+        self.palette.contains_key(&id)
+    }
 
-                // add new dot to palette, error if dot is already there
-                self.spawn_dot(material, point.into_f64(), Vec2::new(0., 0.))
-                    .expect(format!("Dot already exists in pos: {:?}", point.into_f64()).as_str());
+    /// Takes a dot off the canvas, `None` when no dot has the id
+    pub fn remove_dot(&mut self, id: DotId) -> Option<Dot> {
+        let dot = self.palette.remove(&id)?;
+        // frees the pixel for spawning right away, unless another dot has been written over it since
+        let coord = dot.position.clamp_to_resolution(self.resolution).to_rounded_isize();
+        if let Ok(cell) = self.grid.get_mut(coord) {
+            if cell.is_some_and(|canvas_dot| canvas_dot.id == id) {
+                *cell = None;
             }
         }
+        self.dot_id_gen
+            .mark_id_deleted(id)
+            .expect("Dot in palette had an id that was never generated");
+        Some(dot)
+    }
+
+    /// Puts a dot back on the canvas exactly as it is, keeping its id.
+    /// Returns false without changing anything when the dot's pixel or id is already taken.
+    pub fn restore_dot(&mut self, dot: Dot) -> bool {
+        let coord = dot.position.to_rounded_isize();
+        match self.grid.get(coord) {
+            Ok(None) => (),
+            _ => return false,
+        }
+        if self.dot_id_gen.claim_id(dot.id).is_err() {
+            return false;
+        }
+        self.palette.insert(dot.id, dot);
+        *self.grid.get_mut(coord).unwrap() = Some(CanvasDot::from(&dot));
+        true
+    }
+
+    /// Fingerprint of everything on the canvas, two canvases with the same hash hold the same dots.
+    /// It is the same on every platform and Rust release, replays rely on that.
+    pub fn state_hash(&self) -> u64 {
+        // written field by field instead of through `Hash`, so the hash only changes when this does
+        let mut hasher = Fnv1a::default();
+        hasher.write_i32(self.resolution.width);
+        hasher.write_i32(self.resolution.height);
+        let mut ids: Vec<&DotId> = self.palette.keys().collect();
+        ids.sort();
+        for id in ids {
+            let dot = &self.palette[id];
+            hasher.write_u32(*id);
+            hasher.write_u8(dot.material as u8);
+            for n in [dot.position.x, dot.position.y, dot.velocity.x, dot.velocity.y] {
+                hasher.write_u64(n.to_bits());
+            }
+        }
+        hasher.finish()
     }
 
     pub fn write_dots_to_grid(&mut self) {
+        let _span = profiler::span("write_dots_to_grid");
         self.grid.clear();
         for (dot, pos) in self
             .palette
@@ -108,281 +279,11 @@ impl Canvas {
                 Ok(maybe_canvas_dot) => {
                     *maybe_canvas_dot = Some(dot);
                 }
-                Err(CanvasError::CoordOutOfBounds) => println!(
-                    "WARNING: Tried to write a dot to canvas that was out of bounds:\n{:?}",
+                Err(CanvasError::CoordOutOfBounds) => debug!(
+                    "Tried to write a dot to canvas that was out of bounds:\n{:?}",
                     dot
                 ),
             }
         }
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use crate::{
-        game::{
-            canvas::{Canvas, CanvasError},
-            dot::Dot,
-            id_generator::DotIdGen,
-            material::Material,
-            vec2::Vec2,
-        },
-        rendering::glsl_types::Resolution,
-    };
-
-    fn setup_canvas(test_data: Vec<Dot>) -> Result<Canvas, CanvasError> {
-        const WIDTH: usize = 10;
-        const HEIGHT: usize = 10;
-
-        let mut canvas = Canvas::new(Resolution {
-            height: HEIGHT as i32,
-            width: WIDTH as i32,
-        });
-
-        for dot in test_data.iter() {
-            let coord = Vec2::new(
-                dot.position.x.round() as usize,
-                dot.position.y.round() as usize,
-            );
-            if coord.x > WIDTH || coord.y > HEIGHT {
-                panic!("Test data was not setup correctly, dot placed out of bounds.");
-            }
-
-            let canvas_dot = canvas
-                .grid
-                .get_mut(coord.y)
-                .ok_or(CanvasError::CoordOutOfBounds)?
-                .get_mut(coord.x)
-                .ok_or(CanvasError::CoordOutOfBounds)?;
-
-            *canvas_dot = Some(dot.into());
-        }
-
-        Ok(canvas)
-    }
-
-    #[test]
-    fn test_cast_finds_end_point() {
-        let ray_start = Vec2::new(5., 5.);
-        let ray_end = Vec2::new(6., 5.);
-        let mut dot_id_generator = DotIdGen::new();
-        let test_data = vec![
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_start,
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_end,
-                Vec2::new(0., 0.),
-            ),
-        ];
-
-        let expected_id = test_data[1].id;
-
-        let canvas = setup_canvas(test_data).unwrap();
-        let path = canvas.cast_ray(ray_start, ray_end).0;
-
-        assert_eq!(
-            1,
-            path.len(),
-            "Ray cast did not stop at end point:\n{:?}",
-            path
-        );
-        assert_eq!(
-            expected_id,
-            path[0].dot.unwrap().id,
-            "Could not find correct ray end dot"
-        );
-        assert_eq!(
-            Vec2::new(6, 5),
-            path[0].coord,
-            "End point was not the correct coordinate"
-        );
-    }
-
-    #[test]
-    fn test_cast_includes_empty_points() {
-        let ray_start = Vec2::new(4., 5.);
-        let ray_end = Vec2::new(7., 5.);
-        let mut dot_id_generator = DotIdGen::new();
-        let test_data = vec![
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_start,
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_end,
-                Vec2::new(0., 0.),
-            ),
-        ];
-
-        let canvas = setup_canvas(test_data).unwrap();
-        let path = canvas.cast_ray(ray_start, ray_end).0;
-
-        assert_eq!(3, path.len());
-        assert!(
-            path[0].dot.is_none(),
-            "First point was not empty:\n{:?}",
-            path[0]
-        );
-        assert!(
-            path[1].dot.is_none(),
-            "Second point was not empty:\n{:?}",
-            path[1]
-        );
-    }
-
-    #[test]
-    fn test_cast_includes_dots_in_middle_of_ray_and_empty_end_point() {
-        let ray_start = Vec2::new(4., 5.);
-        let middle_dot_point_1 = Vec2::new(5., 5.);
-        let middle_dot_point_2 = Vec2::new(6., 5.);
-        let ray_end = Vec2::new(7., 5.);
-        let mut dot_id_generator = DotIdGen::new();
-        let test_data = vec![
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_start,
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                middle_dot_point_1,
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                middle_dot_point_2,
-                Vec2::new(0., 0.),
-            ),
-        ];
-
-        let middle_dot_id_1 = test_data[1].id;
-        let middle_dot_id_2 = test_data[2].id;
-
-        let canvas = setup_canvas(test_data).unwrap();
-        let path = canvas.cast_ray(ray_start, ray_end).0;
-
-        assert_eq!(3, path.len());
-        assert_eq!(
-            middle_dot_id_1,
-            path[0].dot.unwrap().id,
-            "First middle dot had incorrect id:\n{:?}",
-            path[0].dot
-        );
-        assert_eq!(
-            Vec2::new(5, 5),
-            path[0].coord,
-            "First middle point had incorrect coordinates:\n{:?}",
-            path[0].dot
-        );
-        assert_eq!(
-            middle_dot_id_2,
-            path[1].dot.unwrap().id,
-            "Second middle dot had incorrect id:\n{:?}",
-            path[1].dot
-        );
-        assert_eq!(
-            Vec2::new(6, 5),
-            path[1].coord,
-            "Second middle point had incorrect coordinates:\n{:?}",
-            path[1].dot
-        );
-        assert!(
-            path[2].dot.is_none(),
-            "End point was not empty:\n{:?}",
-            path[2]
-        );
-    }
-
-    #[test]
-    fn test_cast_between_dots_doesnt_capture_them() {
-        let ray_start = Vec2::new(4., 5.);
-        let ray_end = Vec2::new(6., 5.);
-        let mut dot_id_generator = DotIdGen::new();
-        let test_data = vec![
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                ray_start,
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                Vec2::new(5., 4.),
-                Vec2::new(0., 0.),
-            ),
-            Dot::new(
-                &mut dot_id_generator,
-                Material::Sand,
-                Vec2::new(5., 6.),
-                Vec2::new(0., 0.),
-            ),
-        ];
-
-        let canvas = setup_canvas(test_data).unwrap();
-        let path = canvas.cast_ray(ray_start, ray_end).0;
-
-        assert_eq!(2, path.len());
-        assert!(
-            path[0].dot.is_none(),
-            "Ray cast found dot between start and end point when it should have found empty space:\n{:?}",
-            path[0]
-        );
-        assert!(
-            path[0].dot.is_none(),
-            "Ray cast found dot at end point when it should have found empty space:\n{:?}",
-            path[0]
-        );
-    }
-
-    #[test]
-    fn test_cast_to_edge() {
-        // n = nothing (null)
-        // d = dot
-        // * = starting point
-        // # = wall
-        // *------> = 0 degrees
-        //
-        // # # # # #
-        // # n d
-        // #   *
-        let ray_start = Vec2::new(1., 1.);
-        let direction_in_degrees = 247.5;
-        let mut dot_id_generator = DotIdGen::new();
-        let test_data = vec![Dot::new(
-            &mut dot_id_generator,
-            Material::Sand,
-            Vec2::new(1., 0.),
-            Vec2::new(0., 0.),
-        )];
-
-        let canvas = setup_canvas(test_data).unwrap();
-        let path = canvas.cast_ray_to_edge(ray_start, direction_in_degrees);
-
-        assert_eq!(2, path.len(), "PATH: {:?}", path);
-        assert_eq!(
-            Vec2::new(1, 0),
-            path[0].coord,
-            "First dot point had incorrect coordinates:\n{:?}",
-            path[0].dot
-        );
-        assert!(
-            path[1].dot.is_none(),
-            "Ray cast should have captured the dot as the first point in the ray, not the second:\n{:?}",
-            path[1]
-        );
-    }
-}
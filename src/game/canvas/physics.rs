@@ -1,14 +1,18 @@
 use std::{collections::HashSet, time::Duration};
 
-use crate::game::FRICTION;
+use log::debug;
+
+use crate::{game::config::GameConfig, profiler};
 
 use super::{dot::DotModification, Canvas};
 
 impl Canvas {
-    pub fn calculate_physics(&mut self, delta_time: Duration) {
+    pub(crate) fn calculate_physics(&mut self, delta_time: Duration, config: &GameConfig) {
+        let _span = profiler::span("calculate_physics");
+
         // find velocity
         for dot in self.palette.values_mut() {
-            dot.velocity = dot.find_next_velocity(delta_time);
+            dot.velocity = dot.find_next_velocity(delta_time, config.gravity);
         }
 
         // find position & handle collisions
@@ -18,7 +22,7 @@ impl Canvas {
             // let offset_from_drag = dot.find_pos_offset_from_drag();
             let next_pos = dot.find_next_position(delta_time);
             if next_pos.to_rounded_isize() != dot.position.to_rounded_isize() {
-                let collision_check = self.grid.check_for_dot_collision(&dot, next_pos, self.resolution);
+                let collision_check = self.grid.check_for_dot_collision(dot, next_pos, self.resolution);
                 if let Some(collided_dots) = collision_check {
                     if let Some(other) = collided_dots.other {
                         let mut ids = [collided_dots.this.id, other.id];
@@ -44,11 +48,18 @@ impl Canvas {
             });
         }
 
+        debug!(
+            "{} dots moved {:?}, {} collided with another dot",
+            self.palette.len(),
+            delta_time,
+            visited_collisions.len() * 2
+        );
+
         // apply position & collision changes
         for dot_to_modify in dots_to_modify {
             let dot = self.palette.get_mut(&dot_to_modify.id).unwrap();
             if let Some(del_vel) = dot_to_modify.delta_velocity {
-                dot.velocity += del_vel * (1.0 - FRICTION);
+                dot.velocity += del_vel * (1.0 - config.friction);
             }
             if let Some(del_pos) = dot_to_modify.delta_position {
                 dot.position += del_pos;
@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use crate::{game::math::Vec2, rendering::glsl_types::Resolution};
+
+use super::{Canvas, Dot, DotId, Grid};
+
+/// Point of the canvas that stays fixed when the canvas is grown or cropped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+impl Anchor {
+    /// Fraction of the size difference that is added to the left and top of the canvas
+    fn weights(&self) -> Vec2<i32> {
+        // 0 = nothing added before the content, 1 = half, 2 = all of it
+        match self {
+            Anchor::TopLeft => Vec2::new(0, 0),
+            Anchor::Top => Vec2::new(1, 0),
+            Anchor::TopRight => Vec2::new(2, 0),
+            Anchor::Left => Vec2::new(0, 1),
+            Anchor::Center => Vec2::new(1, 1),
+            Anchor::Right => Vec2::new(2, 1),
+            Anchor::BottomLeft => Vec2::new(0, 2),
+            Anchor::Bottom => Vec2::new(1, 2),
+            Anchor::BottomRight => Vec2::new(2, 2),
+        }
+    }
+
+    /// Offset in pixels that existing content is moved by when going from `old` to `new`
+    fn offset(&self, old: Resolution, new: Resolution) -> Vec2<f64> {
+        let weights = self.weights();
+        Vec2::new(
+            ((new.width - old.width) * weights.x / 2) as f64,
+            ((new.height - old.height) * weights.y / 2) as f64,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeMode {
+    /// Grow or crop the canvas around the anchor, dots keep their size
+    Anchored(Anchor),
+    /// Stretch or squash the content so it fills the new resolution
+    Rescale,
+}
+
+/// Summary of what happened to the dots on the canvas during a resize
+#[derive(Debug)]
+pub struct ResizeReport {
+    pub old_resolution: Resolution,
+    pub new_resolution: Resolution,
+    /// Dots that ended up outside of the new canvas bounds
+    pub out_of_bounds: Vec<Dot>,
+    /// Dots that landed on a pixel already taken by another dot while rescaling
+    pub overlapping: Vec<Dot>,
+}
+impl ResizeReport {
+    pub fn dropped_count(&self) -> usize {
+        self.out_of_bounds.len() + self.overlapping.len()
+    }
+}
+
+impl Canvas {
+    /// Changes the resolution of the canvas, moving every dot into the new space.
+    /// Dots that don't fit in the new canvas are removed and returned in the report.
+    pub fn resize(&mut self, resolution: Resolution, mode: ResizeMode) -> ResizeReport {
+        let old_resolution = self.resolution;
+        let scale = Vec2::<f64>::from(resolution) / Vec2::<f64>::from(old_resolution);
+        // the last row and column scale to the far edge of the new canvas, they are kept on its last pixel
+        let rescale = |position: Vec2<f64>| (position * scale).clamp_to_resolution(resolution);
+        let mut report = ResizeReport {
+            old_resolution,
+            new_resolution: resolution,
+            out_of_bounds: Vec::new(),
+            overlapping: Vec::new(),
+        };
+
+        // move dots to where they belong in the new canvas
+        for dot in self.palette.values_mut() {
+            match mode {
+                ResizeMode::Anchored(anchor) => {
+                    dot.position += anchor.offset(old_resolution, resolution);
+                }
+                ResizeMode::Rescale => {
+                    dot.position = rescale(dot.position);
+                    dot.velocity *= scale;
+                }
+            }
+        }
+
+        // find dots that no longer fit, lowest ids win when dots overlap
+        let mut ids: Vec<DotId> = self.palette.keys().copied().collect();
+        ids.sort();
+        let mut taken_coords = HashSet::new();
+        for id in ids {
+            let coord = self.palette[&id].position.to_rounded_isize();
+            let in_bounds = coord.x >= 0
+                && coord.y >= 0
+                && coord.x < resolution.width as isize
+                && coord.y < resolution.height as isize;
+            if !in_bounds {
+                report.out_of_bounds.extend(self.remove_dot(id));
+            } else if !taken_coords.insert(coord) {
+                report.overlapping.extend(self.remove_dot(id));
+            }
+        }
+
+        // lights move along with the dots, the ones that end up outside are dropped without a report
+        for light in self.lights.iter_mut() {
+            match mode {
+                ResizeMode::Anchored(anchor) => {
+                    light.position += anchor.offset(old_resolution, resolution);
+                }
+                ResizeMode::Rescale => light.position = rescale(light.position),
+            }
+        }
+        self.lights.retain(|light| {
+            let coord = light.position.to_rounded_isize();
+            coord.x >= 0
+                && coord.y >= 0
+                && coord.x < resolution.width as isize
+                && coord.y < resolution.height as isize
+        });
+
+        self.resolution = resolution;
+        self.grid = Grid::new_empty(resolution);
+        self.write_dots_to_grid();
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Anchor, ResizeMode};
+    use crate::{
+        game::{canvas::Canvas, material::Material, math::Vec2},
+        rendering::glsl_types::Resolution,
+    };
+
+    fn setup_canvas(positions: &[Vec2<f64>]) -> Canvas {
+        let mut canvas = Canvas::new(Resolution {
+            width: 10,
+            height: 10,
+        });
+        for pos in positions {
+            canvas
+                .spawn_dot(Material::Sand, *pos, Vec2::new(0., 0.))
+                .unwrap();
+        }
+        canvas.write_dots_to_grid();
+        canvas
+    }
+
+    fn positions(canvas: &Canvas) -> Vec<Vec2<isize>> {
+        let mut positions: Vec<_> = canvas
+            .palette
+            .values()
+            .map(|d| d.position.to_rounded_isize())
+            .collect();
+        positions.sort_by_key(|p| (p.x, p.y));
+        positions
+    }
+
+    #[test]
+    fn test_grow_top_left_keeps_positions() {
+        let mut canvas = setup_canvas(&[Vec2::new(2., 3.), Vec2::new(9., 9.)]);
+        let report = canvas.resize(
+            Resolution {
+                width: 20,
+                height: 15,
+            },
+            ResizeMode::Anchored(Anchor::TopLeft),
+        );
+
+        assert_eq!(0, report.dropped_count());
+        assert_eq!(vec![Vec2::new(2, 3), Vec2::new(9, 9)], positions(&canvas));
+        assert!(canvas.grid.get(Vec2::new(19, 14)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_grow_center_moves_content() {
+        let mut canvas = setup_canvas(&[Vec2::new(0., 0.)]);
+        canvas.resize(
+            Resolution {
+                width: 20,
+                height: 14,
+            },
+            ResizeMode::Anchored(Anchor::Center),
+        );
+
+        assert_eq!(vec![Vec2::new(5, 2)], positions(&canvas));
+        assert!(canvas.grid.get(Vec2::new(5, 2)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_crop_reports_dropped_dots() {
+        let mut canvas = setup_canvas(&[Vec2::new(1., 1.), Vec2::new(8., 8.)]);
+        let report = canvas.resize(
+            Resolution {
+                width: 5,
+                height: 5,
+            },
+            ResizeMode::Anchored(Anchor::TopLeft),
+        );
+
+        assert_eq!(1, report.out_of_bounds.len());
+        assert_eq!(Vec2::new(8, 8), report.out_of_bounds[0].position.to_rounded_isize());
+        assert_eq!(vec![Vec2::new(1, 1)], positions(&canvas));
+    }
+
+    #[test]
+    fn test_crop_frees_ids_of_dropped_dots() {
+        let mut canvas = setup_canvas(&[Vec2::new(8., 8.)]);
+        let dropped_id = *canvas.palette.keys().next().unwrap();
+        canvas.resize(
+            Resolution {
+                width: 5,
+                height: 5,
+            },
+            ResizeMode::Anchored(Anchor::TopLeft),
+        );
+
+        assert_eq!(dropped_id, canvas.dot_id_gen.new_id().unwrap());
+    }
+
+    #[test]
+    fn test_rescale_up_spreads_content() {
+        let mut canvas = setup_canvas(&[Vec2::new(0., 0.), Vec2::new(9., 9.)]);
+        let report = canvas.resize(
+            Resolution {
+                width: 20,
+                height: 20,
+            },
+            ResizeMode::Rescale,
+        );
+
+        assert_eq!(0, report.dropped_count());
+        assert_eq!(vec![Vec2::new(0, 0), Vec2::new(18, 18)], positions(&canvas));
+    }
+
+    #[test]
+    fn test_rescale_down_keeps_dots_on_the_edge() {
+        let mut canvas = setup_canvas(&[Vec2::new(0., 0.), Vec2::new(9., 9.), Vec2::new(9., 0.)]);
+        let report = canvas.resize(
+            Resolution {
+                width: 5,
+                height: 5,
+            },
+            ResizeMode::Rescale,
+        );
+
+        assert_eq!(0, report.dropped_count());
+        assert_eq!(
+            vec![Vec2::new(0, 0), Vec2::new(4, 0), Vec2::new(4, 4)],
+            positions(&canvas)
+        );
+    }
+
+    #[test]
+    fn test_rescale_down_reports_overlapping_dots() {
+        let mut canvas = setup_canvas(&[Vec2::new(0., 0.), Vec2::new(1., 1.)]);
+        let report = canvas.resize(
+            Resolution {
+                width: 2,
+                height: 2,
+            },
+            ResizeMode::Rescale,
+        );
+
+        assert_eq!(1, report.overlapping.len());
+        assert_eq!(1, canvas.palette.len());
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    fmt, fs, io,
+    path::PathBuf,
+    time::Duration,
+};
+
+use crate::rendering::glsl_types::Resolution;
+
+use super::{
+    Vec2, CURSOR_SIZE, DELAY_BETWEEN_DOTS, FRICTION, GRAVITY, INITIAL_CANVAS_RESOLUTION,
+    MIN_CANVAS_SIZE,
+};
+
+/// Tuning values of the simulation that can be changed without a rebuild
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameConfig {
+    /// Size of the canvas a new game starts with
+    pub canvas_resolution: Resolution,
+    /// Acceleration of every dot in pixels per second ^2
+    pub gravity: Vec2<f64>,
+    /// While holding a mouse button down, delay between dots that get spawned
+    pub delay_between_dots: Duration,
+    /// Share of velocity lost when 2 dots collide, from 0.0 to 1.0
+    pub friction: f64,
+    /// Radius of the brush and eraser in pixels
+    pub cursor_size: f64,
+}
+impl GameConfig {
+    /// Sets one value by the name config files use for it, checking that it is in range
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "canvas-width" => self.canvas_resolution.width = parse_canvas_size(value)?,
+            "canvas-height" => self.canvas_resolution.height = parse_canvas_size(value)?,
+            "gravity-x" => self.gravity.x = parse_finite(value)?,
+            "gravity-y" => self.gravity.y = parse_finite(value)?,
+            "delay-between-dots-ms" => {
+                let millis = value
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a whole number of milliseconds", value))?;
+                self.delay_between_dots = Duration::from_millis(millis);
+            }
+            "friction" => match parse_finite(value)? {
+                friction if (0. ..=1.).contains(&friction) => self.friction = friction,
+                friction => return Err(format!("friction must be from 0.0 to 1.0, not {}", friction)),
+            },
+            "cursor-size" => match parse_finite(value)? {
+                size if size > 0. => self.cursor_size = size,
+                size => return Err(format!("cursor size must be above 0, not {}", size)),
+            },
+            _ => return Err(format!("unknown setting '{}'", key)),
+        }
+        Ok(())
+    }
+
+    /// Every value with the name config files use for it, setting them all again gives back the same config
+    pub fn entries(&self) -> [(&'static str, String); 7] {
+        [
+            ("canvas-width", self.canvas_resolution.width.to_string()),
+            ("canvas-height", self.canvas_resolution.height.to_string()),
+            ("gravity-x", self.gravity.x.to_string()),
+            ("gravity-y", self.gravity.y.to_string()),
+            (
+                "delay-between-dots-ms",
+                self.delay_between_dots.as_millis().to_string(),
+            ),
+            ("friction", self.friction.to_string()),
+            ("cursor-size", self.cursor_size.to_string()),
+        ]
+    }
+
+    /// Applies a config file with one `key = value` entry per line, `#` starts a comment
+    fn apply_file(&mut self, text: &str) -> Result<(), ConfigError> {
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let result = match line.split_once('=') {
+                Some((key, value)) => self.set(key.trim(), value.trim()),
+                None => Err("expected 'key = value'".to_string()),
+            };
+            result.map_err(|message| ConfigError::Parse {
+                line: i + 1,
+                message,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Applies an override given on the command line as `key=value`
+    fn apply_override(&mut self, text: &str) -> Result<(), ConfigError> {
+        let result = match text.split_once('=') {
+            Some((key, value)) => self.set(key.trim(), value.trim()),
+            None => Err("expected 'key=value'".to_string()),
+        };
+        result.map_err(|message| ConfigError::Override {
+            text: text.to_string(),
+            message,
+        })
+    }
+}
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            canvas_resolution: INITIAL_CANVAS_RESOLUTION,
+            gravity: GRAVITY,
+            delay_between_dots: DELAY_BETWEEN_DOTS,
+            friction: FRICTION,
+            cursor_size: CURSOR_SIZE,
+        }
+    }
+}
+
+fn parse_finite(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(number),
+        _ => Err(format!("'{}' is not a number", value)),
+    }
+}
+
+fn parse_canvas_size(value: &str) -> Result<i32, String> {
+    match value.parse() {
+        Ok(size) if size >= MIN_CANVAS_SIZE => Ok(size),
+        Ok(size) => Err(format!(
+            "canvas size must be at least {}, not {}",
+            MIN_CANVAS_SIZE, size
+        )),
+        Err(_) => Err(format!("'{}' is not a whole number", value)),
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    Override { text: String, message: String },
+}
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "could not read config file: {}", e),
+            ConfigError::Parse { line, message } => {
+                write!(f, "config file line {}: {}", line, message)
+            }
+            ConfigError::Override { text, message } => {
+                write!(f, "config override '{}': {}", text, message)
+            }
+        }
+    }
+}
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+/// Where a config comes from, kept around so it can be loaded again while the game runs
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource {
+    pub path: Option<PathBuf>,
+    /// `key=value` entries that take precedence over the file
+    pub overrides: Vec<String>,
+}
+impl ConfigSource {
+    /// Default values, then the file, then the overrides
+    pub fn load(&self) -> Result<GameConfig, ConfigError> {
+        let mut config = GameConfig::default();
+        if let Some(path) = &self.path {
+            config.apply_file(&fs::read_to_string(path)?)?;
+        }
+        for text in &self.overrides {
+            config.apply_override(text)?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ConfigError, ConfigSource, GameConfig};
+    use crate::game::math::Vec2;
+
+    #[test]
+    fn test_defaults_survive_empty_source() {
+        assert_eq!(GameConfig::default(), ConfigSource::default().load().unwrap());
+    }
+
+    #[test]
+    fn test_file_and_overrides() {
+        let mut config = GameConfig::default();
+        config
+            .apply_file("# heavy and sticky\ngravity-y = 250\nfriction = 0.5 # half\n\ncursor-size=2.5\n")
+            .unwrap();
+        config.apply_override("friction=0.25").unwrap();
+
+        assert_eq!(250., config.gravity.y);
+        assert_eq!(0.25, config.friction);
+        assert_eq!(2.5, config.cursor_size);
+        assert_eq!(GameConfig::default().canvas_resolution, config.canvas_resolution);
+    }
+
+    #[test]
+    fn test_out_of_range_values_are_rejected() {
+        let mut config = GameConfig::default();
+        assert!(config.set("friction", "1.5").is_err());
+        assert!(config.set("friction", "NaN").is_err());
+        assert!(config.set("cursor-size", "0").is_err());
+        assert!(config.set("canvas-width", "10").is_err());
+        assert!(config.set("delay-between-dots-ms", "-1").is_err());
+        assert!(config.set("wind", "3").is_err());
+        assert_eq!(GameConfig::default(), config);
+
+        match config.apply_file("friction = 0.1\nfriction = -0.1\n") {
+            Err(ConfigError::Parse { line: 2, .. }) => (),
+            other => panic!("expected error on line 2, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let config = GameConfig {
+            delay_between_dots: Duration::from_millis(20),
+            gravity: Vec2::new(-12.125, 100.),
+            friction: 0.1,
+            ..Default::default()
+        };
+
+        let mut read_back = GameConfig::default();
+        for (key, value) in config.entries() {
+            read_back.set(key, &value).unwrap();
+        }
+        assert_eq!(config, read_back);
+    }
+}
@@ -1,13 +1,30 @@
-use std::time::{Duration, Instant};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
-use winit::dpi::{PhysicalPosition, PhysicalSize};
+use log::{debug, warn};
 
-use crate::windowing::state::MouseState;
+use crate::rendering::{
+    export::{ExportError, RgbaImage},
+    glsl_types::Resolution,
+    import::{self, ColorMapping, ImportError, ImportReport},
+    shading::Shading,
+};
 
 use super::{
-    canvas::Canvas,
+    camera::Camera,
+    canvas::{Anchor, Canvas, Dot, PointLight, ResizeMode, ResizeReport, SpawnError},
+    config::GameConfig,
+    history::{CanvasEdit, History},
+    input::MouseState,
     material::Material,
-    Vec2, CURSOR_SIZE, DELAY_BETWEEN_DOTS, INITIAL_CANVAS_RESOLUTION,
+    math::rng,
+    replay::{InputEvent, Recorder, ReplayError, ReplayLog},
+    tool::Tool,
+    view_transform::ViewTransform,
+    Vec2, CANVAS_RESIZE_STEP, HISTORY_MEMORY_BUDGET, INITIAL_MATERIAL, MIN_CANVAS_SIZE,
+    QUICKSAVE_PATH, RIGHT_BUTTON_MATERIAL, SINGLE_STEP_DURATION, TIME_SCALES,
 };
 
 pub enum CoordConversion<T> {
@@ -15,26 +32,77 @@ pub enum CoordConversion<T> {
     OutOfBounds,
 }
 
+/// Everything the player does to the simulation in one frame. Positions are already in canvas space
+/// so a frame can be recorded and replayed without a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameInput {
+    /// Real time that passed since the previous frame
+    pub delta_time: Duration,
+    pub tool: Tool,
+    pub mouse_state: MouseState,
+    /// Material bound to the pressed mouse button, `None` when no button is pressed
+    pub material: Option<Material>,
+    /// Canvas cell under the cursor, `None` when the cursor is outside of the canvas
+    pub cursor: Option<Vec2<f64>>,
+}
+
 pub struct Game {
     pub delta_time: Duration,
-    pub canvas: Canvas,
-    pub last_dot_spawned: Instant,
+    pub(crate) canvas: Canvas,
+    pub camera: Camera,
+    pub tool: Tool,
+    /// Material the left mouse button paints with
+    pub material: Material,
+    time_since_last_dot: Duration,
     last_frame_time: Instant,
     frame_count: u128,
+    is_paused: bool,
+    time_scale: f64,
+    seed: u64,
+    config: GameConfig,
+    recorder: Option<Recorder>,
+    history: History,
+    /// Changes made since the mouse button was pressed, saved to history as one edit on release
+    current_edit: CanvasEdit,
 }
 impl Game {
-    pub fn new() -> Self {
-        let mut canvas = Canvas::new(INITIAL_CANVAS_RESOLUTION);
+    pub fn new(config: GameConfig) -> Self {
+        Self::new_with_seed(config, rng::clock_seed())
+    }
 
-        canvas.spawn_dot(Material::Blue, Vec2::new(100., 100.), Vec2::new(50., -50.));
-        canvas.spawn_dot(Material::Orange, Vec2::new(300., 167.), Vec2::new(-100., -100.));
+    /// Creates a game where every random number comes from `seed`
+    pub fn new_with_seed(config: GameConfig, seed: u64) -> Self {
+        rng::set_seed(seed);
+        let mut canvas = Canvas::new(config.canvas_resolution);
+
+        let starting_dots = [
+            (Material::Blue, Vec2::new(100., 100.), Vec2::new(50., -50.)),
+            (Material::Orange, Vec2::new(300., 167.), Vec2::new(-100., -100.)),
+        ];
+        for (material, position, velocity) in starting_dots {
+            match canvas.spawn_dot(material, position, velocity) {
+                // a small canvas from the config may not have room for them
+                Ok(_) | Err(SpawnError::OutOfBounds(_)) => (),
+                Err(e) => warn!("Could not spawn a starting dot, {}", e),
+            }
+        }
 
         let mut game = Self {
             canvas,
+            camera: Camera::new(),
             delta_time: Duration::ZERO,
+            tool: Tool::Brush,
+            material: INITIAL_MATERIAL,
+            time_since_last_dot: Duration::ZERO,
             last_frame_time: Instant::now(),
-            last_dot_spawned: Instant::now(),
             frame_count: 0,
+            is_paused: false,
+            time_scale: 1.,
+            seed,
+            config,
+            recorder: None,
+            history: History::new(HISTORY_MEMORY_BUDGET),
+            current_edit: CanvasEdit::default(),
         };
 
         game.canvas.write_dots_to_grid();
@@ -49,65 +117,539 @@ impl Game {
     }
 
     pub fn set_next_frame(&mut self, delta_time: Duration) {
-        self.canvas.calculate_physics(delta_time);
-        self.canvas.write_dots_to_grid();
+        self.canvas.step(delta_time, &self.config);
         self.frame_count += 1;
     }
 
-    pub fn handle_spawn_dots(
+    /// Runs a whole frame: spawns dots for the input, then moves the simulation forward
+    pub fn run_frame(&mut self, input: FrameInput) {
+        self.record(InputEvent::Frame(input));
+        self.handle_spawn_dots(&input);
+        self.advance(input.delta_time);
+    }
+
+    /// Moves the simulation forward by `real_delta_time` scaled by the time scale, unless paused
+    pub fn advance(&mut self, real_delta_time: Duration) {
+        if self.is_paused {
+            return;
+        }
+        self.set_next_frame(real_delta_time.mul_f64(self.time_scale));
+    }
+
+    pub fn canvas(&self) -> &Canvas {
+        &self.canvas
+    }
+
+    /// Call once a renderer has picked up the changes of the last frame
+    pub fn mark_canvas_clean(&mut self) {
+        self.canvas.grid.mark_clean();
+    }
+
+    /// Lights don't change the simulation, so removing them isn't recorded
+    pub fn clear_lights(&mut self) {
+        self.canvas.lights.clear();
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    /// Switches to new tuning values from the next frame on. The canvas keeps its size,
+    /// resize it separately to follow the config's canvas resolution.
+    pub fn set_config(&mut self, config: GameConfig) {
+        self.record(InputEvent::SetConfig(config));
+        self.config = config;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn set_paused(&mut self, is_paused: bool) {
+        self.record(InputEvent::SetPaused(is_paused));
+        self.is_paused = is_paused;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.set_paused(!self.is_paused());
+    }
+
+    /// Runs exactly one tick of `SINGLE_STEP_DURATION` simulated time, pausing the game if it isn't already
+    pub fn step(&mut self) {
+        self.record(InputEvent::Step);
+        self.is_paused = true;
+        self.set_next_frame(SINGLE_STEP_DURATION);
+    }
+
+    /// Runs a frame of `SINGLE_STEP_DURATION` real time with no mouse input, for running without a window.
+    /// Returns the real time the frame stands for.
+    pub fn tick(&mut self) -> Duration {
+        self.run_frame(FrameInput {
+            delta_time: SINGLE_STEP_DURATION,
+            tool: self.tool,
+            mouse_state: MouseState::default(),
+            material: None,
+            cursor: None,
+        });
+        SINGLE_STEP_DURATION
+    }
+
+    pub fn frame_count(&self) -> u128 {
+        self.frame_count
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Sets how fast simulated time passes compared to real time, clamped to the range of `TIME_SCALES`
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.record(InputEvent::SetTimeScale(time_scale));
+        self.time_scale = time_scale.clamp(TIME_SCALES[0], TIME_SCALES[TIME_SCALES.len() - 1]);
+    }
+
+    /// Moves to the next faster (positive steps) or slower (negative steps) entry in `TIME_SCALES`
+    pub fn step_time_scale(&mut self, steps: i32) {
+        let current = TIME_SCALES
+            .iter()
+            .position(|scale| *scale >= self.time_scale)
+            .unwrap_or(TIME_SCALES.len() - 1) as i32;
+        let next = (current + steps).clamp(0, TIME_SCALES.len() as i32 - 1);
+        self.set_time_scale(TIME_SCALES[next as usize]);
+    }
+
+    /// Short description of the simulation state, meant for the window title
+    pub fn status(&self) -> String {
+        if self.is_paused() {
+            format!("Paused on frame {} at {}x speed", self.frame_count(), self.time_scale())
+        } else {
+            format!("Running at {}x speed", self.time_scale())
+        }
+    }
+
+    /// Grows (positive steps) or shrinks (negative steps) the canvas by `CANVAS_RESIZE_STEP` pixels
+    /// per step on both axes. The render engine must be told about the new canvas afterwards.
+    pub fn step_canvas_size(&mut self, steps: i32, mode: ResizeMode) -> ResizeReport {
+        let delta = steps * CANVAS_RESIZE_STEP;
+        let resolution = Resolution {
+            width: (self.canvas.resolution.width + delta).max(MIN_CANVAS_SIZE),
+            height: (self.canvas.resolution.height + delta).max(MIN_CANVAS_SIZE),
+        };
+        self.resize_canvas(resolution, mode)
+    }
+
+    /// Resizing moves every dot, so edits made before it can no longer be undone
+    pub fn resize_canvas(&mut self, resolution: Resolution, mode: ResizeMode) -> ResizeReport {
+        self.record(InputEvent::Resize(resolution, mode));
+        self.current_edit = CanvasEdit::default();
+        self.history.clear();
+        let report = self.canvas.resize(resolution, mode);
+        if report.dropped_count() > 0 {
+            warn!(
+                "Dropped {} dot(s) while resizing canvas from {}x{} to {}x{} ({} out of bounds, {} overlapping)",
+                report.dropped_count(),
+                report.old_resolution.width,
+                report.old_resolution.height,
+                report.new_resolution.width,
+                report.new_resolution.height,
+                report.out_of_bounds.len(),
+                report.overlapping.len(),
+            );
+        }
+        report
+    }
+
+    /// Replaces everything on the canvas with the pixels of a PNG, fitted to the canvas with `mode`.
+    /// Colours are mapped with the palette file if one is given, otherwise to the nearest material.
+    /// The whole import is a single edit in the undo history.
+    pub fn import_image(
         &mut self,
-        cursor_position: &PhysicalPosition<f64>,
-        window_resolution: &PhysicalSize<u32>,
+        image: impl AsRef<Path>,
+        palette: Option<&Path>,
+        mode: ResizeMode,
+    ) -> Result<ImportReport, ImportError> {
+        let mapping = match palette {
+            Some(path) => ColorMapping::from_palette_file(path)?,
+            None => ColorMapping::Nearest,
+        };
+        let (imported, report) =
+            import::import_png(image, &mapping, self.canvas.resolution, mode)?;
+
+        let mut new_dots: Vec<_> = imported.dots().collect();
+        new_dots.sort_by_key(|dot| dot.id);
+        let dots: Vec<_> = new_dots.iter().map(|dot| (dot.material, dot.position)).collect();
+        // the dots are recorded rather than the file, which may have changed by the time it is replayed
+        self.record(InputEvent::Import(dots.clone()));
+        self.replace_dots(&dots);
+
+        Ok(report)
+    }
+
+    /// Swaps every dot on the canvas for resting dots spawned in the given order, as a single edit
+    fn replace_dots(&mut self, dots: &[(Material, Vec2<f64>)]) {
+        self.finish_edit();
+        let mut edit = self.remove_all_dots();
+        for (material, position) in dots {
+            match self.canvas.spawn_dot(*material, *position, Vec2::new(0., 0.)) {
+                Ok(id) => edit.added.push(Dot {
+                    id,
+                    material: *material,
+                    position: *position,
+                    velocity: Vec2::new(0., 0.),
+                }),
+                Err(e) => warn!("Skipped an imported dot, {}", e),
+            }
+        }
+        self.canvas.write_dots_to_grid();
+        self.history.push(edit);
+    }
+
+    /// Removes every dot, in order of their ids so replays remove them the same way
+    fn remove_all_dots(&mut self) -> CanvasEdit {
+        let mut edit = CanvasEdit::default();
+        let mut ids: Vec<_> = self.canvas.dots().map(|dot| dot.id).collect();
+        ids.sort();
+        for id in ids {
+            edit.removed.extend(self.canvas.remove_dot(id));
+        }
+        edit
+    }
+
+    /// Removes every dot from the canvas as a single edit in the undo history
+    pub fn clear_canvas(&mut self) {
+        self.record(InputEvent::Clear);
+        self.finish_edit();
+        let edit = self.remove_all_dots();
+        self.canvas.write_dots_to_grid();
+        self.history.push(edit);
+    }
+
+    /// Saves the materials of the canvas to `QUICKSAVE_PATH` as a PNG, the only save format.
+    /// Velocities, positions within a pixel, dot ids and lights are not kept.
+    pub fn quick_save(&self) -> Result<PathBuf, ExportError> {
+        RgbaImage::from_canvas(&self.canvas, Shading::FLAT).write_png(QUICKSAVE_PATH)?;
+        Ok(PathBuf::from(QUICKSAVE_PATH))
+    }
+
+    /// Replaces the canvas with the last quick save, the same way as importing it
+    pub fn quick_load(&mut self) -> Result<ImportReport, ImportError> {
+        self.import_image(QUICKSAVE_PATH, None, ResizeMode::Anchored(Anchor::TopLeft))
+    }
+
+    /// Turns the state of the window into input for the next frame
+    pub fn frame_input(
+        &self,
+        cursor_position: Vec2<f64>,
+        scale_factor: f64,
+        window_size: Vec2<f64>,
         mouse_state: &MouseState,
-    ) {
-        if self.last_dot_spawned.elapsed() < DELAY_BETWEEN_DOTS {
+    ) -> FrameInput {
+        let material = if mouse_state.left {
+            Some(self.material)
+        } else if mouse_state.right {
+            Some(RIGHT_BUTTON_MATERIAL)
+        } else {
+            None
+        };
+        let cursor =
+            match self.cursor_to_game_coordinates(cursor_position, scale_factor, window_size) {
+                CoordConversion::Converted(coord) => Some(coord),
+                CoordConversion::OutOfBounds => None,
+            };
+        FrameInput {
+            delta_time: self.delta_time,
+            tool: self.tool,
+            mouse_state: *mouse_state,
+            material,
+            cursor,
+        }
+    }
+
+    pub fn handle_spawn_dots(&mut self, input: &FrameInput) {
+        if input.material.is_none() {
+            self.finish_edit();
+        }
+        self.time_since_last_dot += input.delta_time;
+        if self.time_since_last_dot < self.config.delay_between_dots {
             return;
         }
-        let material = match mouse_state {
-            MouseState::Released => return,
-            MouseState::LeftPressed => Material::Orange,
-            MouseState::RightPressed => Material::Blue,
+        let Some(material) = input.material else {
+            return;
         };
-        match self.physical_position_to_game_coordinates(cursor_position, window_resolution) {
+        match input.cursor {
+            Some(coord) => {
+                let edit = match input.tool {
+                    Tool::Brush => CanvasEdit {
+                        added: self.canvas.spawn_circle_of_dots(self.config.cursor_size, coord, material),
+                        removed: Vec::new(),
+                    },
+                    Tool::Eraser => CanvasEdit {
+                        added: Vec::new(),
+                        removed: self.canvas.erase_circle_of_dots(self.config.cursor_size, coord),
+                    },
+                };
+                self.current_edit.merge(edit);
+                // changes show up right away, even when the simulation is paused
+                self.canvas.write_dots_to_grid();
+                self.time_since_last_dot = Duration::ZERO;
+            }
+            None => debug!("Clicked outside of game space"),
+        }
+    }
+
+    /// Saves the stroke in progress to history
+    fn finish_edit(&mut self) {
+        let edit = std::mem::take(&mut self.current_edit);
+        self.history.push(edit);
+    }
+
+    /// Reverts the last brush stroke or erasure, returns false when there is nothing to undo
+    pub fn undo(&mut self) -> bool {
+        self.record(InputEvent::Undo);
+        self.finish_edit();
+        self.history.undo(&mut self.canvas)
+    }
+
+    /// Applies the last undone edit again, returns false when there is nothing to redo
+    pub fn redo(&mut self) -> bool {
+        self.record(InputEvent::Redo);
+        self.finish_edit();
+        self.history.redo(&mut self.canvas)
+    }
+
+    /// Starts writing every input to a log file at `path` that `Game::replay` can play back
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> Result<(), ReplayError> {
+        self.recorder = Some(Recorder::create(path, self.seed, self.canvas.resolution)?);
+        // replays start from the default config, so the one this game runs with has to be in the log
+        self.record(InputEvent::SetConfig(self.config));
+        Ok(())
+    }
+
+    /// Finishes the log with a hash of the canvas, so a replay can check that it ended up in the same place
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            if let Err(e) = recorder.finish(self.canvas.state_hash()) {
+                warn!("Failed to finish input recording: {}", e);
+            }
+        }
+    }
+
+    fn record(&mut self, event: InputEvent) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(&event) {
+                warn!("Stopped input recording after failing to write to it: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Plays back a log made with `start_recording` on a fresh game. Fails if the log can't be read or
+    /// if the canvas doesn't end up exactly how it was when the recording finished.
+    /// `on_frame` is called after every simulated frame with the game and the real time the frame took.
+    pub fn replay(
+        path: impl AsRef<Path>,
+        mut on_frame: impl FnMut(&Self, Duration),
+    ) -> Result<Self, ReplayError> {
+        let log = ReplayLog::read(path)?;
+        let config = GameConfig {
+            canvas_resolution: log.resolution,
+            ..Default::default()
+        };
+        let mut game = Self::new_with_seed(config, log.seed);
+
+        for event in log.events {
+            match event {
+                InputEvent::Frame(input) => {
+                    game.run_frame(input);
+                    on_frame(&game, input.delta_time);
+                }
+                InputEvent::SetPaused(is_paused) => game.set_paused(is_paused),
+                InputEvent::Step => {
+                    game.step();
+                    on_frame(&game, SINGLE_STEP_DURATION);
+                }
+                InputEvent::SetTimeScale(time_scale) => game.set_time_scale(time_scale),
+                InputEvent::Resize(resolution, mode) => {
+                    game.resize_canvas(resolution, mode);
+                }
+                InputEvent::Import(dots) => game.replace_dots(&dots),
+                InputEvent::Undo => {
+                    game.undo();
+                }
+                InputEvent::Clear => game.clear_canvas(),
+                InputEvent::SetConfig(config) => game.set_config(config),
+                InputEvent::Redo => {
+                    game.redo();
+                }
+            }
+        }
+
+        if let Some(expected) = log.final_hash {
+            let actual = game.canvas.state_hash();
+            if expected != actual {
+                return Err(ReplayError::HashMismatch { expected, actual });
+            }
+        }
+        Ok(game)
+    }
+
+    /// Cursor positions are in logical window pixels and window sizes in physical ones,
+    /// `scale_factor` is the number of physical pixels per logical pixel
+    pub fn pan_camera(
+        &mut self,
+        from: Vec2<f64>,
+        to: Vec2<f64>,
+        scale_factor: f64,
+        window_size: Vec2<f64>,
+    ) {
+        self.camera.pan(
+            (to - from) * scale_factor,
+            window_size,
+            self.canvas.resolution.into(),
+        );
+    }
+
+    pub fn zoom_camera(
+        &mut self,
+        steps: i32,
+        cursor_position: Vec2<f64>,
+        scale_factor: f64,
+        window_size: Vec2<f64>,
+    ) {
+        self.camera.zoom_at(
+            steps,
+            cursor_position * scale_factor,
+            window_size,
+            self.canvas.resolution.into(),
+        );
+    }
+
+    pub fn view_transform(&self, window_size: Vec2<f64>) -> ViewTransform {
+        ViewTransform::new(window_size, self.canvas.resolution.into(), &self.camera)
+    }
+
+    /// Puts a point light into the world under the cursor, returns false when the cursor is outside of the canvas.
+    /// Lights don't change the simulation, so they aren't recorded.
+    pub fn place_light(
+        &mut self,
+        cursor_position: Vec2<f64>,
+        scale_factor: f64,
+        window_size: Vec2<f64>,
+    ) -> bool {
+        match self.cursor_to_game_coordinates(cursor_position, scale_factor, window_size) {
             CoordConversion::Converted(coord) => {
-                self.canvas.spawn_circle_of_dots(CURSOR_SIZE, coord, material);
-                self.last_dot_spawned = Instant::now();
+                self.canvas.lights.push(PointLight::new(coord));
+                true
             }
-            CoordConversion::OutOfBounds => println!("WARNING! Clicked outside of game space"),
+            CoordConversion::OutOfBounds => false,
         }
     }
 
-    pub fn physical_position_to_game_coordinates(
+    /// Finds the canvas cell that is drawn under the cursor, given in logical window pixels
+    pub fn cursor_to_game_coordinates(
         &self,
-        physical_position: &PhysicalPosition<f64>,
-        window_resolution: &PhysicalSize<u32>,
+        cursor_position: Vec2<f64>,
+        scale_factor: f64,
+        window_size: Vec2<f64>,
     ) -> CoordConversion<Vec2<f64>> {
-        let win_res = Vec2::new(
-            window_resolution.width as f64,
-            window_resolution.height as f64,
-        );
-        let can_res: Vec2<f64> = self.canvas.resolution.into();
-        let win_ar = win_res.x / win_res.y;
-        let can_ar = can_res.x / can_res.y;
-
-        let corrected_win_res = if win_ar > can_ar {
-            Vec2::new(win_res.y / can_ar, win_res.y)
-        } else {
-            Vec2::new(win_res.x, win_res.x * can_ar)
-        };
-        let offset = (win_res - corrected_win_res) / 2.;
-        let corrected_position = Vec2::from(physical_position) - offset;
-
-        let game_coord = corrected_position * can_res / corrected_win_res;
-        if game_coord.x < 0.
-            || game_coord.y < 0.
-            || game_coord.x > can_res.x
-            || game_coord.y > can_res.y
+        let cell = self
+            .view_transform(window_size)
+            .logical_to_cell(cursor_position, scale_factor);
+        if cell.x < 0
+            || cell.y < 0
+            || cell.x >= self.canvas.resolution.width as isize
+            || cell.y >= self.canvas.resolution.height as isize
         {
             CoordConversion::OutOfBounds
         } else {
-            CoordConversion::Converted(game_coord)
+            CoordConversion::Converted(cell.into_f64())
         }
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Game;
+    use crate::game::config::GameConfig;
+
+    #[test]
+    fn test_paused_game_does_not_advance() {
+        let mut game = Game::new(GameConfig::default());
+        game.set_paused(true);
+        game.advance(Duration::from_millis(16));
+        assert_eq!(0, game.frame_count());
+    }
+
+    #[test]
+    fn test_step_runs_exactly_one_tick() {
+        let mut game = Game::new(GameConfig::default());
+        game.step();
+        assert_eq!(1, game.frame_count());
+        assert!(game.is_paused());
+
+        game.advance(Duration::from_millis(16));
+        assert_eq!(1, game.frame_count());
+    }
+
+    #[test]
+    fn test_clear_canvas_can_be_undone() {
+        let mut game = Game::new(GameConfig::default());
+        game.advance(Duration::from_millis(100));
+        let dots = game.canvas.dots().count();
+        assert!(dots > 0);
+
+        game.clear_canvas();
+        assert_eq!(0, game.canvas.dots().count());
+        assert!(game.undo());
+        assert_eq!(dots, game.canvas.dots().count());
+    }
+
+    #[test]
+    fn test_time_scale_is_clamped() {
+        let mut game = Game::new(GameConfig::default());
+        game.set_time_scale(100.);
+        assert_eq!(4., game.time_scale());
+        game.set_time_scale(0.);
+        assert_eq!(0.1, game.time_scale());
+    }
+
+    #[test]
+    fn test_step_time_scale_walks_presets() {
+        let mut game = Game::new(GameConfig::default());
+        game.step_time_scale(-1);
+        assert_eq!(0.5, game.time_scale());
+        game.step_time_scale(-10);
+        assert_eq!(0.1, game.time_scale());
+        game.step_time_scale(3);
+        assert_eq!(1., game.time_scale());
+        game.step_time_scale(10);
+        assert_eq!(4., game.time_scale());
+    }
+
+    #[test]
+    fn test_time_scale_slows_down_simulation() {
+        let mut real_time = Game::new(GameConfig::default());
+        let mut slow_motion = Game::new(GameConfig::default());
+        slow_motion.set_time_scale(0.5);
+
+        real_time.advance(Duration::from_millis(100));
+        slow_motion.advance(Duration::from_millis(200));
+
+        let positions = |game: &Game| {
+            let mut positions: Vec<_> = game
+                .canvas
+                .grid
+                .iter_materials_as_bytes()
+                .enumerate()
+                .filter(|(_, material)| *material != 0)
+                .map(|(i, _)| i)
+                .collect();
+            positions.sort();
+            positions
+        };
+        assert_eq!(positions(&real_time), positions(&slow_motion));
+    }
+}
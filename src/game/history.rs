@@ -0,0 +1,260 @@
+use std::{collections::VecDeque, mem};
+
+use super::canvas::{Canvas, Dot};
+
+/// One undoable change to the canvas, like a brush stroke or an erasure.
+/// Dots are stored as they were when the edit happened, ids included, so undo and redo
+/// can hand the exact same ids back to the id generator.
+#[derive(Debug, Clone, Default)]
+pub struct CanvasEdit {
+    pub added: Vec<Dot>,
+    pub removed: Vec<Dot>,
+}
+impl CanvasEdit {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Adds the changes of a later edit to this one
+    pub fn merge(&mut self, other: CanvasEdit) {
+        self.added.extend(other.added);
+        self.removed.extend(other.removed);
+    }
+
+    /// Approximate memory held by this edit in bytes
+    pub fn size_in_bytes(&self) -> usize {
+        (self.added.len() + self.removed.len()) * mem::size_of::<Dot>()
+    }
+
+    fn undo(&mut self, canvas: &mut Canvas) {
+        Self::swap_dots(canvas, &mut self.added, &mut self.removed);
+    }
+
+    fn redo(&mut self, canvas: &mut Canvas) {
+        Self::swap_dots(canvas, &mut self.removed, &mut self.added);
+    }
+
+    /// Takes `to_remove` off the canvas and puts `to_restore` back on it.
+    /// Both lists are updated to the dots' current state, so the edit can be reversed again.
+    fn swap_dots(canvas: &mut Canvas, to_remove: &mut Vec<Dot>, to_restore: &mut Vec<Dot>) {
+        // dots may have moved since the edit, so they are taken out wherever they are now
+        let mut removed = Vec::with_capacity(to_remove.len());
+        for dot in to_remove.iter() {
+            removed.extend(canvas.remove_dot(dot.id));
+        }
+        canvas.write_dots_to_grid();
+
+        // pixels may have been filled since the edit, dots that no longer fit are lost
+        let restored = to_restore
+            .drain(..)
+            .filter(|dot| canvas.restore_dot(*dot))
+            .collect();
+
+        *to_remove = removed;
+        *to_restore = restored;
+    }
+}
+
+/// Undo and redo stacks of canvas edits. The oldest edits are forgotten once the
+/// history holds more than `memory_budget` bytes.
+pub struct History {
+    undo_stack: VecDeque<CanvasEdit>,
+    redo_stack: Vec<CanvasEdit>,
+    memory_budget: usize,
+    memory_used: usize,
+}
+impl History {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            memory_budget,
+            memory_used: 0,
+        }
+    }
+
+    /// Saves a finished edit, anything that was undone before it can no longer be redone
+    pub fn push(&mut self, edit: CanvasEdit) {
+        if edit.is_empty() {
+            return;
+        }
+        for undone in self.redo_stack.drain(..) {
+            self.memory_used -= undone.size_in_bytes();
+        }
+        self.memory_used += edit.size_in_bytes();
+        self.undo_stack.push_back(edit);
+        self.enforce_budget();
+    }
+
+    /// Reverts the most recent edit, returns false when there is nothing to undo
+    pub fn undo(&mut self, canvas: &mut Canvas) -> bool {
+        let Some(mut edit) = self.undo_stack.pop_back() else {
+            return false;
+        };
+        self.memory_used -= edit.size_in_bytes();
+        edit.undo(canvas);
+        self.memory_used += edit.size_in_bytes();
+        self.redo_stack.push(edit);
+        true
+    }
+
+    /// Applies the most recently undone edit again, returns false when there is nothing to redo
+    pub fn redo(&mut self, canvas: &mut Canvas) -> bool {
+        let Some(mut edit) = self.redo_stack.pop() else {
+            return false;
+        };
+        self.memory_used -= edit.size_in_bytes();
+        edit.redo(canvas);
+        self.memory_used += edit.size_in_bytes();
+        self.undo_stack.push_back(edit);
+        true
+    }
+
+    /// Forgets every edit, used when the canvas changes in a way that can't be undone
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.memory_used = 0;
+    }
+
+    fn enforce_budget(&mut self) {
+        while self.memory_used > self.memory_budget {
+            match self.undo_stack.pop_front() {
+                Some(oldest) => self.memory_used -= oldest.size_in_bytes(),
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{CanvasEdit, History};
+    use crate::{
+        game::{
+            canvas::{Canvas, Dot},
+            config::GameConfig,
+            material::Material,
+            math::Vec2,
+        },
+        rendering::glsl_types::Resolution,
+    };
+
+    fn setup_canvas() -> Canvas {
+        Canvas::new(Resolution {
+            width: 20,
+            height: 20,
+        })
+    }
+
+    fn stroke(canvas: &mut Canvas, coord: Vec2<f64>) -> CanvasEdit {
+        let added = canvas.spawn_circle_of_dots(2., coord, Material::Sand);
+        canvas.write_dots_to_grid();
+        CanvasEdit {
+            added,
+            removed: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo_stroke() {
+        let mut canvas = setup_canvas();
+        let mut history = History::new(usize::MAX);
+        let empty_hash = canvas.state_hash();
+        history.push(stroke(&mut canvas, Vec2::new(5., 5.)));
+        let stroke_hash = canvas.state_hash();
+
+        assert!(history.undo(&mut canvas));
+        assert_eq!(empty_hash, canvas.state_hash());
+        assert!(history.redo(&mut canvas));
+        assert_eq!(stroke_hash, canvas.state_hash());
+        assert!(!history.redo(&mut canvas));
+    }
+
+    #[test]
+    fn test_undo_erase_restores_same_ids() {
+        let mut canvas = setup_canvas();
+        let mut history = History::new(usize::MAX);
+        history.push(stroke(&mut canvas, Vec2::new(5., 5.)));
+        let stroke_hash = canvas.state_hash();
+        let removed = canvas.erase_circle_of_dots(1., Vec2::new(5., 5.));
+        canvas.write_dots_to_grid();
+        assert!(!removed.is_empty());
+        history.push(CanvasEdit {
+            added: Vec::new(),
+            removed,
+        });
+
+        assert!(history.undo(&mut canvas));
+        assert_eq!(stroke_hash, canvas.state_hash());
+    }
+
+    #[test]
+    fn test_undo_keeps_id_generator_consistent() {
+        let mut canvas = setup_canvas();
+        let mut history = History::new(usize::MAX);
+        history.push(stroke(&mut canvas, Vec2::new(5., 5.)));
+        history.push(stroke(&mut canvas, Vec2::new(15., 15.)));
+        history.undo(&mut canvas);
+        history.undo(&mut canvas);
+        history.redo(&mut canvas);
+        history.redo(&mut canvas);
+
+        // a new dot must not reuse an id that was handed back by redo
+        let id = canvas
+            .spawn_dot(Material::Dirt, Vec2::new(0., 19.), Vec2::new(0., 0.))
+            .unwrap();
+        let edit_ids: Vec<_> = history
+            .undo_stack
+            .iter()
+            .flat_map(|edit| edit.added.iter().map(|dot| dot.id))
+            .collect();
+        assert!(!edit_ids.contains(&id));
+    }
+
+    #[test]
+    fn test_undo_follows_moved_dots() {
+        let mut canvas = setup_canvas();
+        let mut history = History::new(usize::MAX);
+        history.push(stroke(&mut canvas, Vec2::new(5., 5.)));
+        let empty_hash = {
+            let canvas = setup_canvas();
+            canvas.state_hash()
+        };
+        for _ in 0..10 {
+            canvas.calculate_physics(std::time::Duration::from_millis(50), &GameConfig::default());
+            canvas.write_dots_to_grid();
+        }
+
+        history.undo(&mut canvas);
+        assert_eq!(empty_hash, canvas.state_hash());
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let mut canvas = setup_canvas();
+        let mut history = History::new(usize::MAX);
+        history.push(stroke(&mut canvas, Vec2::new(5., 5.)));
+        history.undo(&mut canvas);
+        history.push(stroke(&mut canvas, Vec2::new(15., 15.)));
+
+        assert!(!history.redo(&mut canvas));
+    }
+
+    #[test]
+    fn test_oldest_edits_dropped_over_budget() {
+        let mut canvas = setup_canvas();
+        let first = stroke(&mut canvas, Vec2::new(5., 5.));
+        let budget = first.size_in_bytes() + mem::size_of::<Dot>();
+        let mut history = History::new(budget);
+        history.push(first);
+        history.push(stroke(&mut canvas, Vec2::new(15., 15.)));
+
+        assert_eq!(1, history.undo_stack.len());
+        assert!(history.memory_used <= budget);
+        assert!(history.undo(&mut canvas));
+        assert!(!history.undo(&mut canvas));
+    }
+}
@@ -0,0 +1,379 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::Path,
+};
+
+use super::material::Material;
+
+// keys whose names are longer than one character, letters, digits and punctuation are named by themselves
+const NAMED_KEYS: [&str; 41] = [
+    "space", "enter", "escape", "tab", "backspace", "delete", "insert", "home", "end", "pageup",
+    "pagedown", "up", "down", "left", "right", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9",
+    "f10", "f11", "f12", "numpad0", "numpad1", "numpad2", "numpad3", "numpad4", "numpad5",
+    "numpad6", "numpad7", "numpad8", "numpad9", "numpad+", "numpad-", "numpad*", "numpad/",
+];
+// single character keys that aren't letters or digits
+const PUNCTUATION_KEYS: &str = "[],.-=+/;'`\\";
+
+// bindings of a game without a bindings file, a bindings file only has to list what it changes
+const DEFAULT_BINDINGS: [(&str, Action); 30] = [
+    ("1", Action::SelectMaterial(Material::Sand)),
+    ("2", Action::SelectMaterial(Material::Dirt)),
+    ("3", Action::SelectMaterial(Material::Blue)),
+    ("4", Action::SelectMaterial(Material::Orange)),
+    ("e", Action::ToggleTool),
+    ("space", Action::TogglePause),
+    ("n", Action::Step),
+    ("up", Action::FasterTime),
+    ("down", Action::SlowerTime),
+    ("=", Action::ZoomIn),
+    ("+", Action::ZoomIn),
+    ("numpad+", Action::ZoomIn),
+    ("-", Action::ZoomOut),
+    ("numpad-", Action::ZoomOut),
+    ("0", Action::ResetCamera),
+    ("]", Action::GrowCanvas),
+    ("[", Action::ShrinkCanvas),
+    (".", Action::GrowCanvasRescaled),
+    (",", Action::ShrinkCanvasRescaled),
+    ("ctrl+z", Action::Undo),
+    ("ctrl+shift+z", Action::Redo),
+    ("delete", Action::ClearCanvas),
+    ("f5", Action::QuickSave),
+    ("f9", Action::QuickLoad),
+    ("f12", Action::Screenshot),
+    ("l", Action::PlaceLight),
+    ("shift+l", Action::ClearLights),
+    ("ctrl+r", Action::ReloadConfig),
+    ("f3", Action::TogglePhysicsLog),
+    ("f4", Action::ExportProfile),
+];
+
+/// Modifier keys held down, independent of the windowing library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+/// Mouse buttons and modifier keys that are held down, any number of them at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MouseState {
+    pub left: bool,
+    pub right: bool,
+    /// Drags the camera around
+    pub middle: bool,
+    pub modifiers: Modifiers,
+}
+
+/// Key pressed together with modifiers. Keys are named the way bindings files name them,
+/// e.g. `a`, `7`, `[`, `space` or `f12`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: String,
+    pub modifiers: Modifiers,
+}
+impl KeyChord {
+    pub fn new(key: &str, modifiers: Modifiers) -> Self {
+        Self {
+            key: key.to_string(),
+            modifiers,
+        }
+    }
+
+    /// Parses modifiers and a key joined by `+`, like `ctrl+shift+z` or `ctrl++`
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut modifiers = Modifiers::default();
+        let mut key = text.trim();
+        loop {
+            if let Some(rest) = key.strip_prefix("ctrl+") {
+                modifiers.ctrl = true;
+                key = rest;
+            } else if let Some(rest) = key.strip_prefix("shift+") {
+                modifiers.shift = true;
+                key = rest;
+            } else if let Some(rest) = key.strip_prefix("alt+") {
+                modifiers.alt = true;
+                key = rest;
+            } else {
+                break;
+            }
+        }
+        is_key_name(key).then(|| Self::new(key, modifiers))
+    }
+}
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "alt+")?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+fn is_key_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.is_ascii_lowercase() || c.is_ascii_digit() || PUNCTUATION_KEYS.contains(c),
+        _ => NAMED_KEYS.contains(&key),
+    }
+}
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Material the left mouse button paints with
+    SelectMaterial(Material),
+    ToggleTool,
+    TogglePause,
+    Step,
+    FasterTime,
+    SlowerTime,
+    ZoomIn,
+    ZoomOut,
+    ResetCamera,
+    GrowCanvas,
+    ShrinkCanvas,
+    /// Grows the canvas and scales its content along with it
+    GrowCanvasRescaled,
+    ShrinkCanvasRescaled,
+    Undo,
+    Redo,
+    ClearCanvas,
+    QuickSave,
+    QuickLoad,
+    Screenshot,
+    PlaceLight,
+    ClearLights,
+    /// Reads the config file again and applies it along with the command line overrides
+    ReloadConfig,
+    /// Turns logging of physics diagnostics on or off
+    TogglePhysicsLog,
+    /// Starts profiling frames, or writes out the profile once it is running
+    ExportProfile,
+}
+impl Action {
+    /// Parses the name of an action as written in bindings files, `select-material` takes a material name
+    pub fn parse(text: &str) -> Option<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let action = match words.as_slice() {
+            ["select-material", name] => match Material::from_name(name)? {
+                Material::EmptySpace => return None,
+                material => Action::SelectMaterial(material),
+            },
+            ["toggle-tool"] => Action::ToggleTool,
+            ["toggle-pause"] => Action::TogglePause,
+            ["step"] => Action::Step,
+            ["faster-time"] => Action::FasterTime,
+            ["slower-time"] => Action::SlowerTime,
+            ["zoom-in"] => Action::ZoomIn,
+            ["zoom-out"] => Action::ZoomOut,
+            ["reset-camera"] => Action::ResetCamera,
+            ["grow-canvas"] => Action::GrowCanvas,
+            ["shrink-canvas"] => Action::ShrinkCanvas,
+            ["grow-canvas-rescaled"] => Action::GrowCanvasRescaled,
+            ["shrink-canvas-rescaled"] => Action::ShrinkCanvasRescaled,
+            ["undo"] => Action::Undo,
+            ["redo"] => Action::Redo,
+            ["clear-canvas"] => Action::ClearCanvas,
+            ["quick-save"] => Action::QuickSave,
+            ["quick-load"] => Action::QuickLoad,
+            ["screenshot"] => Action::Screenshot,
+            ["place-light"] => Action::PlaceLight,
+            ["clear-lights"] => Action::ClearLights,
+            ["reload-config"] => Action::ReloadConfig,
+            ["toggle-physics-log"] => Action::TogglePhysicsLog,
+            ["export-profile"] => Action::ExportProfile,
+            _ => return None,
+        };
+        Some(action)
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+impl fmt::Display for BindingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(e) => write!(f, "could not read bindings file: {}", e),
+            BindingsError::Parse { line, message } => {
+                write!(f, "bindings file line {}: {}", line, message)
+            }
+        }
+    }
+}
+impl From<io::Error> for BindingsError {
+    fn from(e: io::Error) -> Self {
+        BindingsError::Io(e)
+    }
+}
+
+/// Which action each key chord triggers
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    actions: HashMap<KeyChord, Action>,
+}
+impl Bindings {
+    /// Default bindings overridden by a file with one `chord = action` entry per line, `#` starts a comment.
+    /// `chord = none` removes the default binding of a chord.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, BindingsError> {
+        let mut bindings = Self::default();
+        bindings.apply_overrides(&fs::read_to_string(path)?)?;
+        Ok(bindings)
+    }
+
+    fn apply_overrides(&mut self, text: &str) -> Result<(), BindingsError> {
+        for (i, line) in text.lines().enumerate() {
+            let parse_error = |message: String| BindingsError::Parse {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            // the key itself can be `=`, so split on the last one
+            let Some((chord, action)) = line.rsplit_once('=') else {
+                return Err(parse_error("expected 'chord = action'".to_string()));
+            };
+            let (chord, action) = (chord.trim(), action.trim());
+            let chord = KeyChord::parse(chord)
+                .ok_or_else(|| parse_error(format!("unknown key '{}'", chord)))?;
+            if action == "none" {
+                self.actions.remove(&chord);
+                continue;
+            }
+            let action = Action::parse(action)
+                .ok_or_else(|| parse_error(format!("unknown action '{}'", action)))?;
+            self.actions.insert(chord, action);
+        }
+        Ok(())
+    }
+
+    /// Action bound to a chord. A chord with shift that isn't bound falls back to the same chord
+    /// without shift, so holding shift doesn't get in the way of keys like `=`.
+    pub fn action_for(&self, chord: &KeyChord) -> Option<Action> {
+        if let Some(action) = self.actions.get(chord) {
+            return Some(*action);
+        }
+        if !chord.modifiers.shift {
+            return None;
+        }
+        let modifiers = Modifiers {
+            shift: false,
+            ..chord.modifiers
+        };
+        self.actions
+            .get(&KeyChord::new(&chord.key, modifiers))
+            .copied()
+    }
+}
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            actions: DEFAULT_BINDINGS
+                .iter()
+                .map(|(chord, action)| (KeyChord::parse(chord).unwrap(), *action))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Bindings, BindingsError, KeyChord, Modifiers};
+    use crate::game::material::Material;
+
+    const CTRL: Modifiers = Modifiers {
+        ctrl: true,
+        shift: false,
+        alt: false,
+    };
+
+    #[test]
+    fn test_parse_key_chords() {
+        assert_eq!(Some(KeyChord::new("z", CTRL)), KeyChord::parse("ctrl+z"));
+        assert_eq!(Some(KeyChord::new("+", CTRL)), KeyChord::parse("ctrl++"));
+        assert_eq!("ctrl+shift+f5", KeyChord::parse("shift+ctrl+f5").unwrap().to_string());
+        assert_eq!(None, KeyChord::parse("ctrl+"));
+        assert_eq!(None, KeyChord::parse("hyper+z"));
+        assert_eq!(None, KeyChord::parse("Z"));
+    }
+
+    #[test]
+    fn test_default_bindings() {
+        let bindings = Bindings::default();
+        assert_eq!(
+            Some(Action::SelectMaterial(Material::Dirt)),
+            bindings.action_for(&KeyChord::parse("2").unwrap())
+        );
+        assert_eq!(Some(Action::Undo), bindings.action_for(&KeyChord::parse("ctrl+z").unwrap()));
+        assert_eq!(
+            Some(Action::Redo),
+            bindings.action_for(&KeyChord::parse("ctrl+shift+z").unwrap())
+        );
+        // shift isn't bound with `=`, so it is ignored
+        assert_eq!(Some(Action::ZoomIn), bindings.action_for(&KeyChord::parse("shift+=").unwrap()));
+        assert_eq!(None, bindings.action_for(&KeyChord::parse("q").unwrap()));
+    }
+
+    #[test]
+    fn test_only_shift_falls_back_to_the_bare_key() {
+        let bindings = Bindings::default();
+        let action = |chord: &str| bindings.action_for(&KeyChord::parse(chord).unwrap());
+        assert_eq!(Some(Action::TogglePause), action("shift+space"));
+        // ctrl+shift+r isn't bound, so it is ctrl+r
+        assert_eq!(Some(Action::ReloadConfig), action("ctrl+shift+r"));
+        assert_eq!(None, action("ctrl+shift+space"));
+        assert_eq!(None, action("ctrl+space"));
+        assert_eq!(None, action("alt+space"));
+        assert_eq!(None, action("ctrl+2"));
+        assert_eq!(None, action("alt+="));
+        assert_eq!(None, action("ctrl+alt+shift+="));
+    }
+
+    #[test]
+    fn test_file_overrides_defaults() {
+        let mut bindings = Bindings::default();
+        bindings
+            .apply_overrides(
+                "# left handed\nq = select-material sand\n1 = none\n= = zoom-out\nctrl+s = quick-save # as usual\n",
+            )
+            .unwrap();
+
+        let action = |chord: &str| bindings.action_for(&KeyChord::parse(chord).unwrap());
+        assert_eq!(Some(Action::SelectMaterial(Material::Sand)), action("q"));
+        assert_eq!(None, action("1"));
+        assert_eq!(Some(Action::ZoomOut), action("="));
+        assert_eq!(Some(Action::QuickSave), action("ctrl+s"));
+        assert_eq!(Some(Action::QuickSave), action("f5"));
+    }
+
+    #[test]
+    fn test_bad_bindings_line_is_reported() {
+        let mut bindings = Bindings::default();
+        match bindings.apply_overrides("q = step\nq step\n") {
+            Err(BindingsError::Parse { line: 2, .. }) => (),
+            result => panic!("Expected error on line 2 -> {:?}", result),
+        }
+        match bindings.apply_overrides("q = select-material empty\n") {
+            Err(BindingsError::Parse { line: 1, .. }) => (),
+            result => panic!("Expected unknown action error -> {:?}", result),
+        }
+        match bindings.apply_overrides("mouse4 = step\n") {
+            Err(BindingsError::Parse { line: 1, .. }) => (),
+            result => panic!("Expected unknown key error -> {:?}", result),
+        }
+    }
+}
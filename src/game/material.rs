@@ -1,4 +1,4 @@
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Material {
     EmptySpace = 0x00,
     Sand = 0x01,
@@ -7,27 +7,91 @@ pub enum Material {
     Orange = 0x04,
 }
 impl Material {
+    pub const ALL: [Material; 5] = [
+        Material::EmptySpace,
+        Material::Sand,
+        Material::Dirt,
+        Material::Blue,
+        Material::Orange,
+    ];
+
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0x00 => Some(Material::EmptySpace),
+            0x01 => Some(Material::Sand),
+            0x02 => Some(Material::Dirt),
+            0x03 => Some(Material::Blue),
+            0x04 => Some(Material::Orange),
+            _ => None,
+        }
+    }
+
+    /// Parses the lowercase name of a material, as used in config and palette files
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "empty" => Some(Material::EmptySpace),
+            "sand" => Some(Material::Sand),
+            "dirt" => Some(Material::Dirt),
+            "blue" => Some(Material::Blue),
+            "orange" => Some(Material::Orange),
+            _ => None,
+        }
+    }
+
+    /// Colour the material is drawn with as RGB, has to match `get_color` in shader.frag
+    pub fn color(&self) -> [u8; 3] {
+        match self {
+            Material::EmptySpace => [0x00, 0x00, 0x00],
+            Material::Sand => [0xd7, 0xc9, 0xaa],
+            Material::Dirt => [0x56, 0x41, 0x38],
+            Material::Blue => [0x36, 0xc9, 0xc6],
+            Material::Orange => [0xc0, 0x32, 0x21],
+        }
+    }
+
+    /// How far a dot's colour can stray from the material colour, as a hue shift and a lightness change.
+    /// Has to match `get_variation` in shader.frag
+    pub fn color_variation(&self) -> [f32; 2] {
+        match self {
+            Material::EmptySpace => [0., 0.],
+            Material::Sand => [0.03, 0.08],
+            Material::Dirt => [0.02, 0.12],
+            Material::Blue => [0.02, 0.05],
+            Material::Orange => [0.03, 0.05],
+        }
+    }
+
     pub fn properties(&self) -> MaterialProperties {
         match self {
             Material::EmptySpace => MaterialProperties {
                 drag: 0.,
                 bounce: 0.,
+                emissive: false,
+                opacity: 0.,
             },
             Material::Sand => MaterialProperties {
                 drag: 0.,
                 bounce: 0.,
+                emissive: false,
+                opacity: 0.4,
             },
             Material::Dirt => MaterialProperties {
                 drag: 0.,
                 bounce: 0.,
+                emissive: false,
+                opacity: 1.,
             },
             Material::Blue => MaterialProperties {
                 drag: 0.,
                 bounce: 0.,
+                emissive: false,
+                opacity: 0.15,
             },
             Material::Orange => MaterialProperties {
                 drag: 0.,
                 bounce: 0.,
+                emissive: false,
+                opacity: 0.6,
             },
         }
     }
@@ -42,4 +106,12 @@ pub struct MaterialProperties {
     /// Higher means more bounce
     /// Range is 0.0 - 1.0 inclusive
     pub bounce: f64,
+
+    /// Emissive materials give off light and glow when bloom is on
+    /// Has to match `get_emission` in shader.frag
+    pub emissive: bool,
+
+    /// Share of light a single dot of the material blocks
+    /// Range is 0.0 - 1.0 inclusive, 1.0 blocks all light
+    pub opacity: f64,
 }
@@ -0,0 +1,70 @@
+use std::hash::Hasher;
+
+// FNV-1a parameters for 64 bit hashes
+const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a hash, unlike `DefaultHasher` it gives the same result on every Rust release and platform.
+/// Numbers are hashed as little endian bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(OFFSET_BASIS)
+    }
+}
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::Fnv1a;
+
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = Fnv1a::default();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_known_hashes() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, hash(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hash(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, hash(b"foobar"));
+    }
+
+    #[test]
+    fn test_numbers_are_little_endian() {
+        let mut hasher = Fnv1a::default();
+        hasher.write_u32(0x0403_0201);
+        assert_eq!(hash(&[1, 2, 3, 4]), hasher.finish());
+    }
+}
@@ -1,4 +1,6 @@
+mod fnv;
 mod vec2;
 
 pub mod rng;
+pub use fnv::Fnv1a;
 pub use vec2::Vec2;
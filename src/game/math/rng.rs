@@ -1,16 +1,48 @@
-use std::ops::Range;
+use std::{cell::Cell, ops::Range};
 
 #[cfg(not(test))]
 use std::time::{SystemTime, UNIX_EPOCH};
 
+thread_local! {
+    // state of the seeded generator, falls back to the clock when no seed is set
+    static STATE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Makes every following random number on this thread come from a repeatable sequence
+pub fn set_seed(seed: u64) {
+    // xorshift gets stuck on 0, so swap it for an arbitrary non zero number
+    let seed = if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed };
+    STATE.with(|state| state.set(Some(seed)));
+}
+
+/// Seed based on the current time, for when the player didn't ask for a specific one
+pub fn clock_seed() -> u64 {
+    now_in_micros() as u64
+}
+
 pub fn rand_f64(range: Range<f64>) -> f64 {
-    let rand_0_to_1 = (now_in_micros() % 1_000) as f64 / 1_000.;
+    let rand_0_to_1 = (next_entropy() % 1_000) as f64 / 1_000.;
     let diff = range.end - range.start;
     rand_0_to_1 * diff + range.start
 }
 
+// nothing in the simulation flips a coin at the moment, only its test uses it
+#[cfg_attr(not(test), allow(dead_code))]
 pub fn rand_bool() -> bool {
-    (now_in_micros() / 1_000) % 2 == 0
+    (next_entropy() / 1_000).is_multiple_of(2)
+}
+
+fn next_entropy() -> u128 {
+    STATE.with(|state| match state.get() {
+        Some(mut x) => {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            state.set(Some(x));
+            x as u128
+        }
+        None => now_in_micros(),
+    })
 }
 
 #[cfg(not(test))]
@@ -27,7 +59,7 @@ fn now_in_micros() -> u128 {
 
 #[cfg(test)]
 mod tests {
-    use super::{rand_bool, rand_f64};
+    use super::{rand_bool, rand_f64, set_seed};
 
     #[test]
     fn test_rand_f64() {
@@ -36,8 +68,19 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_rand_bool() {
         let b: bool = rand_bool();
         assert_eq!(b, false);
     }
+
+    #[test]
+    fn test_same_seed_gives_same_numbers() {
+        set_seed(42);
+        let first: Vec<f64> = (0..10).map(|_| rand_f64((0.)..1.)).collect();
+        set_seed(42);
+        let second: Vec<f64> = (0..10).map(|_| rand_f64((0.)..1.)).collect();
+        assert_eq!(first, second);
+        assert!(first.iter().any(|n| *n != first[0]), "Seeded numbers never changed");
+    }
 }
@@ -3,7 +3,6 @@ use std::{
     hash::Hash,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign},
 };
-use winit::dpi::{PhysicalPosition, PhysicalSize};
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct Vec2<T> {
@@ -19,7 +18,7 @@ impl<T> Vec2<T> {
     where
         T: Copy + PartialOrd,
     {
-        let mut copy_self = self.clone();
+        let mut copy_self = *self;
         if let Some(min) = min {
             copy_self.x = if min.x > copy_self.x {
                 min.x
@@ -92,6 +91,11 @@ impl Vec2<f64> {
         (self.y.atan2(self.x).to_degrees() + 360.) % 360.
     }
 }
+impl Vec2<u32> {
+    pub fn into_f64(&self) -> Vec2<f64> {
+        Vec2::new(self.x as f64, self.y as f64)
+    }
+}
 impl Vec2<usize> {
     pub fn into_f64(&self) -> Vec2<f64> {
         Vec2::new(self.x as f64, self.y as f64)
@@ -114,44 +118,6 @@ impl Vec2<isize> {
         Self::new(self.x + 1, self.y)
     }
 }
-impl<T> From<PhysicalPosition<T>> for Vec2<T> {
-    fn from(value: PhysicalPosition<T>) -> Self {
-        Self {
-            x: value.x,
-            y: value.y,
-        }
-    }
-}
-impl<T> From<&PhysicalPosition<T>> for Vec2<T>
-where
-    T: Copy,
-{
-    fn from(value: &PhysicalPosition<T>) -> Self {
-        Self {
-            x: value.x,
-            y: value.y,
-        }
-    }
-}
-impl<T> From<PhysicalSize<T>> for Vec2<T> {
-    fn from(value: PhysicalSize<T>) -> Self {
-        Self {
-            x: value.width,
-            y: value.height,
-        }
-    }
-}
-impl<T> From<&PhysicalSize<T>> for Vec2<T>
-where
-    T: Copy,
-{
-    fn from(value: &PhysicalSize<T>) -> Self {
-        Self {
-            x: value.width,
-            y: value.height,
-        }
-    }
-}
 impl<T> From<Resolution> for Vec2<T>
 where
     T: From<i32>,
@@ -259,7 +225,7 @@ where
         self.y += rhs.y;
     }
 }
-impl<'a, T> Add for &'a Vec2<T>
+impl<T> Add for &Vec2<T>
 where
     T: Add + Copy,
 {
@@ -325,7 +291,7 @@ where
         self.y /= rhs.y;
     }
 }
-impl<'a, T> Div for &'a Vec2<T>
+impl<T> Div for &Vec2<T>
 where
     T: Div + Copy,
 {
@@ -367,7 +333,7 @@ where
         self.y -= rhs.y;
     }
 }
-impl<'a, T> Sub for &'a Vec2<T>
+impl<T> Sub for &Vec2<T>
 where
     T: Sub + Copy,
 {
@@ -433,7 +399,7 @@ where
         self.y *= rhs.y;
     }
 }
-impl<'a, T> Mul for &'a Vec2<T>
+impl<T> Mul for &Vec2<T>
 where
     T: Mul + Copy,
 {
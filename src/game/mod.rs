@@ -2,20 +2,48 @@ use std::time::Duration;
 
 use crate::rendering::glsl_types::Resolution;
 
+pub mod camera;
 pub mod canvas;
+pub mod config;
 mod global_game_object;
+pub mod history;
+pub mod input;
 pub mod material;
 pub mod math;
+pub mod replay;
+pub mod tool;
+pub mod view_transform;
 
-pub use global_game_object::Game;
+pub use global_game_object::{FrameInput, Game};
+use material::Material;
 use math::Vec2;
 
-// canvas resolution is the size of the game world in pixels
+// canvas resolution is the size of the game world in pixels, unless the config sets another one
 const INITIAL_CANVAS_RESOLUTION: Resolution = Resolution {
     height: 500,
     width: 500,
 };
 
+// amount of pixels the canvas grows or shrinks by when resized while the game runs
+const CANVAS_RESIZE_STEP: i32 = 100;
+
+// smallest width or height the canvas can be resized to
+const MIN_CANVAS_SIZE: i32 = 100;
+
+// largest amount of window pixels a single canvas pixel can take up when zoomed in
+const MAX_CAMERA_SCALE: f64 = 32.;
+
+// most memory in bytes that undo history can hold before the oldest edits are forgotten
+const HISTORY_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+
+// simulated time that passes when stepping a single tick while paused
+pub const SINGLE_STEP_DURATION: Duration = Duration::from_micros(16_667);
+
+// speeds the simulation can run at, as a multiplier of real time
+const TIME_SCALES: [f64; 7] = [0.1, 0.25, 0.5, 1., 1.5, 2., 4.];
+
+// the values below are defaults that the config can change while the game runs
+
 // gravity of every material in the game in pixels per second ^2
 const GRAVITY: Vec2<f64> = Vec2 { x: 0., y: 100. };
 
@@ -28,3 +56,11 @@ const FRICTION: f64 = 0.0;
 
 // size of cursor that spawns dots; this is the radius of the cursor
 const CURSOR_SIZE: f64 = 5.;
+
+// material the left mouse button paints with until another one is selected
+const INITIAL_MATERIAL: Material = Material::Orange;
+// material the right mouse button always paints with
+const RIGHT_BUTTON_MATERIAL: Material = Material::Blue;
+
+// file the canvas is quick saved to and loaded from, a PNG of its materials that can be edited like any other import
+const QUICKSAVE_PATH: &str = "quicksave.png";
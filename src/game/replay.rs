@@ -0,0 +1,615 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::Duration,
+};
+
+use crate::rendering::glsl_types::Resolution;
+
+use super::{
+    canvas::{Anchor, ResizeMode},
+    config::GameConfig,
+    input::MouseState,
+    material::Material,
+    math::Vec2,
+    tool::Tool,
+    FrameInput,
+};
+
+// first line of every log is this followed by the format version
+const HEADER: &str = "unknown-game-replay";
+// bump whenever an event is added or changed, or the canvas hash is calculated differently.
+// 2 added undo, redo, clear, config and import events and hashes canvases with FNV-1a,
+// 3 records the dots an import made instead of the image path
+const FORMAT_VERSION: u32 = 3;
+
+/// Everything that can change the outcome of the simulation, in the order it happened
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Frame(FrameInput),
+    SetPaused(bool),
+    Step,
+    SetTimeScale(f64),
+    Resize(Resolution, ResizeMode),
+    Undo,
+    Redo,
+    Clear,
+    /// Every setting is written out, settings missing from a log keep their default values
+    SetConfig(GameConfig),
+    /// Material and position of every dot an import spawned, in the order they were spawned
+    Import(Vec<(Material, Vec2<f64>)>),
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    HashMismatch { expected: u64, actual: u64 },
+}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "could not access replay file: {}", e),
+            ReplayError::Parse { line, message } => {
+                write!(f, "replay file is invalid on line {}: {}", line, message)
+            }
+            ReplayError::HashMismatch { expected, actual } => write!(
+                f,
+                "replay did not reproduce the recording, canvas hash is {:016x} but should be {:016x}",
+                actual, expected
+            ),
+        }
+    }
+}
+impl From<io::Error> for ReplayError {
+    fn from(value: io::Error) -> Self {
+        ReplayError::Io(value)
+    }
+}
+
+/// Writes input events to a log file as they happen, one event per line
+pub struct Recorder {
+    writer: BufWriter<File>,
+    elapsed: Duration,
+}
+impl Recorder {
+    pub fn create(
+        path: impl AsRef<Path>,
+        seed: u64,
+        resolution: Resolution,
+    ) -> Result<Self, ReplayError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "{} {}", HEADER, FORMAT_VERSION)?;
+        writeln!(writer, "seed {}", seed)?;
+        writeln!(writer, "resolution {} {}", resolution.width, resolution.height)?;
+        Ok(Self {
+            writer,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    pub fn record(&mut self, event: &InputEvent) -> io::Result<()> {
+        if let InputEvent::Frame(input) = event {
+            self.elapsed += input.delta_time;
+        }
+        writeln!(self.writer, "{}", format_event(event, self.elapsed))?;
+        // flush every frame so the log survives a crash, that is usually when it is needed most
+        if let InputEvent::Frame(_) = event {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self, final_hash: u64) -> io::Result<()> {
+        writeln!(self.writer, "hash {:016x}", final_hash)?;
+        self.writer.flush()
+    }
+}
+
+/// Contents of a log file written by `Recorder`
+#[derive(Debug)]
+pub struct ReplayLog {
+    pub seed: u64,
+    pub resolution: Resolution,
+    pub events: Vec<InputEvent>,
+    /// Missing when the recording never finished, for example when the game crashed
+    pub final_hash: Option<u64>,
+}
+impl ReplayLog {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let mut log = ReplayLog {
+            seed: 0,
+            resolution: Resolution {
+                width: 0,
+                height: 0,
+            },
+            events: Vec::new(),
+            final_hash: None,
+        };
+
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let line_number = i + 1;
+            let parse_error = |message: &str| ReplayError::Parse {
+                line: line_number,
+                message: message.to_string(),
+            };
+
+            if line_number == 1 {
+                let version = line
+                    .strip_prefix(HEADER)
+                    .and_then(|version| version.trim().parse::<u32>().ok())
+                    .ok_or_else(|| parse_error("not a replay file"))?;
+                if version != FORMAT_VERSION {
+                    return Err(parse_error(&format!(
+                        "replay format version {} can't be played, this game plays version {}",
+                        version, FORMAT_VERSION
+                    )));
+                }
+                continue;
+            }
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => (),
+                ["seed", seed] => log.seed = parse(seed).map_err(|m| parse_error(&m))?,
+                ["resolution", width, height] => {
+                    log.resolution = Resolution {
+                        width: parse(width).map_err(|m| parse_error(&m))?,
+                        height: parse(height).map_err(|m| parse_error(&m))?,
+                    }
+                }
+                ["hash", hash] => {
+                    log.final_hash = Some(
+                        u64::from_str_radix(hash, 16)
+                            .map_err(|_| parse_error("hash is not a hex number"))?,
+                    )
+                }
+                _ => log
+                    .events
+                    .push(parse_event(&words).map_err(|m| parse_error(&m))?),
+            }
+        }
+
+        Ok(log)
+    }
+}
+
+fn format_event(event: &InputEvent, elapsed: Duration) -> String {
+    match event {
+        InputEvent::Frame(input) => {
+            let material = input
+                .material
+                .map_or("-".to_string(), |m| (m as u8).to_string());
+            let cursor = input
+                .cursor
+                .map_or("- -".to_string(), |c| format!("{} {}", c.x, c.y));
+            format!(
+                "frame {} {} {} {} {} {}",
+                elapsed.as_micros(),
+                input.delta_time.as_nanos(),
+                tool_name(input.tool),
+                mouse_state_name(input.mouse_state),
+                material,
+                cursor
+            )
+        }
+        InputEvent::SetPaused(is_paused) => format!("paused {}", is_paused),
+        InputEvent::Step => "step".to_string(),
+        InputEvent::SetTimeScale(time_scale) => format!("time_scale {}", time_scale),
+        InputEvent::Resize(resolution, mode) => format!(
+            "resize {} {} {}",
+            resolution.width,
+            resolution.height,
+            resize_mode_name(*mode)
+        ),
+        InputEvent::Undo => "undo".to_string(),
+        InputEvent::Redo => "redo".to_string(),
+        InputEvent::Clear => "clear".to_string(),
+        InputEvent::SetConfig(config) => {
+            let entries: Vec<String> = config
+                .entries()
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            format!("config {}", entries.join(" "))
+        }
+        InputEvent::Import(dots) => {
+            // `f64` is displayed with as many digits as it takes to read back the exact same number
+            let dots: Vec<String> = dots
+                .iter()
+                .map(|(material, position)| {
+                    format!("{} {} {}", *material as u8, position.x, position.y)
+                })
+                .collect();
+            format!("import {}", dots.join(" ")).trim_end().to_string()
+        }
+    }
+}
+
+fn parse_event(words: &[&str]) -> Result<InputEvent, String> {
+    match words {
+        ["frame", _time, delta, tool, mouse_state, material, x, y] => {
+            let material = match *material {
+                "-" => None,
+                m => Some(
+                    Material::from_u8(parse(m)?).ok_or(format!("unknown material '{}'", m))?,
+                ),
+            };
+            let cursor = match (*x, *y) {
+                ("-", "-") => None,
+                (x, y) => Some(Vec2::new(parse(x)?, parse(y)?)),
+            };
+            Ok(InputEvent::Frame(FrameInput {
+                delta_time: Duration::from_nanos(parse(delta)?),
+                tool: parse_tool(tool)?,
+                mouse_state: parse_mouse_state(mouse_state)?,
+                material,
+                cursor,
+            }))
+        }
+        ["paused", is_paused] => Ok(InputEvent::SetPaused(parse(is_paused)?)),
+        ["step"] => Ok(InputEvent::Step),
+        ["time_scale", time_scale] => Ok(InputEvent::SetTimeScale(parse(time_scale)?)),
+        ["resize", width, height, mode] => Ok(InputEvent::Resize(
+            Resolution {
+                width: parse(width)?,
+                height: parse(height)?,
+            },
+            parse_resize_mode(mode)?,
+        )),
+        ["undo"] => Ok(InputEvent::Undo),
+        ["redo"] => Ok(InputEvent::Redo),
+        ["clear"] => Ok(InputEvent::Clear),
+        ["config", entries @ ..] => {
+            let mut config = GameConfig::default();
+            for entry in entries {
+                let (key, value) = entry
+                    .split_once('=')
+                    .ok_or(format!("expected 'key=value', found '{}'", entry))?;
+                config.set(key, value)?;
+            }
+            Ok(InputEvent::SetConfig(config))
+        }
+        ["import", dots @ ..] => {
+            if dots.len() % 3 != 0 {
+                return Err("expected a material and a position for every imported dot".to_string());
+            }
+            dots.chunks_exact(3)
+                .map(|dot| {
+                    let material = Material::from_u8(parse(dot[0])?)
+                        .ok_or(format!("unknown material '{}'", dot[0]))?;
+                    Ok((material, Vec2::new(parse(dot[1])?, parse(dot[2])?)))
+                })
+                .collect::<Result<_, String>>()
+                .map(InputEvent::Import)
+        }
+        _ => Err(format!("unknown event '{}'", words.join(" "))),
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("could not parse '{}'", word))
+}
+
+fn tool_name(tool: Tool) -> &'static str {
+    match tool {
+        Tool::Brush => "brush",
+        Tool::Eraser => "eraser",
+    }
+}
+
+fn parse_tool(word: &str) -> Result<Tool, String> {
+    [Tool::Brush, Tool::Eraser]
+        .into_iter()
+        .find(|t| tool_name(*t) == word)
+        .ok_or(format!("unknown tool '{}'", word))
+}
+
+/// Held buttons and modifiers joined by `+`, like `left+shift`, or `released` when nothing is held
+fn mouse_state_name(mouse_state: MouseState) -> String {
+    let held = [
+        (mouse_state.left, "left"),
+        (mouse_state.right, "right"),
+        (mouse_state.middle, "middle"),
+        (mouse_state.modifiers.ctrl, "ctrl"),
+        (mouse_state.modifiers.shift, "shift"),
+        (mouse_state.modifiers.alt, "alt"),
+    ];
+    let names: Vec<&str> = held
+        .into_iter()
+        .filter(|(is_held, _)| *is_held)
+        .map(|(_, name)| name)
+        .collect();
+    if names.is_empty() {
+        "released".to_string()
+    } else {
+        names.join("+")
+    }
+}
+
+fn parse_mouse_state(word: &str) -> Result<MouseState, String> {
+    let mut mouse_state = MouseState::default();
+    if word == "released" {
+        return Ok(mouse_state);
+    }
+    for name in word.split('+') {
+        let held = match name {
+            "left" => &mut mouse_state.left,
+            "right" => &mut mouse_state.right,
+            "middle" => &mut mouse_state.middle,
+            "ctrl" => &mut mouse_state.modifiers.ctrl,
+            "shift" => &mut mouse_state.modifiers.shift,
+            "alt" => &mut mouse_state.modifiers.alt,
+            _ => return Err(format!("unknown mouse state '{}'", word)),
+        };
+        *held = true;
+    }
+    Ok(mouse_state)
+}
+
+const ANCHORS: [(Anchor, &str); 9] = [
+    (Anchor::TopLeft, "top-left"),
+    (Anchor::Top, "top"),
+    (Anchor::TopRight, "top-right"),
+    (Anchor::Left, "left"),
+    (Anchor::Center, "center"),
+    (Anchor::Right, "right"),
+    (Anchor::BottomLeft, "bottom-left"),
+    (Anchor::Bottom, "bottom"),
+    (Anchor::BottomRight, "bottom-right"),
+];
+
+fn resize_mode_name(mode: ResizeMode) -> String {
+    match mode {
+        ResizeMode::Rescale => "rescale".to_string(),
+        ResizeMode::Anchored(anchor) => {
+            let (_, name) = ANCHORS.iter().find(|(a, _)| *a == anchor).unwrap();
+            format!("anchored-{}", name)
+        }
+    }
+}
+
+fn parse_resize_mode(word: &str) -> Result<ResizeMode, String> {
+    if word == "rescale" {
+        return Ok(ResizeMode::Rescale);
+    }
+    word.strip_prefix("anchored-")
+        .and_then(|name| ANCHORS.iter().find(|(_, n)| *n == name))
+        .map(|(anchor, _)| ResizeMode::Anchored(*anchor))
+        .ok_or(format!("unknown resize mode '{}'", word))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, time::Duration};
+
+    use super::{
+        format_event, mouse_state_name, parse_event, parse_mouse_state, InputEvent, ReplayError,
+        ReplayLog, FORMAT_VERSION, HEADER,
+    };
+    use crate::{
+        game::{
+            canvas::ResizeMode,
+            config::GameConfig,
+            input::{Modifiers, MouseState},
+            material::Material,
+            math::Vec2,
+            tool::Tool,
+            FrameInput, Game,
+        },
+        rendering::{export::RgbaImage, shading::Shading},
+    };
+
+    fn temp_log(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("unknown-game-{}-{}.replay", name, std::process::id()))
+    }
+
+    fn play_some_input(game: &mut Game) {
+        let pressed = FrameInput {
+            delta_time: Duration::from_millis(16),
+            tool: Tool::Brush,
+            mouse_state: MouseState {
+                left: true,
+                ..Default::default()
+            },
+            material: Some(Material::Orange),
+            cursor: Some(Vec2::new(250., 100.)),
+        };
+        for _ in 0..20 {
+            game.run_frame(pressed);
+        }
+        game.set_time_scale(0.5);
+        game.run_frame(FrameInput {
+            tool: Tool::Eraser,
+            delta_time: Duration::from_millis(60),
+            ..pressed
+        });
+        game.step();
+        game.set_paused(false);
+        for _ in 0..20 {
+            game.run_frame(FrameInput {
+                mouse_state: MouseState::default(),
+                material: None,
+                cursor: None,
+                ..pressed
+            });
+        }
+    }
+
+    #[test]
+    fn test_replay_reproduces_recording() {
+        let path = temp_log("reproduce");
+        let mut game = Game::new_with_seed(GameConfig::default(), 7);
+        game.start_recording(&path).unwrap();
+        play_some_input(&mut game);
+        game.stop_recording();
+
+        let replayed = Game::replay(&path, |_, _| ()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(game.canvas.state_hash(), replayed.canvas.state_hash());
+        assert_eq!(game.frame_count(), replayed.frame_count());
+    }
+
+    #[test]
+    fn test_replay_follows_config_changes() {
+        let path = temp_log("config");
+        let mut config = GameConfig::default();
+        config.set("canvas-width", "300").unwrap();
+        config.set("friction", "0.5").unwrap();
+        let mut game = Game::new_with_seed(config, 3);
+        game.start_recording(&path).unwrap();
+        play_some_input(&mut game);
+        config.set("gravity-y", "-40").unwrap();
+        config.set("cursor-size", "2").unwrap();
+        game.set_config(config);
+        play_some_input(&mut game);
+        game.stop_recording();
+
+        let replayed = Game::replay(&path, |_, _| ()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(game.config(), replayed.config());
+        assert_eq!(game.canvas.state_hash(), replayed.canvas.state_hash());
+    }
+
+    #[test]
+    fn test_log_round_trips_events() {
+        let path = temp_log("round-trip");
+        let mut game = Game::new_with_seed(GameConfig::default(), 99);
+        game.start_recording(&path).unwrap();
+        play_some_input(&mut game);
+        game.stop_recording();
+
+        let log = ReplayLog::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(99, log.seed);
+        assert_eq!(game.canvas.resolution, log.resolution);
+        assert_eq!(Some(game.canvas.state_hash()), log.final_hash);
+        assert!(log.events.contains(&InputEvent::Step));
+        assert!(log.events.contains(&InputEvent::SetTimeScale(0.5)));
+        assert_eq!(
+            41,
+            log.events
+                .iter()
+                .filter(|e| matches!(e, InputEvent::Frame(_)))
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_import_event_round_trips() {
+        let event = InputEvent::Import(vec![
+            (Material::Sand, Vec2::new(0., 3.)),
+            (Material::Blue, Vec2::new(0.1 + 0.2, 1. / 3.)),
+        ]);
+        let line = format_event(&event, Duration::ZERO);
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        assert_eq!(event, parse_event(&words).unwrap());
+        assert_eq!(Ok(InputEvent::Import(Vec::new())), parse_event(&["import"]));
+        assert!(parse_event(&["import", "1", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_replay_doesnt_read_imported_image_again() {
+        let path = temp_log("import");
+        let image = std::env::temp_dir().join(format!("unknown-game-import-{}.png", std::process::id()));
+        let mut game = Game::new_with_seed(GameConfig::default(), 5);
+        play_some_input(&mut game);
+        RgbaImage::from_canvas(game.canvas(), Shading::FLAT)
+            .write_png(&image)
+            .unwrap();
+
+        let mut game = Game::new_with_seed(GameConfig::default(), 5);
+        game.start_recording(&path).unwrap();
+        game.import_image(&image, None, ResizeMode::Rescale).unwrap();
+        play_some_input(&mut game);
+        game.stop_recording();
+        // like a quick save made after the recording
+        fs::remove_file(&image).unwrap();
+
+        let replayed = Game::replay(&path, |_, _| ()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(game.canvas.state_hash(), replayed.canvas.state_hash());
+    }
+
+    #[test]
+    fn test_mouse_state_round_trips() {
+        let mouse_state = MouseState {
+            left: true,
+            middle: true,
+            modifiers: Modifiers {
+                shift: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!("left+middle+shift", mouse_state_name(mouse_state));
+        assert_eq!(Ok(mouse_state), parse_mouse_state("left+middle+shift"));
+        assert_eq!(Ok(MouseState::default()), parse_mouse_state("released"));
+        // logs written before several buttons were tracked
+        assert!(parse_mouse_state("right").unwrap().right);
+        assert!(parse_mouse_state("left+wheel").is_err());
+    }
+
+    #[test]
+    fn test_replay_fails_on_different_outcome() {
+        let path = temp_log("mismatch");
+        let mut game = Game::new_with_seed(GameConfig::default(), 1);
+        game.start_recording(&path).unwrap();
+        play_some_input(&mut game);
+        game.stop_recording();
+
+        // pretend the recording ended somewhere else
+        let log = fs::read_to_string(&path).unwrap();
+        let (events, _) = log.rsplit_once("hash ").unwrap();
+        fs::write(&path, format!("{}hash 0000000000000001\n", events)).unwrap();
+
+        let result = Game::replay(&path, |_, _| ());
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ReplayError::HashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_replay_reports_bad_line() {
+        let path = temp_log("bad-line");
+        fs::write(
+            &path,
+            format!(
+                "{} {}\nseed 3\nresolution 500 500\nteleport 1 2\n",
+                HEADER, FORMAT_VERSION
+            ),
+        )
+        .unwrap();
+
+        let result = ReplayLog::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ReplayError::Parse { line: 4, .. })));
+    }
+
+    #[test]
+    fn test_replay_rejects_other_versions() {
+        let path = temp_log("version");
+        for version in [FORMAT_VERSION - 1, FORMAT_VERSION + 1] {
+            fs::write(&path, format!("{} {}\nseed 3\n", HEADER, version)).unwrap();
+            assert!(matches!(
+                ReplayLog::read(&path),
+                Err(ReplayError::Parse { line: 1, .. })
+            ));
+        }
+        fs::write(&path, "seed 3\n").unwrap();
+        assert!(matches!(
+            ReplayLog::read(&path),
+            Err(ReplayError::Parse { line: 1, .. })
+        ));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,16 @@
+/// What happens to the canvas under the cursor while a mouse button is held down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// Spawns dots of the material bound to the pressed mouse button
+    Brush,
+    /// Removes every dot under the cursor
+    Eraser,
+}
+impl Tool {
+    pub fn toggled(self) -> Self {
+        match self {
+            Tool::Brush => Tool::Eraser,
+            Tool::Eraser => Tool::Brush,
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::rendering::{glsl_types::ViewUniform, lighting::Lighting, shading::Shading};
+
+use super::{camera::Camera, math::Vec2};
+
+/// Mapping between physical window pixels and canvas pixels for one frame.
+/// This is the only place the letterbox and camera maths lives, the fragment shader gets the
+/// result through `ViewUniform` and the cursor, in logical window pixels, goes through `logical_to_cell`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewTransform {
+    /// Physical window pixels per canvas pixel
+    pub scale: f64,
+    /// Physical window position of the top left corner of the canvas
+    pub origin: Vec2<f64>,
+}
+impl ViewTransform {
+    pub fn new(window_res: Vec2<f64>, canvas_res: Vec2<f64>, camera: &Camera) -> Self {
+        let scale = Self::fit_scale(window_res, canvas_res) * camera.zoom;
+        let view_center = canvas_res / 2. + camera.offset;
+        Self {
+            scale,
+            origin: window_res / 2. - view_center * scale,
+        }
+    }
+
+    /// Physical window pixels per canvas pixel when the whole canvas is letterboxed into the window
+    pub fn fit_scale(window_res: Vec2<f64>, canvas_res: Vec2<f64>) -> f64 {
+        let fit = window_res / canvas_res;
+        fit.x.min(fit.y)
+    }
+
+    pub fn window_to_canvas(&self, physical_position: Vec2<f64>) -> Vec2<f64> {
+        (physical_position - self.origin) / self.scale
+    }
+
+    pub fn canvas_to_window(&self, canvas_position: Vec2<f64>) -> Vec2<f64> {
+        canvas_position * self.scale + self.origin
+    }
+
+    /// `scale_factor` is the DPI scale factor of the window, physical pixels per logical pixel
+    pub fn logical_to_canvas(&self, logical_position: Vec2<f64>, scale_factor: f64) -> Vec2<f64> {
+        self.window_to_canvas(logical_position * scale_factor)
+    }
+
+    pub fn canvas_to_logical(&self, canvas_position: Vec2<f64>, scale_factor: f64) -> Vec2<f64> {
+        self.canvas_to_window(canvas_position) / scale_factor
+    }
+
+    /// Canvas cell that is drawn at the logical window position, cells cover `[n, n + 1)` just like in the shader
+    pub fn logical_to_cell(&self, logical_position: Vec2<f64>, scale_factor: f64) -> Vec2<isize> {
+        let canvas_position = self.logical_to_canvas(logical_position, scale_factor);
+        Vec2::new(
+            canvas_position.x.floor() as isize,
+            canvas_position.y.floor() as isize,
+        )
+    }
+
+    /// Logical window position of the center of a canvas cell
+    // nothing is drawn at a cell from the CPU side, the tests check `logical_to_cell` against it
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn cell_to_logical(&self, cell: Vec2<isize>, scale_factor: f64) -> Vec2<f64> {
+        self.canvas_to_logical(cell.into_f64() + 0.5, scale_factor)
+    }
+
+    pub fn to_uniform(self, shading: Shading, lighting: Lighting) -> ViewUniform {
+        ViewUniform {
+            origin: [self.origin.x as f32, self.origin.y as f32],
+            scale: self.scale as f32,
+            shading: shading.flags(),
+            light_cell_size: lighting.uniform_cell_size(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ViewTransform;
+    use crate::game::{camera::Camera, math::Vec2};
+
+    /// Physical window position and the canvas cell expected under it
+    type Point = ((f64, f64), (isize, isize));
+
+    struct Case {
+        name: &'static str,
+        window: (f64, f64),
+        canvas: (f64, f64),
+        points: &'static [Point],
+    }
+
+    const CASES: &[Case] = &[
+        Case {
+            name: "square window, square canvas",
+            window: (500., 500.),
+            canvas: (500., 500.),
+            points: &[((0., 0.), (0, 0)), ((499.9, 499.9), (499, 499)), ((250., 250.), (250, 250))],
+        },
+        Case {
+            name: "square window, upscaled canvas",
+            window: (1000., 1000.),
+            canvas: (500., 500.),
+            points: &[((0., 0.), (0, 0)), ((1.9, 1.9), (0, 0)), ((2., 2.), (1, 1)), ((999., 999.), (499, 499))],
+        },
+        Case {
+            name: "wide window, bars left and right",
+            window: (1000., 500.),
+            canvas: (500., 500.),
+            points: &[((249., 0.), (-1, 0)), ((250., 0.), (0, 0)), ((749., 499.), (499, 499)), ((750., 0.), (500, 0))],
+        },
+        Case {
+            name: "tall window, bars top and bottom",
+            window: (500., 1000.),
+            canvas: (500., 500.),
+            points: &[((0., 249.), (0, -1)), ((0., 250.), (0, 0)), ((499., 749.), (499, 499)), ((0., 750.), (0, 500))],
+        },
+        Case {
+            name: "tall window, wide canvas",
+            window: (400., 800.),
+            canvas: (200., 100.),
+            points: &[((0., 300.), (0, 0)), ((399., 499.), (199, 99)), ((200., 400.), (100, 50))],
+        },
+        Case {
+            name: "wide window, tall canvas",
+            window: (800., 400.),
+            canvas: (100., 200.),
+            points: &[((300., 0.), (0, 0)), ((499., 399.), (99, 199)), ((299., 0.), (-1, 0))],
+        },
+        Case {
+            name: "square window, tall canvas",
+            window: (600., 600.),
+            canvas: (100., 300.),
+            points: &[((200., 0.), (0, 0)), ((399., 599.), (99, 299))],
+        },
+    ];
+
+    #[test]
+    fn test_logical_to_cell_for_all_aspect_ratios() {
+        for case in CASES {
+            let transform = ViewTransform::new(
+                Vec2::new(case.window.0, case.window.1),
+                Vec2::new(case.canvas.0, case.canvas.1),
+                &Camera::new(),
+            );
+            for (window_pos, expected) in case.points {
+                let cell = transform.logical_to_cell(Vec2::new(window_pos.0, window_pos.1), 1.);
+                assert_eq!(
+                    Vec2::new(expected.0, expected.1),
+                    cell,
+                    "{}: window position {:?}",
+                    case.name,
+                    window_pos
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cell_to_logical_round_trips_for_all_aspect_ratios() {
+        let camera = Camera {
+            offset: Vec2::new(7., -3.),
+            zoom: 2.,
+        };
+        for case in CASES {
+            let transform = ViewTransform::new(
+                Vec2::new(case.window.0, case.window.1),
+                Vec2::new(case.canvas.0, case.canvas.1),
+                &camera,
+            );
+            for cell in [Vec2::new(0, 0), Vec2::new(13, 42), Vec2::new(99, 99)] {
+                for scale_factor in [1., 1.5, 2.] {
+                    let logical_pos = transform.cell_to_logical(cell, scale_factor);
+                    assert_eq!(
+                        cell,
+                        transform.logical_to_cell(logical_pos, scale_factor),
+                        "{} at {}x",
+                        case.name,
+                        scale_factor
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_canvas_is_centered_in_window() {
+        for case in CASES {
+            let transform = ViewTransform::new(
+                Vec2::new(case.window.0, case.window.1),
+                Vec2::new(case.canvas.0, case.canvas.1),
+                &Camera::new(),
+            );
+            let center = transform.canvas_to_window(Vec2::new(case.canvas.0, case.canvas.1) / 2.);
+            assert_eq!(Vec2::new(case.window.0, case.window.1) / 2., center, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn test_logical_positions_use_scale_factor() {
+        // a 1000x500 physical window is 500x250 logical pixels at 2x DPI
+        let transform = ViewTransform::new(
+            Vec2::new(1000., 500.),
+            Vec2::new(500., 500.),
+            &Camera::new(),
+        );
+        assert_eq!(Vec2::new(0., 0.), transform.logical_to_canvas(Vec2::new(125., 0.), 2.));
+        assert_eq!(Vec2::new(500., 500.), transform.logical_to_canvas(Vec2::new(375., 250.), 2.));
+        assert_eq!(Vec2::new(250., 125.), transform.canvas_to_logical(Vec2::new(250., 250.), 2.));
+    }
+}
@@ -0,0 +1,60 @@
+//! Falling sand simulation without any windowing or GPU code.
+//!
+//! A `Canvas` holds the dots: spawn them with `spawn_dot`, query them through `dots` and `grid`, and move
+//! them with `step`. `Game` adds player input, undo history and replays on top, `FrameExporter` and
+//! `save_screenshot` turn canvases into images and `import_png` reads them back. PNG is the only save
+//! format, it keeps the material of every pixel but not velocities, ids or lights. The modules are private,
+//! the simulation API is re-exported here and the plumbing only the game binary needs lives in
+//! `internal`.
+
+mod bench;
+mod game;
+mod profiler;
+mod rendering;
+
+pub use game::{
+    canvas::{
+        dirty::DirtyRect, Anchor, Canvas, CanvasError, Dot, ResizeMode, ResizeReport, SpawnError,
+    },
+    config::{ConfigError, ConfigSource, GameConfig},
+    input::{Modifiers, MouseState},
+    material::Material,
+    math::Vec2,
+    replay::ReplayError,
+    Game, SINGLE_STEP_DURATION,
+};
+pub use rendering::{
+    export::{save_screenshot, ExportError, FrameExporter, RgbaImage},
+    glsl_types::Resolution,
+    import::{canvas_from_rgba, import_png, ColorMapping, ImportError, ImportReport},
+    shading::Shading,
+};
+
+/// Renderer, benchmark, profiler and key binding plumbing shared with the game binary. It is not part
+/// of the simulation API and changes whenever the binary needs it to.
+#[doc(hidden)]
+pub mod internal {
+    pub use crate::bench::{
+        run as run_bench, BenchResult, CountingAllocator, Scenario as BenchScenario,
+        DEFAULT_TICKS as DEFAULT_BENCH_TICKS,
+    };
+    pub use crate::game::input::{Action, Bindings, BindingsError, KeyChord};
+    pub use crate::profiler::{
+        default_export_path as default_profile_path, enable as enable_profiler,
+        end_frame as end_profiled_frame, export_and_log as export_profile,
+        is_enabled as profiler_enabled, span as profile_span,
+    };
+    pub use crate::rendering::{
+        glsl_types::{PostPushConstants, ViewUniform},
+        lighting::{LightMap, Lighting},
+        post_processing::PostProcessing,
+        shading::CELL_BYTES,
+        software_renderer::SoftwareRenderer,
+    };
+}
+
+// the benchmark tests count allocations, the game binary installs the same allocator for every run
+// since `--bench` is only known once it is running
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: bench::CountingAllocator = bench::CountingAllocator;
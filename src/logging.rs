@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, LineWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// messages from one line of code that are let through per interval, the rest are dropped and counted
+const RATE_LIMIT_BURST: u32 = 5;
+const RATE_LIMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+// module physics diagnostics are logged from at debug level, off unless a filter names it
+pub const PHYSICS_MODULE: &str = "game::canvas";
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Most verbose level logged for each module. Modules are paths inside the crate without its name,
+/// like `game::canvas`, and the longest one that matches a message decides.
+/// Physics diagnostics stay off until `PHYSICS_MODULE` is given its own level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+}
+impl LogFilter {
+    /// Parses a comma separated list of a default level and `module=level` entries, like `warn,game::canvas=debug`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut filter = Self::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((module, level)) => {
+                    filter.set_module(module.trim(), LevelFilter::from_str(level.trim()).ok()?)
+                }
+                None => filter.default = LevelFilter::from_str(entry).ok()?,
+            }
+        }
+        Some(filter)
+    }
+
+    pub fn set_module(&mut self, module: &str, level: LevelFilter) {
+        match self.modules.iter_mut().find(|(m, _)| m == module) {
+            Some((_, old_level)) => *old_level = level,
+            None => self.modules.push((module.to_string(), level)),
+        }
+    }
+
+    pub fn level_for(&self, module: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .filter(|(m, _)| {
+                module == m || (module.starts_with(m.as_str()) && module[m.len()..].starts_with("::"))
+            })
+            .max_by_key(|(m, _)| m.len())
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            default: LevelFilter::Info,
+            modules: vec![(PHYSICS_MODULE.to_string(), LevelFilter::Info)],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CallSite {
+    interval_start: Instant,
+    logged: u32,
+    dropped: u32,
+}
+
+/// Keeps messages logged every frame from flooding the output, counted per line of code
+#[derive(Debug, Default)]
+struct RateLimiter {
+    call_sites: HashMap<(String, u32), CallSite>,
+}
+impl RateLimiter {
+    /// `None` when the message should be dropped, otherwise how many from the same place were dropped before it
+    fn check(&mut self, file: &str, line: u32, now: Instant) -> Option<u32> {
+        let call_site = self
+            .call_sites
+            .entry((file.to_string(), line))
+            .or_insert(CallSite {
+                interval_start: now,
+                logged: 0,
+                dropped: 0,
+            });
+        if now.duration_since(call_site.interval_start) >= RATE_LIMIT_INTERVAL {
+            call_site.interval_start = now;
+            call_site.logged = 0;
+        }
+        if call_site.logged >= RATE_LIMIT_BURST {
+            call_site.dropped += 1;
+            return None;
+        }
+        call_site.logged += 1;
+        Some(std::mem::take(&mut call_site.dropped))
+    }
+}
+
+struct Logger {
+    filter: RwLock<LogFilter>,
+    rate_limiter: Mutex<RateLimiter>,
+    file: Option<Mutex<LineWriter<File>>>,
+    start: Instant,
+}
+impl Logger {
+    fn write(&self, level: Level, module: &str, message: &str) {
+        // stdout is left to the results the game prints, like replay summaries and benchmarks
+        match level {
+            Level::Error => eprintln!("ERROR: {}", message),
+            Level::Warn => eprintln!("WARNING: {}", message),
+            Level::Info => eprintln!("{}", message),
+            Level::Debug | Level::Trace => eprintln!("{} {}: {}", level, module, message),
+        }
+        if let Some(file) = &self.file {
+            let elapsed = self.start.elapsed().as_secs_f64();
+            // there is nowhere left to report a failing log file
+            let _ = writeln!(
+                file.lock().unwrap(),
+                "{:10.3} {:5} {}: {}",
+                elapsed,
+                level,
+                module,
+                message
+            );
+        }
+    }
+}
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.read().unwrap().level_for(module_of(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let file = record.file().unwrap_or(record.target());
+        let line = record.line().unwrap_or(0);
+        let Some(dropped) = self.rate_limiter.lock().unwrap().check(file, line, Instant::now()) else {
+            return;
+        };
+        let module = module_of(record.target());
+        if dropped > 0 {
+            self.write(
+                record.level(),
+                module,
+                &format!("{} more like the next message were dropped", dropped),
+            );
+        }
+        self.write(record.level(), module, &record.args().to_string());
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Module path inside the crate, the crate name is left out so filters don't depend on it
+fn module_of(target: &str) -> &str {
+    target.split_once("::").map_or("", |(_, module)| module)
+}
+
+/// Routes the `log` macros to the console, and to `file` as well when one is given.
+/// Only the first call does anything.
+pub fn init(filter: LogFilter, file: Option<&Path>) -> io::Result<()> {
+    let file = match file {
+        Some(path) => Some(Mutex::new(LineWriter::new(File::create(path)?))),
+        None => None,
+    };
+    let max_level = filter.max_level();
+    let logger = LOGGER.get_or_init(|| Logger {
+        filter: RwLock::new(filter),
+        rate_limiter: Mutex::new(RateLimiter::default()),
+        file,
+        start: Instant::now(),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(max_level);
+    }
+    Ok(())
+}
+
+/// Changes what a module logs while the game runs
+#[cfg(feature = "window")]
+pub fn set_module_level(module: &str, level: LevelFilter) {
+    if let Some(logger) = LOGGER.get() {
+        let mut filter = logger.filter.write().unwrap();
+        filter.set_module(module, level);
+        log::set_max_level(filter.max_level());
+    }
+}
+
+/// Turns physics diagnostics on or off, returns whether they are on now
+#[cfg(feature = "window")]
+pub fn toggle_physics_diagnostics() -> bool {
+    let Some(logger) = LOGGER.get() else {
+        return false;
+    };
+    let is_on = logger.filter.read().unwrap().level_for(PHYSICS_MODULE) >= LevelFilter::Debug;
+    let level = if is_on {
+        LevelFilter::Info
+    } else {
+        LevelFilter::Debug
+    };
+    set_module_level(PHYSICS_MODULE, level);
+    !is_on
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use log::LevelFilter;
+
+    use super::{module_of, LogFilter, RateLimiter, RATE_LIMIT_BURST, RATE_LIMIT_INTERVAL};
+
+    #[test]
+    fn test_parse_filter() {
+        let filter = LogFilter::parse("warn, game::canvas=debug,rendering=off").unwrap();
+        assert_eq!(LevelFilter::Warn, filter.level_for("windowing::event_handler"));
+        assert_eq!(LevelFilter::Debug, filter.level_for("game::canvas"));
+        assert_eq!(LevelFilter::Debug, filter.level_for("game::canvas::physics"));
+        assert_eq!(LevelFilter::Warn, filter.level_for("game::canvases"));
+        assert_eq!(LevelFilter::Off, filter.level_for("rendering::render_engine"));
+        assert_eq!(LevelFilter::Debug, filter.max_level());
+
+        assert_eq!(Some(LogFilter::default()), LogFilter::parse(""));
+        assert_eq!(
+            LevelFilter::Info,
+            LogFilter::parse("debug").unwrap().level_for("game::canvas::physics")
+        );
+        assert_eq!(None, LogFilter::parse("loud"));
+        assert_eq!(None, LogFilter::parse("game=loud"));
+    }
+
+    #[test]
+    fn test_longest_module_wins() {
+        let mut filter = LogFilter::parse("game=error,game::canvas=trace").unwrap();
+        assert_eq!(LevelFilter::Trace, filter.level_for("game::canvas::grid"));
+        assert_eq!(LevelFilter::Error, filter.level_for("game::replay"));
+
+        filter.set_module("game::canvas", LevelFilter::Warn);
+        assert_eq!(LevelFilter::Warn, filter.level_for("game::canvas::grid"));
+    }
+
+    #[test]
+    fn test_rate_limiter_drops_repeats_and_counts_them() {
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..RATE_LIMIT_BURST {
+            assert_eq!(Some(0), limiter.check("a.rs", 1, start));
+        }
+        assert_eq!(None, limiter.check("a.rs", 1, start));
+        assert_eq!(None, limiter.check("a.rs", 1, start + Duration::from_millis(10)));
+        // other lines have their own budget
+        assert_eq!(Some(0), limiter.check("a.rs", 2, start));
+
+        assert_eq!(Some(2), limiter.check("a.rs", 1, start + RATE_LIMIT_INTERVAL));
+        assert_eq!(Some(0), limiter.check("a.rs", 1, start + RATE_LIMIT_INTERVAL));
+    }
+
+    #[test]
+    fn test_module_leaves_out_crate_name() {
+        assert_eq!("game::canvas", module_of("unknown_game::game::canvas"));
+        assert_eq!("", module_of("unknown_game"));
+    }
+}
@@ -1,21 +1,282 @@
-#![feature(map_try_insert)]
+use std::{path::PathBuf, time::Duration};
 
-use game::Game;
-use winit::event_loop::EventLoop;
+use cli::Cli;
+use log::{error, info, warn};
+use logging::LogFilter;
+use unknown_game::{
+    internal::{
+        enable_profiler, end_profiled_frame, export_profile, run_bench, CountingAllocator,
+        DEFAULT_BENCH_TICKS,
+    },
+    Anchor, ConfigSource, FrameExporter, Game, ResizeMode, Shading,
+};
 
-mod game;
-mod rendering;
+mod cli;
+mod display;
+mod logging;
+#[cfg(feature = "window")]
+mod windowed;
+#[cfg(feature = "window")]
 mod windowing;
 
+// counts allocations so `--bench` can report them, installed for every run because the option is only known
+// once the binary is running, it costs a thread local add on top of each system allocation
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+// exit code when the game fails while running, like a replay that doesn't match or a missing graphics device
+const EXIT_FAILURE: i32 = 1;
+// exit code when the command line arguments or the config they point to are wrong
+const EXIT_BAD_ARGUMENTS: i32 = 2;
+// exit code when --list-devices can't get the graphics devices, usually because there is no Vulkan driver
+#[cfg_attr(not(feature = "window"), allow(dead_code))]
+const EXIT_NO_GRAPHICS: i32 = 3;
+
+// set to print the graphics devices Vulkan can see and exit
+const LIST_DEVICES_ENV_VAR: &str = "UNKNOWN_GAME_LIST_DEVICES";
+
+// set to a file path to record every input of this session, so it can be replayed later
+const RECORD_ENV_VAR: &str = "UNKNOWN_GAME_RECORD";
+// set to a recorded file path to replay it without a window and check it ends up the same
+const REPLAY_ENV_VAR: &str = "UNKNOWN_GAME_REPLAY";
+// set while replaying to write every frame to a `.gif` file or to a directory of numbered PNGs
+const EXPORT_ENV_VAR: &str = "UNKNOWN_GAME_EXPORT";
+// set to a PNG file to start the game with its pixels as the canvas content
+const IMPORT_ENV_VAR: &str = "UNKNOWN_GAME_IMPORT";
+// set to a palette file to map the imported image's colours to materials exactly
+const IMPORT_PALETTE_ENV_VAR: &str = "UNKNOWN_GAME_IMPORT_PALETTE";
+// set to flat or a comma separated list of variation and velocity to pick the colour effects of the canvas
+const SHADING_ENV_VAR: &str = "UNKNOWN_GAME_SHADING";
+// set to a file of `key = value` lines to change the tuning values of the simulation, see `GameConfig`
+const CONFIG_ENV_VAR: &str = "UNKNOWN_GAME_CONFIG";
+// set to a default level and module levels like warn,game::canvas=debug to pick what gets logged
+const LOG_ENV_VAR: &str = "UNKNOWN_GAME_LOG";
+// set to a file path to also write everything that gets logged to it
+const LOG_FILE_ENV_VAR: &str = "UNKNOWN_GAME_LOG_FILE";
+// set to a file path to time each phase of every frame and write the profile there on exit, CSV for a .csv path
+const PROFILE_ENV_VAR: &str = "UNKNOWN_GAME_PROFILE";
+
+fn shading_from_env() -> Shading {
+    match std::env::var(SHADING_ENV_VAR) {
+        Ok(value) => Shading::parse(&value).unwrap_or_else(|| {
+            warn!(
+                "Unknown shading '{}', expected flat or a list of variation and velocity",
+                value
+            );
+            Shading::default()
+        }),
+        Err(_) => Shading::default(),
+    }
+}
+
+/// Writes every frame to `export_path` when one is given, created on the first frame so it knows the canvas resolution
+fn frame_exporter(export_path: Option<PathBuf>, shading: Shading) -> impl FnMut(&Game, Duration) {
+    let mut export_path = export_path;
+    let mut exporter: Option<FrameExporter> = None;
+    move |game, frame_time| {
+        if let Some(export_path) = export_path.take() {
+            match FrameExporter::create(&export_path, game.canvas().resolution(), shading) {
+                Ok(e) => exporter = Some(e),
+                Err(e) => warn!(
+                    "Could not export frames to '{}': {}",
+                    export_path.display(),
+                    e
+                ),
+            }
+        }
+        if let Some(e) = exporter.as_mut() {
+            if let Err(err) = e.write_frame(game.canvas(), frame_time) {
+                warn!("Stopped exporting frames: {}", err);
+                exporter = None;
+            }
+        }
+    }
+}
+
+/// Value of a command line option, or of its environment variable when the option wasn't given
+fn option_or_env(option: Option<PathBuf>, env_var: &str) -> Option<PathBuf> {
+    option.or_else(|| std::env::var_os(env_var).map(PathBuf::from))
+}
+
+fn exit_with_error(error: impl std::fmt::Display, exit_code: i32) -> ! {
+    error!("{}", error);
+    std::process::exit(exit_code);
+}
+
 fn main() {
-    let game = Game::new();
-    let event_loop = EventLoop::new();
-    let window_state = windowing::state::WindowState::new(&event_loop);
-    let render_engine = rendering::render_engine::RenderEngine::new(
-        &event_loop,
-        window_state.window.clone(),
-        &game,
-    );
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            // logging isn't set up before the arguments are known
+            eprintln!("ERROR: {}", e);
+            eprintln!("Run with --help to see every option");
+            std::process::exit(EXIT_BAD_ARGUMENTS);
+        }
+    };
+    if cli.help {
+        println!("{}", cli::HELP);
+        return;
+    }
+
+    // warned about once there is a logger to warn with
+    let mut bad_env_log_filter = None;
+    let log_filter = cli.log_filter.unwrap_or_else(|| match std::env::var(LOG_ENV_VAR) {
+        Ok(value) => LogFilter::parse(&value).unwrap_or_else(|| {
+            bad_env_log_filter = Some(value);
+            LogFilter::default()
+        }),
+        Err(_) => LogFilter::default(),
+    });
+    let log_file = option_or_env(cli.log_file, LOG_FILE_ENV_VAR);
+    if let Err(e) = logging::init(log_filter, log_file.as_deref()) {
+        eprintln!("ERROR: Could not open log file: {}", e);
+        std::process::exit(EXIT_FAILURE);
+    }
+    if let Some(value) = bad_env_log_filter {
+        warn!(
+            "Unknown log filter '{}', expected a level and module levels like warn,game::canvas=debug",
+            value
+        );
+    }
+
+    // before anything else runs, so listing devices never records, loads or benchmarks
+    if cli.list_devices || std::env::var(LIST_DEVICES_ENV_VAR).is_ok() {
+        #[cfg(feature = "window")]
+        match windowed::list_devices() {
+            Ok(devices) => {
+                for (i, name) in devices.iter().enumerate() {
+                    println!("#{} {}", i, name);
+                }
+                return;
+            }
+            Err(e) => exit_with_error(e, EXIT_NO_GRAPHICS),
+        }
+        #[cfg(not(feature = "window"))]
+        exit_with_error(
+            "This build has no window and draws with no graphics device, build it with the window feature to list them",
+            EXIT_BAD_ARGUMENTS,
+        );
+    }
+
+    if !cli.bench.is_empty() {
+        if cfg!(debug_assertions) {
+            warn!("Benchmarking a debug build, numbers from a release build are the ones worth comparing");
+        }
+        let ticks = cli.bench_ticks.unwrap_or(DEFAULT_BENCH_TICKS);
+        for scenario in cli.bench {
+            println!("{}", run_bench(scenario, ticks));
+        }
+        return;
+    }
+
+    let profile_path = option_or_env(cli.profile, PROFILE_ENV_VAR);
+    if profile_path.is_some() {
+        enable_profiler();
+    }
 
-    windowing::run_game_loop(event_loop, window_state, render_engine, game);
+    let export_path = option_or_env(cli.export, EXPORT_ENV_VAR);
+    if let Some(path) = option_or_env(cli.replay, REPLAY_ENV_VAR) {
+        let mut export_frame = frame_exporter(export_path, shading_from_env());
+        let result = Game::replay(&path, |game, frame_time| {
+            export_frame(game, frame_time);
+            end_profiled_frame();
+        });
+        if let Some(profile_path) = &profile_path {
+            export_profile(profile_path);
+        }
+        match result {
+            Ok(game) => println!(
+                "Replay of '{}' finished after {} frames",
+                path.display(),
+                game.frame_count()
+            ),
+            Err(e) => exit_with_error(e, EXIT_FAILURE),
+        }
+        return;
+    }
+
+    let mut config_source = ConfigSource {
+        path: option_or_env(cli.config, CONFIG_ENV_VAR),
+        overrides: cli.overrides,
+    };
+    if let Some(size) = cli.canvas_size {
+        config_source.overrides.push(format!("canvas-width={}", size.width));
+        config_source.overrides.push(format!("canvas-height={}", size.height));
+    }
+    let config = match config_source.load() {
+        Ok(config) => config,
+        Err(e) => exit_with_error(e, EXIT_BAD_ARGUMENTS),
+    };
+
+    let mut game = match cli.seed {
+        Some(seed) => Game::new_with_seed(config, seed),
+        None => Game::new(config),
+    };
+    if let Some(path) = option_or_env(cli.record, RECORD_ENV_VAR) {
+        if let Err(e) = game.start_recording(&path) {
+            warn!("Could not record inputs to '{}': {}", path.display(), e);
+        }
+    }
+    if let Some(path) = option_or_env(cli.load, IMPORT_ENV_VAR) {
+        let palette = option_or_env(cli.palette, IMPORT_PALETTE_ENV_VAR);
+        match game.import_image(
+            &path,
+            palette.as_deref(),
+            ResizeMode::Anchored(Anchor::TopLeft),
+        ) {
+            Ok(report) => info!("{}", report),
+            Err(e) => {
+                game.stop_recording();
+                exit_with_error(
+                    format!("Could not load '{}': {}", path.display(), e),
+                    EXIT_FAILURE,
+                );
+            }
+        }
+    }
+
+    if let Some(ticks) = cli.headless_ticks {
+        let mut on_frame = frame_exporter(export_path, shading_from_env());
+        for _ in 0..ticks {
+            let frame_time = game.tick();
+            on_frame(&game, frame_time);
+            end_profiled_frame();
+        }
+        game.stop_recording();
+        if let Some(path) = &profile_path {
+            export_profile(path);
+        }
+        println!(
+            "Ran {} ticks without a window, canvas hash is {:016x}",
+            ticks,
+            game.canvas().state_hash()
+        );
+        return;
+    }
+    if export_path.is_some() {
+        warn!("Frames are only exported with --headless or --replay");
+    }
+
+    #[cfg(feature = "window")]
+    windowed::run(
+        windowed::WindowOptions {
+            renderer: cli.renderer,
+            device: cli.device,
+            present_mode: cli.present_mode,
+            frame_cap: cli.frame_cap,
+            post_processing: cli.post_processing,
+            lighting: cli.lighting,
+        },
+        game,
+        config_source,
+        profile_path,
+    );
+    #[cfg(not(feature = "window"))]
+    {
+        game.stop_recording();
+        exit_with_error(
+            "This build has no window, run it with --headless, --replay or --bench or build it with the window feature",
+            EXIT_BAD_ARGUMENTS,
+        );
+    }
 }
@@ -0,0 +1,371 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+// frames the rolling min, mean, p99 and max are worked out over
+const ROLLING_FRAMES: usize = 300;
+// frames whose spans are kept for exporting, older ones are forgotten so a long session doesn't grow forever
+const TRACE_FRAMES: usize = 1200;
+// phase every frame is recorded as, from the end of the previous frame to the end of this one
+const FRAME_PHASE: &str = "frame";
+
+thread_local! {
+    // the game loop, physics and rendering all run on the main thread
+    static PROFILER: RefCell<Option<Profiler>> = const { RefCell::new(None) };
+}
+
+/// Starts recording spans on this thread, does nothing if it already is
+pub fn enable() {
+    PROFILER.with(|profiler| {
+        profiler.borrow_mut().get_or_insert_with(|| Profiler::new(Instant::now()));
+    });
+}
+
+pub fn is_enabled() -> bool {
+    PROFILER.with(|profiler| profiler.borrow().is_some())
+}
+
+/// Times `phase` until the returned guard is dropped. Costs a thread local lookup when profiling is off.
+pub fn span(phase: &'static str) -> Span {
+    Span {
+        phase,
+        start: is_enabled().then(Instant::now),
+    }
+}
+
+/// Call once at the end of every frame, so spans are added up per frame
+pub fn end_frame() {
+    PROFILER.with(|profiler| {
+        if let Some(profiler) = profiler.borrow_mut().as_mut() {
+            profiler.end_frame(Instant::now());
+        }
+    });
+}
+
+/// Writes every kept span to `path`, a CSV file when it ends in `.csv` and a Chrome trace otherwise.
+/// Returns the rolling statistics at the time of writing.
+pub fn export(path: impl AsRef<Path>) -> io::Result<ProfileReport> {
+    PROFILER.with(|profiler| match profiler.borrow().as_ref() {
+        Some(profiler) => {
+            profiler.export(path)?;
+            Ok(profiler.report())
+        }
+        None => Ok(ProfileReport::default()),
+    })
+}
+
+/// Exports to `path` and logs the rolling statistics
+pub fn export_and_log(path: &Path) {
+    match export(path) {
+        Ok(report) => info!("Wrote profile to '{}'\n{}", path.display(), report),
+        Err(e) => warn!("Could not write profile to '{}': {}", path.display(), e),
+    }
+}
+
+/// Timestamped file in the working directory to export to when no path was given
+pub fn default_export_path() -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(format!("unknown-game-profile-{}.json", timestamp))
+}
+
+/// Guard returned by `span`
+pub struct Span {
+    phase: &'static str,
+    /// `None` when profiling was off as the span started
+    start: Option<Instant>,
+}
+impl Drop for Span {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        let duration = start.elapsed();
+        PROFILER.with(|profiler| {
+            if let Some(profiler) = profiler.borrow_mut().as_mut() {
+                profiler.record(self.phase, start, duration);
+            }
+        });
+    }
+}
+
+/// One timed run of a phase
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpanRecord {
+    pub frame: u64,
+    pub phase: &'static str,
+    /// Since the profiler was enabled
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Spans of recent frames, and time per frame spent in each phase
+pub struct Profiler {
+    start: Instant,
+    frame: u64,
+    frame_start: Instant,
+    current_frame: Vec<SpanRecord>,
+    /// Spans of finished frames, oldest first
+    trace: VecDeque<Vec<SpanRecord>>,
+    /// Total time spent in each phase in every recent frame it ran in, phases in the order they first ran
+    rolling: Vec<(&'static str, VecDeque<Duration>)>,
+}
+impl Profiler {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            start: now,
+            frame: 0,
+            frame_start: now,
+            current_frame: Vec::new(),
+            trace: VecDeque::new(),
+            rolling: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, phase: &'static str, start: Instant, duration: Duration) {
+        self.current_frame.push(SpanRecord {
+            frame: self.frame,
+            phase,
+            start: start.saturating_duration_since(self.start),
+            duration,
+        });
+    }
+
+    pub fn end_frame(&mut self, now: Instant) {
+        self.record(FRAME_PHASE, self.frame_start, now - self.frame_start);
+
+        let mut totals: Vec<(&'static str, Duration)> = Vec::new();
+        for span in &self.current_frame {
+            match totals.iter_mut().find(|(phase, _)| *phase == span.phase) {
+                Some((_, total)) => *total += span.duration,
+                None => totals.push((span.phase, span.duration)),
+            }
+        }
+        for (phase, total) in totals {
+            let samples = match self.rolling.iter_mut().find(|(p, _)| *p == phase) {
+                Some((_, samples)) => samples,
+                None => {
+                    self.rolling.push((phase, VecDeque::new()));
+                    &mut self.rolling.last_mut().unwrap().1
+                }
+            };
+            if samples.len() == ROLLING_FRAMES {
+                samples.pop_front();
+            }
+            samples.push_back(total);
+        }
+
+        if self.trace.len() == TRACE_FRAMES {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(std::mem::take(&mut self.current_frame));
+        self.frame += 1;
+        self.frame_start = now;
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport(
+            self.rolling
+                .iter()
+                .map(|(phase, samples)| PhaseStats::from_samples(phase, samples))
+                .collect(),
+        )
+    }
+
+    fn spans(&self) -> impl Iterator<Item = &SpanRecord> {
+        self.trace.iter().flatten()
+    }
+
+    /// Chrome trace event format, opens in `chrome://tracing` or Perfetto
+    pub fn write_chrome_trace(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{{\"traceEvents\":[")?;
+        for (i, span) in self.spans().enumerate() {
+            // phase names are identifiers from the code, they never need escaping
+            writeln!(
+                writer,
+                "{}{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":{:.3},\"dur\":{:.3},\"args\":{{\"frame\":{}}}}}",
+                if i == 0 { "" } else { "," },
+                span.phase,
+                span.start.as_secs_f64() * 1e6,
+                span.duration.as_secs_f64() * 1e6,
+                span.frame
+            )?;
+        }
+        writeln!(writer, "]}}")
+    }
+
+    /// One span per line with its frame, phase, start and duration in microseconds
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "frame,phase,start_us,duration_us")?;
+        for span in self.spans() {
+            writeln!(
+                writer,
+                "{},{},{:.3},{:.3}",
+                span.frame,
+                span.phase,
+                span.start.as_secs_f64() * 1e6,
+                span.duration.as_secs_f64() * 1e6
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn export(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            self.write_csv(&mut writer)?;
+        } else {
+            self.write_chrome_trace(&mut writer)?;
+        }
+        writer.flush()
+    }
+}
+
+/// Time per frame spent in a phase over the recent frames it ran in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhaseStats {
+    pub phase: &'static str,
+    pub frames: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+impl PhaseStats {
+    fn from_samples(phase: &'static str, samples: &VecDeque<Duration>) -> Self {
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort();
+        let frames = sorted.len();
+        // nearest rank, the smallest sample that at least 99% of samples are at or below
+        let p99_rank = (frames * 99).div_ceil(100).max(1);
+        Self {
+            phase,
+            frames,
+            min: sorted.first().copied().unwrap_or_default(),
+            mean: sorted.iter().sum::<Duration>() / frames.max(1) as u32,
+            p99: sorted.get(p99_rank - 1).copied().unwrap_or_default(),
+            max: sorted.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// Rolling statistics of every phase, printed as a table in milliseconds
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProfileReport(pub Vec<PhaseStats>);
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.;
+        write!(
+            f,
+            "{:<20} {:>6} {:>8} {:>8} {:>8} {:>8}",
+            "phase (ms)", "frames", "min", "mean", "p99", "max"
+        )?;
+        for stats in &self.0 {
+            write!(
+                f,
+                "\n{:<20} {:>6} {:>8.3} {:>8.3} {:>8.3} {:>8.3}",
+                stats.phase,
+                stats.frames,
+                ms(stats.min),
+                ms(stats.mean),
+                ms(stats.p99),
+                ms(stats.max)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        time::{Duration, Instant},
+    };
+
+    use super::{PhaseStats, Profiler, ROLLING_FRAMES};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_phase_stats() {
+        let samples: VecDeque<Duration> = (1..=100).map(ms).collect();
+        let stats = PhaseStats::from_samples("physics", &samples);
+        assert_eq!(100, stats.frames);
+        assert_eq!(ms(1), stats.min);
+        assert_eq!(Duration::from_micros(50_500), stats.mean);
+        assert_eq!(ms(99), stats.p99);
+        assert_eq!(ms(100), stats.max);
+
+        let one = PhaseStats::from_samples("upload", &VecDeque::from([ms(3)]));
+        assert_eq!((ms(3), ms(3)), (one.p99, one.mean));
+    }
+
+    #[test]
+    fn test_phases_add_up_per_frame() {
+        let start = Instant::now();
+        let mut profiler = Profiler::new(start);
+        profiler.record("grid", start, ms(2));
+        profiler.record("physics", start + ms(2), ms(5));
+        profiler.record("grid", start + ms(7), ms(3));
+        profiler.end_frame(start + ms(16));
+        profiler.record("grid", start + ms(16), ms(1));
+        profiler.end_frame(start + ms(20));
+
+        let report = profiler.report();
+        let phases: Vec<_> = report.0.iter().map(|stats| stats.phase).collect();
+        assert_eq!(vec!["grid", "physics", "frame"], phases);
+        assert_eq!((ms(1), ms(5)), (report.0[0].min, report.0[0].max));
+        assert_eq!(1, report.0[1].frames);
+        assert_eq!((ms(4), ms(16)), (report.0[2].min, report.0[2].max));
+    }
+
+    #[test]
+    fn test_rolling_window_forgets_old_frames() {
+        let start = Instant::now();
+        let mut profiler = Profiler::new(start);
+        let mut now = start;
+        for i in 0..ROLLING_FRAMES as u64 + 10 {
+            now += ms(if i < 10 { 100 } else { 10 });
+            profiler.end_frame(now);
+        }
+        let frame = profiler.report().0[0];
+        assert_eq!(ROLLING_FRAMES, frame.frames);
+        assert_eq!(ms(10), frame.max);
+    }
+
+    #[test]
+    fn test_exports() {
+        let start = Instant::now();
+        let mut profiler = Profiler::new(start);
+        profiler.record("physics", start + Duration::from_micros(1500), ms(2));
+        profiler.end_frame(start + ms(4));
+
+        let mut csv = Vec::new();
+        profiler.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            "frame,phase,start_us,duration_us\n0,physics,1500.000,2000.000\n0,frame,0.000,4000.000\n",
+            String::from_utf8(csv).unwrap()
+        );
+
+        let mut trace = Vec::new();
+        profiler.write_chrome_trace(&mut trace).unwrap();
+        let trace = String::from_utf8(trace).unwrap();
+        assert!(trace.starts_with("{\"traceEvents\":[\n{\"name\":\"physics\",\"ph\":\"X\""));
+        assert!(trace.contains(",{\"name\":\"frame\""));
+        assert!(trace.ends_with("]}\n"));
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+
+use crate::game::{canvas::Canvas, material::Material};
+
+use super::{
+    glsl_types::{Resolution, ViewUniform},
+    shading::Shading,
+    software_renderer::SoftwareRenderer,
+};
+
+// colour of bytes that are not a known material, same as the default case in shader.frag
+const UNKNOWN_MATERIAL_COLOR: [u8; 3] = [0x00, 0x00, 0x00];
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+    /// GIF frames can't be larger than 65535 pixels on either axis
+    TooLargeForGif(Resolution),
+}
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{}", e),
+            ExportError::Png(e) => write!(f, "could not encode PNG: {}", e),
+            ExportError::Gif(e) => write!(f, "could not encode GIF: {}", e),
+            ExportError::TooLargeForGif(resolution) => write!(
+                f,
+                "canvas of {}x{} is too large for a GIF",
+                resolution.width, resolution.height
+            ),
+        }
+    }
+}
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+impl From<png::EncodingError> for ExportError {
+    fn from(e: png::EncodingError) -> Self {
+        ExportError::Png(e)
+    }
+}
+impl From<gif::EncodingError> for ExportError {
+    fn from(e: gif::EncodingError) -> Self {
+        ExportError::Gif(e)
+    }
+}
+
+/// RGB colour of a material byte as it is stored in the canvas buffer
+pub fn material_byte_color(byte: u8) -> [u8; 3] {
+    Material::from_u8(byte).map_or(UNKNOWN_MATERIAL_COLOR, |m| m.color())
+}
+
+/// Image with 4 bytes per pixel
+pub struct RgbaImage {
+    pub resolution: Resolution,
+    pub pixels: Vec<u8>,
+}
+impl RgbaImage {
+    /// Draws the canvas with the software renderer, one pixel per canvas cell
+    pub fn from_canvas(canvas: &Canvas, shading: Shading) -> Self {
+        let mut renderer = SoftwareRenderer::new();
+        let view = ViewUniform {
+            origin: [0., 0.],
+            scale: 1.,
+            shading: shading.flags(),
+            light_cell_size: 0,
+        };
+        renderer.render(canvas, view, canvas.resolution);
+        renderer.into_frame()
+    }
+
+    pub fn write_png(&self, path: impl AsRef<Path>) -> Result<(), ExportError> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(
+            writer,
+            self.resolution.width as u32,
+            self.resolution.height as u32,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+/// Writes the canvas to a timestamped PNG in `dir` and returns the path of the new file
+pub fn save_screenshot(
+    canvas: &Canvas,
+    dir: impl AsRef<Path>,
+    shading: Shading,
+) -> Result<PathBuf, ExportError> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir
+        .as_ref()
+        .join(format!("unknown-game-{}.png", timestamp));
+    RgbaImage::from_canvas(canvas, shading).write_png(&path)?;
+    Ok(path)
+}
+
+/// Writes canvas frames one after another, either as numbered PNGs or as an animated GIF
+pub enum FrameExporter {
+    PngSequence {
+        dir: PathBuf,
+        next_index: usize,
+        shading: Shading,
+    },
+    /// Pixels are indices into a palette of material colours, so GIF frames are never shaded
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        resolution: Resolution,
+    },
+}
+impl FrameExporter {
+    /// Paths ending in `.gif` become an animated GIF, anything else is a directory of PNGs
+    pub fn create(
+        path: impl AsRef<Path>,
+        resolution: Resolution,
+        shading: Shading,
+    ) -> Result<Self, ExportError> {
+        let path = path.as_ref();
+        let is_gif = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        if !is_gif {
+            fs::create_dir_all(path)?;
+            return Ok(FrameExporter::PngSequence {
+                dir: path.to_path_buf(),
+                next_index: 0,
+                shading,
+            });
+        }
+
+        let (Ok(width), Ok(height)) = (
+            u16::try_from(resolution.width),
+            u16::try_from(resolution.height),
+        ) else {
+            return Err(ExportError::TooLargeForGif(resolution));
+        };
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(writer, width, height, &gif_palette())?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(FrameExporter::Gif {
+            encoder,
+            resolution,
+        })
+    }
+
+    /// Adds the canvas as the next frame, `frame_time` is how long the frame is shown in a GIF
+    pub fn write_frame(&mut self, canvas: &Canvas, frame_time: Duration) -> Result<(), ExportError> {
+        match self {
+            FrameExporter::PngSequence {
+                dir,
+                next_index,
+                shading,
+            } => {
+                let path = dir.join(format!("frame_{:06}.png", next_index));
+                RgbaImage::from_canvas(canvas, *shading).write_png(path)?;
+                *next_index += 1;
+            }
+            FrameExporter::Gif {
+                encoder,
+                resolution,
+            } => {
+                if canvas.resolution != *resolution {
+                    warn!("Skipped GIF frame, canvas was resized after export started");
+                    return Ok(());
+                }
+                // material bytes are used as indices into the global palette directly
+                let mut frame = gif::Frame::from_indexed_pixels(
+                    resolution.width as u16,
+                    resolution.height as u16,
+                    canvas.grid.iter_materials_as_bytes().collect::<Vec<u8>>(),
+                    None,
+                );
+                // gif delays are in hundredths of a second
+                frame.delay = (frame_time.as_millis() / 10).clamp(1, u16::MAX as u128) as u16;
+                encoder.write_frame(&frame)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Palette with the colour of every possible material byte at the index of that byte
+fn gif_palette() -> Vec<u8> {
+    (0..=u8::MAX).flat_map(material_byte_color).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, time::Duration};
+
+    use super::{FrameExporter, RgbaImage};
+    use crate::{
+        game::{canvas::Canvas, material::Material, math::Vec2},
+        rendering::{glsl_types::Resolution, shading::Shading},
+    };
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("unknown-game-export-{}-{}", std::process::id(), name))
+    }
+
+    fn setup_canvas() -> Canvas {
+        let mut canvas = Canvas::new(Resolution {
+            width: 4,
+            height: 3,
+        });
+        canvas
+            .spawn_dot(Material::Sand, Vec2::new(1., 0.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas
+            .spawn_dot(Material::Orange, Vec2::new(3., 2.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas.write_dots_to_grid();
+        canvas
+    }
+
+    fn pixel(image: &[u8], width: usize, x: usize, y: usize) -> &[u8] {
+        let i = (y * width + x) * 4;
+        &image[i..i + 4]
+    }
+
+    #[test]
+    fn test_image_uses_material_colors() {
+        let image = RgbaImage::from_canvas(&setup_canvas(), Shading::FLAT);
+
+        assert_eq!(4 * 3 * 4, image.pixels.len());
+        assert_eq!(&[0, 0, 0, 0xff], pixel(&image.pixels, 4, 0, 0));
+        assert_eq!(&[0xd7, 0xc9, 0xaa, 0xff], pixel(&image.pixels, 4, 1, 0));
+        assert_eq!(&[0xc0, 0x32, 0x21, 0xff], pixel(&image.pixels, 4, 3, 2));
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let path = temp_path("round-trip.png");
+        let image = RgbaImage::from_canvas(&setup_canvas(), Shading::FLAT);
+        image.write_png(&path).unwrap();
+
+        let decoder = png::Decoder::new(fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((4, 3), (info.width, info.height));
+        assert_eq!(image.pixels, buf[..info.buffer_size()]);
+    }
+
+    #[test]
+    fn test_png_sequence_is_numbered() {
+        let dir = temp_path("sequence");
+        let canvas = setup_canvas();
+        let mut exporter = FrameExporter::create(&dir, canvas.resolution, Shading::FLAT).unwrap();
+        for _ in 0..3 {
+            exporter.write_frame(&canvas, Duration::from_millis(16)).unwrap();
+        }
+
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            vec!["frame_000000.png", "frame_000001.png", "frame_000002.png"],
+            names
+        );
+    }
+
+    #[test]
+    fn test_gif_holds_every_frame() {
+        let path = temp_path("animation.gif");
+        let canvas = setup_canvas();
+        let mut exporter = FrameExporter::create(&path, canvas.resolution, Shading::FLAT).unwrap();
+        for _ in 0..2 {
+            exporter.write_frame(&canvas, Duration::from_millis(50)).unwrap();
+        }
+        drop(exporter);
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(fs::File::open(&path).unwrap())
+            .unwrap();
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(5, frame.delay);
+            frames += 1;
+        }
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, frames);
+    }
+}
@@ -1,38 +1,48 @@
-use vulkano::{buffer::BufferContents, pipeline::graphics::vertex_input::Vertex as VertexMacro};
-use winit::dpi::PhysicalSize;
+#[cfg(feature = "window")]
+use vulkano::buffer::BufferContents;
 
-#[derive(BufferContents, VertexMacro, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "window", derive(BufferContents))]
 #[repr(C)]
-pub struct Vertex {
-    #[format(R32G32_SFLOAT)]
-    pub position: [f32; 2],
+pub struct ViewUniform {
+    pub origin: [f32; 2],
+    pub scale: f32,
+    /// `Shading::flags`
+    pub shading: u32,
+    /// `Lighting::uniform_cell_size`
+    pub light_cell_size: u32,
 }
 
-#[derive(BufferContents, Clone, Copy, Debug)]
+/// Push constants shared by the post processing shaders, every pass reads the parts it needs
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "window", derive(BufferContents))]
 #[repr(C)]
-pub struct Resolution {
-    pub width: i32,
-    pub height: i32,
+pub struct PostPushConstants {
+    /// Blur direction in pixels, either `[1, 0]` or `[0, 1]`
+    pub direction: [i32; 2],
+    /// Strength of the glow added by the composite pass
+    pub intensity: f32,
 }
-impl Resolution {
-    pub fn update_from(&mut self, value: PhysicalSize<u32>) {
-        self.width = value.width as i32;
-        self.height = value.height as i32;
-    }
-}
-impl From<PhysicalSize<u32>> for Resolution {
-    fn from(value: PhysicalSize<u32>) -> Self {
+impl PostPushConstants {
+    pub fn blur(direction: [i32; 2]) -> Self {
         Self {
-            width: value.width as i32,
-            height: value.height as i32,
+            direction,
+            intensity: 0.,
         }
     }
-}
-impl From<&PhysicalSize<u32>> for Resolution {
-    fn from(value: &PhysicalSize<u32>) -> Self {
+
+    pub fn composite(intensity: f32) -> Self {
         Self {
-            width: value.width as i32,
-            height: value.height as i32,
+            direction: [0, 0],
+            intensity,
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "window", derive(BufferContents))]
+#[repr(C)]
+pub struct Resolution {
+    pub width: i32,
+    pub height: i32,
+}
@@ -0,0 +1,343 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    fs::File,
+    io::{self, BufReader},
+    path::Path,
+};
+
+use crate::game::{
+    canvas::{Canvas, ResizeMode},
+    material::Material,
+    math::Vec2,
+};
+
+use super::glsl_types::Resolution;
+
+// pixels with less alpha than this are treated as empty space
+const MIN_OPAQUE_ALPHA: u8 = 128;
+
+// furthest a pixel colour can be from a material colour (euclidean distance in RGB) to still count as that material
+const MAX_COLOR_DISTANCE: u32 = 48;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Png(png::DecodingError),
+    Palette { line: usize, message: String },
+}
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "{}", e),
+            ImportError::Png(e) => write!(f, "could not decode PNG: {}", e),
+            ImportError::Palette { line, message } => {
+                write!(f, "palette file line {}: {}", line, message)
+            }
+        }
+    }
+}
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+impl From<png::DecodingError> for ImportError {
+    fn from(e: png::DecodingError) -> Self {
+        ImportError::Png(e)
+    }
+}
+
+/// How pixel colours are turned into materials
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorMapping {
+    /// Closest material colour within `MAX_COLOR_DISTANCE`
+    Nearest,
+    /// Exact colours listed in a palette file
+    Palette(HashMap<[u8; 3], Material>),
+}
+impl ColorMapping {
+    /// Reads a palette file with one `rrggbb = material` entry per line, `#` starts a comment
+    pub fn from_palette_file(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        Self::parse_palette(&fs::read_to_string(path)?)
+    }
+
+    fn parse_palette(text: &str) -> Result<Self, ImportError> {
+        let mut palette = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let palette_error = |message: String| ImportError::Palette {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((hex, name)) = line.split_once('=') else {
+                return Err(palette_error("expected 'rrggbb = material'".to_string()));
+            };
+            let (hex, name) = (hex.trim(), name.trim());
+            let color = match u32::from_str_radix(hex, 16) {
+                Ok(color) if hex.len() == 6 => color.to_be_bytes(),
+                _ => return Err(palette_error(format!("'{}' is not a rrggbb colour", hex))),
+            };
+            let material = Material::from_name(name)
+                .ok_or_else(|| palette_error(format!("unknown material '{}'", name)))?;
+            palette.insert([color[1], color[2], color[3]], material);
+        }
+        Ok(ColorMapping::Palette(palette))
+    }
+
+    fn material_for(&self, color: [u8; 3]) -> Option<Material> {
+        match self {
+            ColorMapping::Palette(palette) => palette.get(&color).copied(),
+            ColorMapping::Nearest => Material::ALL
+                .into_iter()
+                .map(|m| (m, color_distance_squared(color, m.color())))
+                .filter(|(_, distance)| *distance <= MAX_COLOR_DISTANCE.pow(2))
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(m, _)| m),
+        }
+    }
+}
+
+fn color_distance_squared(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+        .sum()
+}
+
+/// Summary of an image import
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub image_resolution: Option<Resolution>,
+    /// Dots that ended up on the canvas
+    pub imported: usize,
+    /// Dots that were cut off or squashed together while fitting the image to the canvas
+    pub dropped: usize,
+    /// Colours that don't map to a material with the amount of pixels that had them, most common first
+    pub unknown_colors: Vec<([u8; 3], usize)>,
+}
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(resolution) = self.image_resolution {
+            write!(f, "Imported {}x{} image: ", resolution.width, resolution.height)?;
+        }
+        write!(
+            f,
+            "{} dot(s) placed, {} dropped while fitting to the canvas",
+            self.imported, self.dropped
+        )?;
+        if !self.unknown_colors.is_empty() {
+            let pixel_count: usize = self.unknown_colors.iter().map(|(_, count)| count).sum();
+            write!(
+                f,
+                ", {} pixel(s) with {} unknown colour(s) left empty:",
+                pixel_count,
+                self.unknown_colors.len()
+            )?;
+            for ([r, g, b], count) in &self.unknown_colors {
+                write!(f, " {:02x}{:02x}{:02x} ({})", r, g, b, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Loads a PNG and turns it into a canvas of `resolution`, fitting the image with `mode`
+pub fn import_png(
+    path: impl AsRef<Path>,
+    mapping: &ColorMapping,
+    resolution: Resolution,
+    mode: ResizeMode,
+) -> Result<(Canvas, ImportReport), ImportError> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let bytes = &buf[..info.buffer_size()];
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => bytes.to_vec(),
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xff])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        // indexed images are expanded to RGB(A) by the decoder, so this is plain grayscale
+        png::ColorType::Grayscale | png::ColorType::Indexed => {
+            bytes.iter().flat_map(|g| [*g, *g, *g, 0xff]).collect()
+        }
+    };
+    let image_resolution = Resolution {
+        width: info.width as i32,
+        height: info.height as i32,
+    };
+
+    let (mut canvas, mut report) = canvas_from_rgba(image_resolution, &rgba, mapping);
+    let resize_report = canvas.resize(resolution, mode);
+    report.dropped = resize_report.dropped_count();
+    report.imported -= report.dropped;
+    Ok((canvas, report))
+}
+
+/// Builds a canvas the size of the image with one dot for every pixel that maps to a material
+pub fn canvas_from_rgba(
+    resolution: Resolution,
+    rgba: &[u8],
+    mapping: &ColorMapping,
+) -> (Canvas, ImportReport) {
+    let mut canvas = Canvas::new(resolution);
+    let mut report = ImportReport {
+        image_resolution: Some(resolution),
+        ..Default::default()
+    };
+    let mut unknown_colors: HashMap<[u8; 3], usize> = HashMap::new();
+
+    for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+        if pixel[3] < MIN_OPAQUE_ALPHA {
+            continue;
+        }
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let material = match mapping.material_for(color) {
+            Some(Material::EmptySpace) => continue,
+            Some(material) => material,
+            None => {
+                *unknown_colors.entry(color).or_default() += 1;
+                continue;
+            }
+        };
+        let position = Vec2::new(
+            (i % resolution.width as usize) as f64,
+            (i / resolution.width as usize) as f64,
+        );
+        canvas
+            .spawn_dot(material, position, Vec2::new(0., 0.))
+            .expect("Every pixel of the image gets its own dot");
+        report.imported += 1;
+    }
+    canvas.write_dots_to_grid();
+
+    report.unknown_colors = unknown_colors.into_iter().collect();
+    report
+        .unknown_colors
+        .sort_by(|(a_color, a_count), (b_color, b_count)| {
+            b_count.cmp(a_count).then(a_color.cmp(b_color))
+        });
+    (canvas, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{canvas_from_rgba, import_png, ColorMapping, ImportError};
+    use crate::{
+        game::{
+            canvas::{Anchor, Canvas, ResizeMode},
+            material::Material,
+            math::Vec2,
+        },
+        rendering::{export::RgbaImage, glsl_types::Resolution, shading::Shading},
+    };
+
+    fn material_at(canvas: &Canvas, x: isize, y: isize) -> Option<Material> {
+        canvas
+            .grid
+            .get(Vec2::new(x, y))
+            .unwrap()
+            .map(|dot| dot.material)
+    }
+
+    const SIZE: Resolution = Resolution {
+        width: 2,
+        height: 2,
+    };
+
+    #[test]
+    fn test_nearest_color_mapping() {
+        let rgba = [
+            0xd0, 0xc0, 0xa0, 0xff, // close to sand
+            0x36, 0xc9, 0xc6, 0x00, // blue, but transparent
+            0x00, 0x00, 0x00, 0xff, // empty space
+            0xff, 0x00, 0xff, 0xff, // nothing like any material
+        ];
+        let (canvas, report) = canvas_from_rgba(SIZE, &rgba, &ColorMapping::Nearest);
+
+        assert_eq!(Some(Material::Sand), material_at(&canvas, 0, 0));
+        assert_eq!(None, material_at(&canvas, 1, 0));
+        assert_eq!(None, material_at(&canvas, 0, 1));
+        assert_eq!(None, material_at(&canvas, 1, 1));
+        assert_eq!(1, report.imported);
+        assert_eq!(vec![([0xff, 0x00, 0xff], 1)], report.unknown_colors);
+    }
+
+    #[test]
+    fn test_palette_mapping() {
+        let mapping = ColorMapping::parse_palette(
+            "# level palette\nff0000 = dirt\n00ff00 = orange # grass\n\n",
+        )
+        .unwrap();
+        let rgba = [
+            0xff, 0x00, 0x00, 0xff, //
+            0x00, 0xff, 0x00, 0xff, //
+            0xfe, 0x00, 0x00, 0xff, // not exact, so unknown
+            0x00, 0x00, 0x00, 0xff, //
+        ];
+        let (canvas, report) = canvas_from_rgba(SIZE, &rgba, &mapping);
+
+        assert_eq!(Some(Material::Dirt), material_at(&canvas, 0, 0));
+        assert_eq!(Some(Material::Orange), material_at(&canvas, 1, 0));
+        assert_eq!(2, report.unknown_colors.len());
+    }
+
+    #[test]
+    fn test_bad_palette_line_is_reported() {
+        match ColorMapping::parse_palette("ff0000 = dirt\nff00 = sand\n") {
+            Err(ImportError::Palette { line: 2, .. }) => (),
+            result => panic!("Expected error on line 2 -> {:?}", result),
+        }
+        match ColorMapping::parse_palette("ff0000 = lava\n") {
+            Err(ImportError::Palette { line: 1, .. }) => (),
+            result => panic!("Expected unknown material error -> {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_import_png_crops_to_canvas() {
+        let path = env::temp_dir().join(format!("unknown-game-import-{}.png", std::process::id()));
+        let mut image_canvas = Canvas::new(Resolution {
+            width: 6,
+            height: 6,
+        });
+        image_canvas
+            .spawn_dot(Material::Sand, Vec2::new(1., 1.), Vec2::new(0., 0.))
+            .unwrap();
+        image_canvas
+            .spawn_dot(Material::Dirt, Vec2::new(5., 5.), Vec2::new(0., 0.))
+            .unwrap();
+        image_canvas.write_dots_to_grid();
+        RgbaImage::from_canvas(&image_canvas, Shading::default()).write_png(&path).unwrap();
+
+        let (canvas, report) = import_png(
+            &path,
+            &ColorMapping::Nearest,
+            Resolution {
+                width: 4,
+                height: 4,
+            },
+            ResizeMode::Anchored(Anchor::TopLeft),
+        )
+        .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(Some(Material::Sand), material_at(&canvas, 1, 1));
+        assert_eq!(1, report.imported);
+        assert_eq!(1, report.dropped);
+    }
+}
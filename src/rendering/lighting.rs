@@ -0,0 +1,381 @@
+use std::{cmp::Reverse, collections::BTreeMap};
+
+use crate::game::{
+    canvas::{Canvas, PointLight},
+    math::Vec2,
+};
+
+use super::{glsl_types::Resolution, software_renderer::to_unorm8};
+
+// canvas pixels per side of a light map texel unless set otherwise
+const DEFAULT_CELL_SIZE: u32 = 4;
+
+// light coming in from above the canvas, before anything blocks it
+const SKY_LIGHT: f32 = 1.;
+// light that reaches everywhere, so caves are dark but not pitch black
+const AMBIENT_LIGHT: f32 = 0.08;
+
+// reach in canvas pixels and brightness of the light every emissive dot gives off
+const EMISSIVE_LIGHT_RADIUS: f64 = 16.;
+const EMISSIVE_LIGHT_INTENSITY: f32 = 0.6;
+// emissive dots within the same square of this many canvas pixels share one light
+const EMISSIVE_CLUSTER_SIZE: f64 = 8.;
+// most lights emissive dots add to a light map, every one of them ray-casts to the texels around it
+const MAX_EMISSIVE_LIGHTS: usize = 256;
+
+/// Whether the canvas is lit and how finely the light map is worked out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lighting {
+    pub enabled: bool,
+    /// Canvas pixels per side of one light map texel, bigger is faster but makes shadows blockier
+    pub cell_size: u32,
+}
+impl Lighting {
+    /// Every pixel fully lit
+    pub const OFF: Self = Self {
+        enabled: false,
+        cell_size: DEFAULT_CELL_SIZE,
+    };
+
+    /// Parses `off`, `on` or the light map cell size in canvas pixels
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "off" => Some(Self::OFF),
+            "on" => Some(Self::default()),
+            cell_size => match cell_size.parse() {
+                Ok(cell_size) if cell_size > 0 => Some(Self {
+                    enabled: true,
+                    cell_size,
+                }),
+                _ => None,
+            },
+        }
+    }
+
+    /// Value of `light_cell_size` in the view uniform, 0 turns lighting off in the shader
+    pub fn uniform_cell_size(&self) -> u32 {
+        if self.enabled {
+            self.cell_size
+        } else {
+            0
+        }
+    }
+}
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cell_size: DEFAULT_CELL_SIZE,
+        }
+    }
+}
+
+/// Emissive dots in one square of the canvas, added up so they can share a light
+struct EmissiveCluster {
+    position_sum: Vec2<f64>,
+    color_sum: [f32; 3],
+    dots: u32,
+}
+
+/// Light reaching every part of the canvas, one RGB texel per `cell_size` x `cell_size` canvas pixels.
+/// Every texel is lit as seen from the canvas pixel in its centre.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightMap {
+    pub width: usize,
+    pub height: usize,
+    cell_size: u32,
+    /// Row by row, can be brighter than 1 where lights overlap
+    texels: Vec<[f32; 3]>,
+}
+impl LightMap {
+    /// Texels needed across and down to cover a canvas, the last ones may stick out over its edge
+    pub fn dimensions(canvas_resolution: Resolution, cell_size: u32) -> (usize, usize) {
+        let cell_size = cell_size.max(1) as usize;
+        (
+            (canvas_resolution.width.max(0) as usize).div_ceil(cell_size),
+            (canvas_resolution.height.max(0) as usize).div_ceil(cell_size),
+        )
+    }
+
+    /// Lights the canvas with sky light from above, its point lights and its emissive dots.
+    /// Light is blocked by the opacity of every dot it passes through.
+    pub fn generate(canvas: &Canvas, cell_size: u32) -> Self {
+        let cell_size = cell_size.max(1);
+        let (width, height) = Self::dimensions(canvas.resolution, cell_size);
+        let mut light_map = Self {
+            width,
+            height,
+            cell_size,
+            texels: vec![[AMBIENT_LIGHT; 3]; width * height],
+        };
+        light_map.add_sky_light(canvas);
+
+        let emissive_lights = Self::emissive_lights(
+            canvas
+                .dots()
+                .filter(|dot| dot.material.properties().emissive)
+                .map(|dot| (dot.position, dot.material.color())),
+        );
+        for light in canvas.lights.iter().chain(&emissive_lights) {
+            light_map.add_point_light(canvas, light);
+        }
+        light_map
+    }
+
+    /// Lights given off by emissive dots at their positions with their RGB colours. Dots close together
+    /// share one light as bright as all of them, and only the brightest `MAX_EMISSIVE_LIGHTS` are kept,
+    /// so a canvas full of emissive dots can't stall a frame.
+    fn emissive_lights(dots: impl Iterator<Item = (Vec2<f64>, [u8; 3])>) -> Vec<PointLight> {
+        // ordered by square, so the lights come out the same every frame
+        let mut clusters = BTreeMap::new();
+        for (position, color) in dots {
+            let square = (
+                (position.x / EMISSIVE_CLUSTER_SIZE).floor() as i64,
+                (position.y / EMISSIVE_CLUSTER_SIZE).floor() as i64,
+            );
+            let cluster = clusters.entry(square).or_insert(EmissiveCluster {
+                position_sum: Vec2::new(0., 0.),
+                color_sum: [0.; 3],
+                dots: 0,
+            });
+            cluster.position_sum += position;
+            for (sum, c) in cluster.color_sum.iter_mut().zip(color) {
+                *sum += c as f32 / 255.;
+            }
+            cluster.dots += 1;
+        }
+
+        let mut clusters: Vec<_> = clusters.into_values().collect();
+        clusters.sort_by_key(|cluster| Reverse(cluster.dots));
+        clusters.truncate(MAX_EMISSIVE_LIGHTS);
+        clusters
+            .into_iter()
+            .map(|cluster| PointLight {
+                position: cluster.position_sum / cluster.dots as f64,
+                color: cluster.color_sum.map(|c| c / cluster.dots as f32),
+                radius: EMISSIVE_LIGHT_RADIUS,
+                intensity: EMISSIVE_LIGHT_INTENSITY * cluster.dots as f32,
+            })
+            .collect()
+    }
+
+    /// Canvas pixel the texel at `(x, y)` is lit as
+    fn sample_cell(&self, x: usize, y: usize, canvas_resolution: Resolution) -> Vec2<isize> {
+        let half = self.cell_size as usize / 2;
+        Vec2::new(
+            (x * self.cell_size as usize + half).min(canvas_resolution.width as usize - 1) as isize,
+            (y * self.cell_size as usize + half).min(canvas_resolution.height as usize - 1) as isize,
+        )
+    }
+
+    /// Walks down every column from the top of the canvas, dimming the sky light with each dot on the way
+    fn add_sky_light(&mut self, canvas: &Canvas) {
+        for x in 0..self.width {
+            let mut transmittance = 1.;
+            let mut cell_y = 0;
+            for y in 0..self.height {
+                let sample = self.sample_cell(x, y, canvas.resolution);
+                while cell_y < sample.y {
+                    if let Ok(Some(dot)) = canvas.grid.get(Vec2::new(sample.x, cell_y)) {
+                        transmittance *= 1. - dot.material.properties().opacity;
+                    }
+                    cell_y += 1;
+                }
+                let light = SKY_LIGHT * transmittance as f32;
+                for channel in self.texels[y * self.width + x].iter_mut() {
+                    *channel += light;
+                }
+            }
+        }
+    }
+
+    /// Adds the light reaching every texel within the light's radius that isn't blocked on the way there
+    fn add_point_light(&mut self, canvas: &Canvas, light: &PointLight) {
+        let cell_size = self.cell_size as f64;
+        let first = (light.position - light.radius) / cell_size;
+        let last = (light.position + light.radius) / cell_size;
+        let xs = (first.x.floor().max(0.) as usize)..(last.x.ceil().max(0.) as usize + 1).min(self.width);
+        let ys = (first.y.floor().max(0.) as usize)..(last.y.ceil().max(0.) as usize + 1).min(self.height);
+        for y in ys {
+            for x in xs.clone() {
+                let sample = self.sample_cell(x, y, canvas.resolution).into_f64();
+                let falloff = light.falloff((sample - light.position).pythagorean_theorem());
+                if falloff <= 0. {
+                    continue;
+                }
+                let transmittance =
+                    canvas
+                        .grid
+                        .transmittance(light.position, sample, canvas.resolution) as f32;
+                let texel = &mut self.texels[y * self.width + x];
+                for (channel, color) in texel.iter_mut().zip(light.color) {
+                    *channel += color * falloff * transmittance;
+                }
+            }
+        }
+    }
+
+    /// Texels as the light map buffer stores them, RGBA8 in one `uint` each for `unpackUnorm4x8`.
+    /// Light is clamped to 1 here, so nothing gets brighter than its own colour.
+    pub fn iter_packed(&self) -> impl Iterator<Item = u32> + '_ {
+        self.texels.iter().map(|[r, g, b]| {
+            u32::from_le_bytes([to_unorm8(*r), to_unorm8(*g), to_unorm8(*b), u8::MAX])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LightMap, Lighting, AMBIENT_LIGHT, EMISSIVE_LIGHT_INTENSITY, MAX_EMISSIVE_LIGHTS};
+    use crate::{
+        game::{
+            canvas::{Canvas, PointLight},
+            material::Material,
+            math::Vec2,
+        },
+        rendering::glsl_types::Resolution,
+    };
+
+    fn canvas(width: i32, height: i32) -> Canvas {
+        Canvas::new(Resolution { width, height })
+    }
+
+    fn fill(canvas: &mut Canvas, material: Material, xs: std::ops::Range<i32>, ys: std::ops::Range<i32>) {
+        for y in ys {
+            for x in xs.clone() {
+                canvas
+                    .spawn_dot(material, Vec2::new(x as f64, y as f64), Vec2::new(0., 0.))
+                    .unwrap();
+            }
+        }
+        canvas.write_dots_to_grid();
+    }
+
+    fn red(light_map: &LightMap) -> Vec<Vec<f32>> {
+        (0..light_map.height)
+            .map(|y| (0..light_map.width).map(|x| light_map.texels[y * light_map.width + x][0]).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_light_map_covers_the_canvas() {
+        let resolution = Resolution {
+            width: 10,
+            height: 8,
+        };
+        assert_eq!((3, 2), LightMap::dimensions(resolution, 4));
+        assert_eq!((10, 8), LightMap::dimensions(resolution, 1));
+    }
+
+    #[test]
+    fn test_open_sky_lights_everything() {
+        let light_map = LightMap::generate(&canvas(4, 4), 1);
+        assert!(red(&light_map).iter().flatten().all(|light| *light >= 1.));
+        assert!(light_map.iter_packed().all(|texel| texel == u32::MAX));
+    }
+
+    #[test]
+    fn test_cave_dug_into_dirt_is_dark() {
+        // a layer of dirt with a hollow under its middle
+        let mut canvas = canvas(5, 6);
+        fill(&mut canvas, Material::Dirt, 0..5, 2..6);
+        let hollow: Vec<_> = canvas
+            .dots()
+            .filter(|dot| {
+                let p = dot.position.to_rounded_isize();
+                p.y >= 4 && (1..4).contains(&p.x)
+            })
+            .map(|dot| dot.id)
+            .collect();
+        for id in hollow {
+            canvas.remove_dot(id);
+        }
+        canvas.write_dots_to_grid();
+
+        let light = red(&LightMap::generate(&canvas, 1));
+        // surface of the dirt faces the sky
+        assert_eq!(1. + AMBIENT_LIGHT, light[2][2]);
+        assert_eq!(AMBIENT_LIGHT, light[3][2]);
+        assert_eq!(AMBIENT_LIGHT, light[4][2]);
+        assert_eq!(AMBIENT_LIGHT, light[5][1]);
+    }
+
+    #[test]
+    fn test_translucent_material_dims_light() {
+        let mut canvas = canvas(1, 4);
+        fill(&mut canvas, Material::Blue, 0..1, 1..3);
+        let light = red(&LightMap::generate(&canvas, 1));
+        let opacity = Material::Blue.properties().opacity as f32;
+
+        assert_eq!(1. + AMBIENT_LIGHT, light[1][0]);
+        assert!(light[2][0] < light[1][0] && light[3][0] < light[2][0]);
+        let expected = (1. - opacity) * (1. - opacity) + AMBIENT_LIGHT;
+        assert!((light[3][0] - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_point_light_is_blocked_by_walls() {
+        // roofed over so only the point light reaches inside, with a wall at x = 4
+        let mut canvas = canvas(9, 4);
+        fill(&mut canvas, Material::Dirt, 0..9, 0..1);
+        fill(&mut canvas, Material::Dirt, 4..5, 1..4);
+        canvas.lights.push(PointLight::new(Vec2::new(2., 2.)));
+
+        let light = red(&LightMap::generate(&canvas, 1));
+        assert_eq!(light[2][1], light[2][3]);
+        assert!(light[2][1] > AMBIENT_LIGHT);
+        // the wall itself is lit on the side facing the light, but nothing behind it is
+        assert!(light[2][4] > AMBIENT_LIGHT);
+        assert_eq!(AMBIENT_LIGHT, light[2][6]);
+    }
+
+    #[test]
+    fn test_coarse_light_map_samples_texel_centres() {
+        let mut canvas = canvas(8, 8);
+        fill(&mut canvas, Material::Dirt, 0..8, 2..5);
+        let light = red(&LightMap::generate(&canvas, 4));
+
+        // texel centres are at rows 2 and 6, the first is the surface of the dirt
+        assert_eq!(1. + AMBIENT_LIGHT, light[0][0]);
+        assert_eq!(AMBIENT_LIGHT, light[1][0]);
+    }
+
+    #[test]
+    fn test_emissive_lights_are_merged_and_capped() {
+        let lights = LightMap::emissive_lights(
+            [
+                (Vec2::new(1., 1.), [255, 0, 0]),
+                (Vec2::new(3., 5.), [0, 0, 255]),
+                (Vec2::new(20., 1.), [255, 255, 255]),
+            ]
+            .into_iter(),
+        );
+        // the two dots in the first square share the brighter light, which comes first
+        assert_eq!(2, lights.len());
+        assert_eq!(Vec2::new(2., 3.), lights[0].position);
+        assert_eq!([0.5, 0., 0.5], lights[0].color);
+        assert_eq!(2. * EMISSIVE_LIGHT_INTENSITY, lights[0].intensity);
+        assert_eq!(Vec2::new(20., 1.), lights[1].position);
+
+        // a whole canvas of emissive dots
+        let full = (0..400)
+            .flat_map(|y| (0..400).map(move |x| (Vec2::new(x as f64, y as f64), [255, 255, 255])));
+        assert_eq!(MAX_EMISSIVE_LIGHTS, LightMap::emissive_lights(full).len());
+    }
+
+    #[test]
+    fn test_parse_lighting() {
+        assert_eq!(Some(Lighting::OFF), Lighting::parse("off"));
+        assert_eq!(Some(Lighting::default()), Lighting::parse("on"));
+        assert_eq!(
+            Some(Lighting {
+                enabled: true,
+                cell_size: 2
+            }),
+            Lighting::parse("2")
+        );
+        assert_eq!(None, Lighting::parse("0"));
+        assert_eq!(None, Lighting::parse("bright"));
+        assert_eq!(0, Lighting::OFF.uniform_cell_size());
+    }
+}
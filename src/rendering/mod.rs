@@ -1,4 +1,7 @@
+pub mod export;
 pub mod glsl_types;
-pub mod render_engine;
-pub mod geometry;
-mod load_shaders;
+pub mod import;
+pub mod lighting;
+pub mod post_processing;
+pub mod shading;
+pub mod software_renderer;
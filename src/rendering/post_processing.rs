@@ -0,0 +1,231 @@
+use super::{export::RgbaImage, glsl_types::PostPushConstants, software_renderer::to_unorm8};
+
+// have to match the constants in blur.frag
+const BLUR_WEIGHTS: [f32; 5] = [0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216];
+// window pixels between two taps of the blur
+const BLUR_SPREAD: i32 = 2;
+
+// how strongly the glow is added on top of the scene
+pub const BLOOM_INTENSITY: f32 = 1.;
+
+/// Passes that run after the canvas is drawn, every one of them can be turned off on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PostProcessing {
+    /// Adds glow on top of the scene, turning this off skips every other pass
+    pub bloom: bool,
+    /// Only emissive pixels glow, without it the whole scene does
+    pub bright_pass: bool,
+    /// Spreads the glow out, without it the glow stays on the pixels it comes from
+    pub blur: bool,
+}
+impl PostProcessing {
+    pub const OFF: Self = Self {
+        bloom: false,
+        bright_pass: false,
+        blur: false,
+    };
+
+    /// Parses `off` or a comma separated list of `bloom`, `bright-pass` and `blur`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut post_processing = Self::OFF;
+        for pass in value.split(',').map(str::trim) {
+            match pass {
+                "off" => (),
+                "bloom" => post_processing.bloom = true,
+                "bright-pass" => post_processing.bright_pass = true,
+                "blur" => post_processing.blur = true,
+                _ => return None,
+            }
+        }
+        Some(post_processing)
+    }
+
+    pub fn composite_intensity(&self) -> f32 {
+        if self.bloom {
+            BLOOM_INTENSITY
+        } else {
+            0.
+        }
+    }
+}
+impl Default for PostProcessing {
+    fn default() -> Self {
+        Self {
+            bloom: true,
+            bright_pass: true,
+            blur: true,
+        }
+    }
+}
+
+/// Runs the same passes as `RenderEngine` on a scene whose alpha channel holds the emission of every pixel.
+/// Every pass writes to an 8 bit image like the offscreen images on the GPU, so results match frame for frame.
+pub fn apply(scene: &RgbaImage, settings: PostProcessing) -> RgbaImage {
+    let mut glow = None;
+    if settings.bloom {
+        if settings.bright_pass {
+            glow = Some(bright_pass(scene));
+        }
+        if settings.blur {
+            let source = glow.as_ref().unwrap_or(scene);
+            let horizontal = blur(source, PostPushConstants::blur([1, 0]));
+            glow = Some(blur(&horizontal, PostPushConstants::blur([0, 1])));
+        }
+    }
+    composite(
+        scene,
+        glow.as_ref().unwrap_or(scene),
+        PostPushConstants::composite(settings.composite_intensity()),
+    )
+}
+
+fn map_pixels(image: &RgbaImage, mut fragment: impl FnMut(usize, usize) -> [f32; 4]) -> RgbaImage {
+    let width = image.resolution.width.max(0) as usize;
+    let height = image.resolution.height.max(0) as usize;
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.extend(fragment(x, y).map(to_unorm8));
+        }
+    }
+    RgbaImage {
+        resolution: image.resolution,
+        pixels,
+    }
+}
+
+/// `imageLoad` of an 8 bit UNORM image
+fn load(image: &RgbaImage, x: usize, y: usize) -> [f32; 4] {
+    let i = (y * image.resolution.width as usize + x) * 4;
+    [0, 1, 2, 3].map(|c| image.pixels[i + c] as f32 / 255.)
+}
+
+/// bright_pass.frag
+pub fn bright_pass(scene: &RgbaImage) -> RgbaImage {
+    map_pixels(scene, |x, y| {
+        let [r, g, b, emission] = load(scene, x, y);
+        [r * emission, g * emission, b * emission, 1.]
+    })
+}
+
+/// blur.frag, one direction of the separable gaussian blur
+pub fn blur(source: &RgbaImage, push: PostPushConstants) -> RgbaImage {
+    let width = source.resolution.width;
+    let height = source.resolution.height;
+    let tap = |x: i32, y: i32| {
+        load(
+            source,
+            x.clamp(0, width - 1) as usize,
+            y.clamp(0, height - 1) as usize,
+        )
+    };
+    map_pixels(source, |x, y| {
+        let (x, y) = (x as i32, y as i32);
+        let [r, g, b, _] = tap(x, y);
+        let mut sum = [r * BLUR_WEIGHTS[0], g * BLUR_WEIGHTS[0], b * BLUR_WEIGHTS[0]];
+        for (i, weight) in BLUR_WEIGHTS.iter().enumerate().skip(1) {
+            let offset_x = push.direction[0] * i as i32 * BLUR_SPREAD;
+            let offset_y = push.direction[1] * i as i32 * BLUR_SPREAD;
+            for [r, g, b, _] in [tap(x + offset_x, y + offset_y), tap(x - offset_x, y - offset_y)] {
+                sum[0] += r * weight;
+                sum[1] += g * weight;
+                sum[2] += b * weight;
+            }
+        }
+        [sum[0], sum[1], sum[2], 1.]
+    })
+}
+
+/// composite.frag, adds the glow on top of the scene
+pub fn composite(scene: &RgbaImage, glow: &RgbaImage, push: PostPushConstants) -> RgbaImage {
+    map_pixels(scene, |x, y| {
+        let [r, g, b, _] = load(scene, x, y);
+        let [glow_r, glow_g, glow_b, _] = load(glow, x, y);
+        [
+            r + glow_r * push.intensity,
+            g + glow_g * push.intensity,
+            b + glow_b * push.intensity,
+            1.,
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, blur, bright_pass, PostProcessing};
+    use crate::rendering::{
+        export::RgbaImage,
+        glsl_types::{PostPushConstants, Resolution},
+    };
+
+    /// Dark image of `width` x 1 with a single bright pixel in the middle, `emission` is its alpha
+    fn single_light(width: i32, emission: u8) -> RgbaImage {
+        let mut pixels = vec![0; width as usize * 4];
+        let i = (width as usize / 2) * 4;
+        pixels[i..i + 4].copy_from_slice(&[200, 100, 0, emission]);
+        RgbaImage {
+            resolution: Resolution { width, height: 1 },
+            pixels,
+        }
+    }
+
+    fn red_channel(image: &RgbaImage) -> Vec<u8> {
+        image.pixels.chunks(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn test_bright_pass_keeps_only_emissive_pixels() {
+        assert_eq!(vec![0, 0, 0], red_channel(&bright_pass(&single_light(3, 0))));
+        assert_eq!(vec![0, 200, 0], red_channel(&bright_pass(&single_light(3, 255))));
+    }
+
+    #[test]
+    fn test_blur_spreads_symmetrically_and_only_along_its_direction() {
+        let light = single_light(21, 255);
+        let blurred = red_channel(&blur(&light, PostPushConstants::blur([1, 0])));
+
+        // centre keeps the largest weight, taps are 2 pixels apart
+        assert_eq!(45, blurred[10]);
+        assert_eq!(39, blurred[8]);
+        assert_eq!(blurred[8], blurred[12]);
+        assert_eq!(0, blurred[9]);
+        assert_eq!(3, blurred[2]);
+        assert_eq!(0, blurred[0]);
+
+        let vertical = red_channel(&blur(&light, PostPushConstants::blur([0, 1])));
+        assert_eq!(0, vertical[8]);
+    }
+
+    #[test]
+    fn test_bloom_adds_glow_around_emissive_pixels() {
+        let lit = apply(&single_light(21, 255), PostProcessing::default());
+        let unlit = apply(&single_light(21, 0), PostProcessing::default());
+
+        assert_eq!(0, red_channel(&unlit)[8]);
+        assert!(red_channel(&lit)[8] > 0);
+        assert_eq!(245, red_channel(&lit)[10]);
+        assert!(lit.pixels.chunks(4).all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn test_post_processing_off_only_makes_pixels_opaque() {
+        let scene = single_light(5, 255);
+        let result = apply(&scene, PostProcessing::OFF);
+        assert_eq!(red_channel(&scene), red_channel(&result));
+        assert!(result.pixels.chunks(4).all(|pixel| pixel[3] == 255));
+    }
+
+    #[test]
+    fn test_parse_post_processing() {
+        assert_eq!(Some(PostProcessing::OFF), PostProcessing::parse("off"));
+        assert_eq!(
+            Some(PostProcessing {
+                bloom: true,
+                bright_pass: false,
+                blur: true
+            }),
+            PostProcessing::parse("bloom, blur")
+        );
+        assert_eq!(None, PostProcessing::parse("lens-flare"));
+    }
+}
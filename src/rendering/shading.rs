@@ -0,0 +1,193 @@
+use crate::game::{
+    canvas::{dot::CanvasDot, DotId},
+    material::Material,
+    math::Vec2,
+};
+
+// bytes every canvas cell takes up in the canvas storage buffer
+pub const CELL_BYTES: usize = 2;
+
+// speed in pixels per second between two speed levels of velocity tinting
+const SPEED_LEVEL_STEP: f64 = 60.;
+
+const SEED_MASK: u8 = 0x3f;
+const MAX_SPEED_LEVEL: u8 = 3;
+
+/// One canvas cell as the shaders see it. Cells are stored as 16 bits, two per little endian `uint`
+/// of the canvas buffer with the first cell in the low half:
+///
+/// | bits  | content                                                    |
+/// |-------|------------------------------------------------------------|
+/// | 0-7   | material byte                                              |
+/// | 8-13  | colour variation seed, stays the same for the life of a dot |
+/// | 14-15 | speed level from 0 (resting) to 3, for velocity tinting    |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cell {
+    pub material: u8,
+    pub seed: u8,
+    pub speed_level: u8,
+}
+impl Cell {
+    pub fn from_dot(maybe_dot: &Option<CanvasDot>) -> Self {
+        match maybe_dot {
+            Some(dot) => Self {
+                material: dot.material as u8,
+                seed: variation_seed(dot.id),
+                speed_level: speed_level(dot.velocity),
+            },
+            None => Self {
+                material: Material::EmptySpace as u8,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn pack(self) -> u16 {
+        let shade = (self.seed & SEED_MASK) | (self.speed_level.min(MAX_SPEED_LEVEL) << 6);
+        u16::from_le_bytes([self.material, shade])
+    }
+
+    pub fn unpack(packed: u16) -> Self {
+        let [material, shade] = packed.to_le_bytes();
+        Self {
+            material,
+            seed: shade & SEED_MASK,
+            speed_level: shade >> 6,
+        }
+    }
+}
+
+/// Seed that gives a dot its own shade of its material, spread out so neighbouring ids look different
+pub fn variation_seed(id: DotId) -> u8 {
+    (id.wrapping_mul(0x9e37_79b1) >> 26) as u8
+}
+
+pub fn speed_level(velocity: Vec2<f64>) -> u8 {
+    let level = velocity.pythagorean_theorem() / SPEED_LEVEL_STEP;
+    (level as u8).min(MAX_SPEED_LEVEL)
+}
+
+/// Optional colour effects of the canvas shader, passed to it as flags in the view uniform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shading {
+    /// Vary hue and lightness of every dot within the range of its material
+    pub variation: bool,
+    /// Lighten dots the faster they move
+    pub velocity_tint: bool,
+}
+impl Shading {
+    // have to match the `SHADING_` constants in shader.frag
+    pub const VARIATION_FLAG: u32 = 1;
+    pub const VELOCITY_TINT_FLAG: u32 = 2;
+
+    /// Every material in its base colour
+    pub const FLAT: Self = Self {
+        variation: false,
+        velocity_tint: false,
+    };
+
+    pub fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.variation {
+            flags |= Self::VARIATION_FLAG;
+        }
+        if self.velocity_tint {
+            flags |= Self::VELOCITY_TINT_FLAG;
+        }
+        flags
+    }
+
+    /// Parses `flat` or a comma separated list of `variation` and `velocity`
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut shading = Self::FLAT;
+        for effect in value.split(',').map(str::trim) {
+            match effect {
+                "flat" => (),
+                "variation" => shading.variation = true,
+                "velocity" => shading.velocity_tint = true,
+                _ => return None,
+            }
+        }
+        Some(shading)
+    }
+}
+impl Default for Shading {
+    fn default() -> Self {
+        Self {
+            variation: true,
+            velocity_tint: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{speed_level, variation_seed, Cell, Shading};
+    use crate::game::math::Vec2;
+
+    #[test]
+    fn test_cell_bit_layout() {
+        let cell = Cell {
+            material: 0x04,
+            seed: 0x2a,
+            speed_level: 2,
+        };
+        // grouped the way the fields are packed, speed level, seed and material
+        #[allow(clippy::unusual_byte_groupings)]
+        let packed = 0b10_101010_00000100;
+        assert_eq!(packed, cell.pack());
+        assert_eq!(cell, Cell::unpack(cell.pack()));
+    }
+
+    #[test]
+    fn test_out_of_range_fields_are_cut_off() {
+        let cell = Cell {
+            material: 0x01,
+            seed: 0xff,
+            speed_level: 9,
+        };
+        assert_eq!(
+            Cell {
+                material: 0x01,
+                seed: 0x3f,
+                speed_level: 3
+            },
+            Cell::unpack(cell.pack())
+        );
+    }
+
+    #[test]
+    fn test_seed_is_stable_and_varies_between_ids() {
+        assert_eq!(variation_seed(7), variation_seed(7));
+        let seeds: Vec<u8> = (0..64).map(variation_seed).collect();
+        assert!(seeds.iter().all(|seed| *seed < 64));
+        let mut distinct = seeds.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert!(distinct.len() > 32);
+    }
+
+    #[test]
+    fn test_speed_levels() {
+        assert_eq!(0, speed_level(Vec2::new(0., 0.)));
+        assert_eq!(1, speed_level(Vec2::new(0., 70.)));
+        assert_eq!(3, speed_level(Vec2::new(1000., 0.)));
+    }
+
+    #[test]
+    fn test_parse_shading() {
+        assert_eq!(Some(Shading::FLAT), Shading::parse("flat"));
+        assert_eq!(
+            Some(Shading {
+                variation: true,
+                velocity_tint: true
+            }),
+            Shading::parse("variation, velocity")
+        );
+        assert_eq!(None, Shading::parse("bloom"));
+        assert_eq!(
+            Shading::VARIATION_FLAG | Shading::VELOCITY_TINT_FLAG,
+            Shading::parse("velocity,variation").unwrap().flags()
+        );
+    }
+}
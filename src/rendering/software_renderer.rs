@@ -0,0 +1,452 @@
+use crate::game::{canvas::Canvas, material::Material, Game};
+
+use super::{
+    export::{material_byte_color, RgbaImage},
+    glsl_types::{Resolution, ViewUniform},
+    lighting::{LightMap, Lighting},
+    post_processing::{self, PostProcessing},
+    shading::{Cell, Shading},
+};
+
+// colour of window pixels outside of the canvas, letterbox in shader.frag
+const LETTERBOX_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 0.];
+
+/// Renders frames on the CPU with the same maths as shader.frag and the post processing shaders,
+/// one fragment per window pixel. Colours are quantised like the UNORM images the GPU renders to,
+/// so a frame matches what the shaders write pixel for pixel.
+pub struct SoftwareRenderer {
+    frame: RgbaImage,
+    shading: Shading,
+    post_processing: PostProcessing,
+    lighting: Lighting,
+}
+impl SoftwareRenderer {
+    pub fn new() -> Self {
+        Self {
+            frame: RgbaImage {
+                resolution: Resolution {
+                    width: 0,
+                    height: 0,
+                },
+                pixels: Vec::new(),
+            },
+            shading: Shading::default(),
+            post_processing: PostProcessing::default(),
+            lighting: Lighting::default(),
+        }
+    }
+
+    /// Last frame that was rendered
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    /// Last frame that was rendered
+    pub fn into_frame(self) -> RgbaImage {
+        self.frame
+    }
+
+    pub fn render(&mut self, canvas: &Canvas, view: ViewUniform, window_resolution: Resolution) {
+        let cells = pack_canvas_bytes(canvas.grid.iter_cells_as_bytes());
+        let light_map: Vec<u32> = if view.light_cell_size != 0 {
+            LightMap::generate(canvas, view.light_cell_size)
+                .iter_packed()
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let width = window_resolution.width.max(0) as usize;
+        let height = window_resolution.height.max(0) as usize;
+
+        let mut scene = RgbaImage {
+            resolution: window_resolution,
+            pixels: Vec::with_capacity(width * height * 4),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                // gl_FragCoord is the centre of the pixel
+                let frag_coord = [x as f32 + 0.5, y as f32 + 0.5];
+                let color = fragment(frag_coord, &cells, &light_map, canvas.resolution, view);
+                scene.pixels.extend(color.map(to_unorm8));
+            }
+        }
+        self.frame = post_processing::apply(&scene, self.post_processing);
+    }
+
+    /// Renders the game through its camera, as it would look in a window of `window_resolution`
+    pub fn render_game(&mut self, game: &Game, window_resolution: Resolution) {
+        let view = game
+            .view_transform(window_resolution.into())
+            .to_uniform(self.shading, self.lighting);
+        self.render(game.canvas(), view, window_resolution);
+    }
+
+    pub fn set_shading(&mut self, shading: Shading) {
+        self.shading = shading;
+    }
+
+    pub fn set_post_processing(&mut self, post_processing: PostProcessing) {
+        self.post_processing = post_processing;
+    }
+
+    pub fn set_lighting(&mut self, lighting: Lighting) {
+        self.lighting = lighting;
+    }
+}
+impl Default for SoftwareRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Packs cell bytes into little endian words the way they end up in the canvas storage buffer,
+/// padded with empty space to a multiple of 4 bytes
+pub fn pack_canvas_bytes(bytes: impl Iterator<Item = u8>) -> Vec<u32> {
+    let bytes: Vec<u8> = bytes.collect();
+    bytes
+        .chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_le_bytes(word)
+        })
+        .collect()
+}
+
+/// `get_cell` in shader.frag
+pub fn get_cell(cells: &[u32], flat_coord: usize) -> u16 {
+    let cell_pair = cells[flat_coord / 2];
+    ((cell_pair >> ((flat_coord % 2) * 16)) & 0xffff) as u16
+}
+
+/// `shade` in shader.frag
+fn shade(rgb: [f32; 3], cell: Cell, shading: u32) -> [f32; 3] {
+    let [mut r, mut g, mut b] = rgb;
+    if shading & Shading::VARIATION_FLAG != 0 {
+        let [hue_range, lightness_range] =
+            Material::from_u8(cell.material).map_or([0., 0.], |m| m.color_variation());
+        let hue = (cell.seed & 7) as f32 * (2. / 7.) - 1.;
+        let lightness = (cell.seed >> 3) as f32 * (2. / 7.) - 1.;
+        r += hue * hue_range;
+        b -= hue * hue_range;
+        let lightness = 1. + lightness * lightness_range;
+        r *= lightness;
+        g *= lightness;
+        b *= lightness;
+    }
+    if shading & Shading::VELOCITY_TINT_FLAG != 0 && cell.material != 0 {
+        let tint = cell.speed_level as f32 * 0.1;
+        r += (1. - r) * tint;
+        g += (1. - g) * tint;
+        b += (1. - b) * tint;
+    }
+    [r, g, b]
+}
+
+/// `get_light` in shader.frag
+fn get_light(
+    light_map: &[u32],
+    canvas_coord: [i32; 2],
+    canvas_resolution: Resolution,
+    light_cell_size: u32,
+) -> [f32; 3] {
+    let light_width = (canvas_resolution.width as u32).div_ceil(light_cell_size);
+    let texel = canvas_coord.map(|c| c as u32 / light_cell_size);
+    let [r, g, b, _] = light_map[(texel[1] * light_width + texel[0]) as usize].to_le_bytes();
+    [r, g, b].map(|c| c as f32 / 255.)
+}
+
+/// `main` in shader.frag, returns `f_color`
+fn fragment(
+    frag_coord: [f32; 2],
+    cells: &[u32],
+    light_map: &[u32],
+    canvas_resolution: Resolution,
+    view: ViewUniform,
+) -> [f32; 4] {
+    let canvas_coord = [
+        ((frag_coord[0] - view.origin[0]) / view.scale).floor() as i32,
+        ((frag_coord[1] - view.origin[1]) / view.scale).floor() as i32,
+    ];
+
+    if canvas_coord[0] < 0
+        || canvas_coord[0] >= canvas_resolution.width
+        || canvas_coord[1] < 0
+        || canvas_coord[1] >= canvas_resolution.height
+    {
+        return LETTERBOX_COLOR;
+    }
+
+    let flat_coord = canvas_coord[0] + canvas_resolution.width * canvas_coord[1];
+    let cell = Cell::unpack(get_cell(cells, flat_coord as usize));
+
+    let color = material_byte_color(cell.material).map(|c| c as f32 / 255.);
+    let [mut r, mut g, mut b] = shade(color, cell, view.shading);
+    if view.light_cell_size != 0 {
+        let [light_r, light_g, light_b] =
+            get_light(light_map, canvas_coord, canvas_resolution, view.light_cell_size);
+        r *= light_r;
+        g *= light_g;
+        b *= light_b;
+    }
+    let emission = match Material::from_u8(cell.material) {
+        Some(material) if material.properties().emissive => 1.,
+        _ => 0.,
+    };
+    [r, g, b, emission]
+}
+
+/// Float to 8 bit conversion of a UNORM colour attachment
+pub fn to_unorm8(value: f32) -> u8 {
+    (value.clamp(0., 1.) * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_cell, pack_canvas_bytes, SoftwareRenderer};
+    use crate::{
+        game::{
+            camera::Camera, canvas::Canvas, material::Material, math::Vec2,
+            view_transform::ViewTransform,
+        },
+        rendering::{
+            glsl_types::Resolution, lighting::Lighting, post_processing::PostProcessing,
+            shading::Shading,
+        },
+    };
+
+    /// Canvas of 4x2 with sand in the top left corner and orange in the bottom right
+    fn setup_canvas() -> Canvas {
+        let mut canvas = Canvas::new(Resolution {
+            width: 4,
+            height: 2,
+        });
+        canvas
+            .spawn_dot(Material::Sand, Vec2::new(0., 0.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas
+            .spawn_dot(Material::Orange, Vec2::new(3., 1.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas.write_dots_to_grid();
+        canvas
+    }
+
+    /// Renders a canvas that fits the window exactly and returns one colour per pixel
+    fn render_pixels(canvas: &Canvas, shading: Shading) -> Vec<[u8; 4]> {
+        let view = ViewTransform::new(canvas.resolution.into(), canvas.resolution.into(), &Camera::new());
+        let mut renderer = SoftwareRenderer::new();
+        renderer.render(canvas, view.to_uniform(shading, Lighting::OFF), canvas.resolution);
+        renderer
+            .into_frame()
+            .pixels
+            .chunks(4)
+            .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+            .collect()
+    }
+
+    /// Renders the canvas without shading and turns the frame into one character per pixel:
+    /// `-` letterbox, `.` empty space, `s` sand, `o` orange
+    fn render_to_text(canvas: &Canvas, window: Resolution, camera: &Camera) -> Vec<String> {
+        let view = ViewTransform::new(
+            Vec2::new(window.width as f64, window.height as f64),
+            canvas.resolution.into(),
+            camera,
+        );
+        let mut renderer = SoftwareRenderer::new();
+        renderer.render(canvas, view.to_uniform(Shading::FLAT, Lighting::OFF), window);
+
+        renderer
+            .into_frame()
+            .pixels
+            .chunks(4)
+            .map(|pixel| match pixel {
+                [13, 13, 13, 255] => '-',
+                [0, 0, 0, 255] => '.',
+                [0xd7, 0xc9, 0xaa, 255] => 's',
+                [0xc0, 0x32, 0x21, 255] => 'o',
+                p => panic!("Unexpected pixel colour {:?}", p),
+            })
+            .collect::<Vec<_>>()
+            .chunks(window.width as usize)
+            .map(|row| row.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_golden_exact_fit() {
+        let frame = render_to_text(
+            &setup_canvas(),
+            Resolution {
+                width: 8,
+                height: 4,
+            },
+            &Camera::new(),
+        );
+        assert_eq!(
+            vec![
+                "ss......", //
+                "ss......",
+                "......oo",
+                "......oo",
+            ],
+            frame
+        );
+    }
+
+    #[test]
+    fn test_golden_tall_window_letterboxes() {
+        let frame = render_to_text(
+            &setup_canvas(),
+            Resolution {
+                width: 4,
+                height: 6,
+            },
+            &Camera::new(),
+        );
+        assert_eq!(
+            vec![
+                "----", //
+                "----",
+                "s...",
+                "...o",
+                "----",
+                "----",
+            ],
+            frame
+        );
+    }
+
+    #[test]
+    fn test_golden_wide_window_pillarboxes() {
+        let frame = render_to_text(
+            &setup_canvas(),
+            Resolution {
+                width: 10,
+                height: 2,
+            },
+            &Camera::new(),
+        );
+        assert_eq!(
+            vec![
+                "---s...---", //
+                "---...o---",
+            ],
+            frame
+        );
+    }
+
+    #[test]
+    fn test_golden_zoomed_camera() {
+        let mut camera = Camera::new();
+        camera.zoom = 2.;
+        camera.offset = Vec2::new(1., 0.);
+        let frame = render_to_text(
+            &setup_canvas(),
+            Resolution {
+                width: 4,
+                height: 2,
+            },
+            &camera,
+        );
+        assert_eq!(
+            vec![
+                "....", //
+                "..oo",
+            ],
+            frame
+        );
+    }
+
+    #[test]
+    fn test_packed_cells_unpack_like_the_shader() {
+        let cells = [0x0201u16, 0x0403, 0x0605];
+        let words = pack_canvas_bytes(cells.iter().flat_map(|cell| cell.to_le_bytes()));
+
+        assert_eq!(vec![0x04030201, 0x00000605], words);
+        for (i, cell) in cells.iter().enumerate() {
+            assert_eq!(*cell, get_cell(&words, i));
+        }
+        assert_eq!(0, get_cell(&words, 3));
+    }
+
+    #[test]
+    fn test_variation_gives_dots_their_own_shade() {
+        let mut canvas = Canvas::new(Resolution {
+            width: 8,
+            height: 1,
+        });
+        for x in 0..8 {
+            canvas
+                .spawn_dot(Material::Sand, Vec2::new(x as f64, 0.), Vec2::new(0., 0.))
+                .unwrap();
+        }
+        canvas.write_dots_to_grid();
+
+        let pixels = render_pixels(&canvas, Shading::default());
+        assert_eq!(pixels, render_pixels(&canvas, Shading::default()));
+        let mut distinct = pixels.clone();
+        distinct.sort();
+        distinct.dedup();
+        assert!(distinct.len() > 1);
+        let sand = Material::Sand.color();
+        for pixel in pixels {
+            for channel in 0..3 {
+                assert!((pixel[channel] as i32 - sand[channel] as i32).abs() <= 24);
+            }
+        }
+    }
+
+    #[test]
+    fn test_velocity_tint_lightens_fast_dots() {
+        let mut canvas = Canvas::new(Resolution {
+            width: 2,
+            height: 1,
+        });
+        canvas
+            .spawn_dot(Material::Dirt, Vec2::new(0., 0.), Vec2::new(0., 0.))
+            .unwrap();
+        canvas
+            .spawn_dot(Material::Dirt, Vec2::new(1., 0.), Vec2::new(0., 500.))
+            .unwrap();
+        canvas.write_dots_to_grid();
+
+        let pixels = render_pixels(
+            &canvas,
+            Shading {
+                variation: false,
+                velocity_tint: true,
+            },
+        );
+        let [r, g, b] = Material::Dirt.color();
+        assert_eq!([r, g, b, 255], pixels[0]);
+        assert!((0..3).all(|channel| pixels[1][channel] > pixels[0][channel]));
+    }
+
+    #[test]
+    fn test_lighting_darkens_dots_under_dirt() {
+        let mut canvas = Canvas::new(Resolution {
+            width: 1,
+            height: 3,
+        });
+        for (material, y) in [(Material::Sand, 0.), (Material::Dirt, 1.), (Material::Sand, 2.)] {
+            canvas
+                .spawn_dot(material, Vec2::new(0., y), Vec2::new(0., 0.))
+                .unwrap();
+        }
+        canvas.write_dots_to_grid();
+
+        let view = ViewTransform::new(canvas.resolution.into(), canvas.resolution.into(), &Camera::new());
+        let lighting = Lighting {
+            enabled: true,
+            cell_size: 1,
+        };
+        let mut renderer = SoftwareRenderer::new();
+        renderer.set_post_processing(PostProcessing::OFF);
+        renderer.render(&canvas, view.to_uniform(Shading::FLAT, lighting), canvas.resolution);
+        let pixels = renderer.into_frame().pixels;
+
+        let [r, g, b] = Material::Sand.color();
+        assert_eq!([r, g, b, 255], pixels[0..4]);
+        assert!((0..3).all(|channel| pixels[8 + channel] < pixels[channel] / 4));
+    }
+}
@@ -0,0 +1,196 @@
+//! Starts the game in a window, only built with the `window` feature
+
+use std::{path::PathBuf, time::Duration};
+
+use log::warn;
+use unknown_game::{
+    internal::{Bindings, Lighting, PostProcessing},
+    ConfigSource, Game,
+};
+use winit::event_loop::EventLoop;
+
+use crate::{
+    display::{
+        device_selector::DeviceSelector, present_mode::PresentModeSetting,
+        render_engine::RenderEngine, render_error::RenderError, renderer::Renderer,
+        renderer_kind::RendererKind, software_presenter::SoftwarePresenter,
+    },
+    exit_with_error, shading_from_env,
+    windowing::{self, frame_limiter::FrameLimiter},
+    EXIT_FAILURE,
+};
+
+// set to the index or part of the name of a graphics device to use it instead of the best one
+const DEVICE_ENV_VAR: &str = "UNKNOWN_GAME_DEVICE";
+// set to print frame times and time spent waiting on the GPU every few seconds
+const FRAME_STATS_ENV_VAR: &str = "UNKNOWN_GAME_FRAME_STATS";
+const FRAME_STATS_INTERVAL: Duration = Duration::from_secs(5);
+// set to fifo (vsync), mailbox or immediate (no vsync), falls back to the closest supported mode
+const PRESENT_MODE_ENV_VAR: &str = "UNKNOWN_GAME_PRESENT_MODE";
+// set to a number of frames per second the game loop should not go over
+const FRAME_CAP_ENV_VAR: &str = "UNKNOWN_GAME_FRAME_CAP";
+// set to off or a comma separated list of bloom, bright-pass and blur to pick the post processing passes
+const POST_PROCESSING_ENV_VAR: &str = "UNKNOWN_GAME_POST_PROCESSING";
+// set to off, on or the canvas pixels per side of a light map texel to pick how finely light and shadow are drawn
+const LIGHTING_ENV_VAR: &str = "UNKNOWN_GAME_LIGHTING";
+// set to vulkan or software to pick what draws the game, software needs no graphics driver but is slow
+const RENDERER_ENV_VAR: &str = "UNKNOWN_GAME_RENDERER";
+// set to a file of `chord = action` lines to change which keys do what
+const BINDINGS_ENV_VAR: &str = "UNKNOWN_GAME_BINDINGS";
+
+fn bindings_from_env() -> Bindings {
+    match std::env::var(BINDINGS_ENV_VAR) {
+        Ok(path) => Bindings::from_file(&path).unwrap_or_else(|e| {
+            warn!("Using default key bindings, '{}': {}", path, e);
+            Bindings::default()
+        }),
+        Err(_) => Bindings::default(),
+    }
+}
+
+fn present_mode_from_env() -> PresentModeSetting {
+    match std::env::var(PRESENT_MODE_ENV_VAR) {
+        Ok(value) => PresentModeSetting::parse(&value).unwrap_or_else(|| {
+            warn!(
+                "Unknown present mode '{}', expected fifo, mailbox or immediate",
+                value
+            );
+            PresentModeSetting::default()
+        }),
+        Err(_) => PresentModeSetting::default(),
+    }
+}
+
+fn frame_cap_from_env() -> Option<f64> {
+    let value = std::env::var(FRAME_CAP_ENV_VAR).ok()?;
+    match value.trim().parse::<f64>() {
+        Ok(max_fps) if max_fps > 0. && max_fps.is_finite() => Some(max_fps),
+        _ => {
+            warn!("Frame cap '{}' is not a positive number", value);
+            None
+        }
+    }
+}
+
+fn renderer_kind_from_env() -> RendererKind {
+    match std::env::var(RENDERER_ENV_VAR) {
+        Ok(value) => RendererKind::parse(&value).unwrap_or_else(|| {
+            warn!("Unknown renderer '{}', expected vulkan or software", value);
+            RendererKind::default()
+        }),
+        Err(_) => RendererKind::default(),
+    }
+}
+
+fn post_processing_from_env() -> PostProcessing {
+    match std::env::var(POST_PROCESSING_ENV_VAR) {
+        Ok(value) => PostProcessing::parse(&value).unwrap_or_else(|| {
+            warn!(
+                "Unknown post processing '{}', expected off or a list of bloom, bright-pass and blur",
+                value
+            );
+            PostProcessing::default()
+        }),
+        Err(_) => PostProcessing::default(),
+    }
+}
+
+fn lighting_from_env() -> Lighting {
+    match std::env::var(LIGHTING_ENV_VAR) {
+        Ok(value) => Lighting::parse(&value).unwrap_or_else(|| {
+            warn!(
+                "Unknown lighting '{}', expected off, on or a light map cell size",
+                value
+            );
+            Lighting::default()
+        }),
+        Err(_) => Lighting::default(),
+    }
+}
+
+/// Names of the graphics devices Vulkan can see, in the order `--device` indexes them
+pub fn list_devices() -> Result<Vec<String>, RenderError> {
+    RenderEngine::list_devices(&EventLoop::new())
+}
+
+/// Window and renderer options from the command line, the ones not given are read from their
+/// environment variables
+#[derive(Debug, Default)]
+pub struct WindowOptions {
+    pub renderer: Option<RendererKind>,
+    pub device: Option<DeviceSelector>,
+    pub present_mode: Option<PresentModeSetting>,
+    pub frame_cap: Option<f64>,
+    pub post_processing: Option<PostProcessing>,
+    pub lighting: Option<Lighting>,
+}
+
+pub fn run(
+    options: WindowOptions,
+    mut game: Game,
+    config_source: ConfigSource,
+    profile_path: Option<PathBuf>,
+) {
+    let event_loop = EventLoop::new();
+    let device = options.device.or_else(|| {
+        std::env::var(DEVICE_ENV_VAR)
+            .ok()
+            .map(|value| DeviceSelector::parse(&value))
+    });
+    let present_mode = options.present_mode.unwrap_or_else(present_mode_from_env);
+
+    let mut window_state = windowing::state::WindowState::new(&event_loop);
+    window_state.shading = shading_from_env();
+    window_state.bindings = bindings_from_env();
+    window_state.config_source = config_source;
+    window_state.profile_path = profile_path;
+    window_state.frame_limiter = options
+        .frame_cap
+        .or_else(frame_cap_from_env)
+        .map(FrameLimiter::new);
+    let renderer_kind = options.renderer.unwrap_or_else(renderer_kind_from_env);
+    let renderer: Result<Box<dyn Renderer>, _> = match renderer_kind {
+        RendererKind::Vulkan => RenderEngine::new(
+            &event_loop,
+            window_state.window.clone(),
+            &game,
+            device.as_ref(),
+            present_mode,
+        )
+        .map(|mut render_engine| {
+            if std::env::var(FRAME_STATS_ENV_VAR).is_ok() {
+                render_engine.enable_frame_stats(FRAME_STATS_INTERVAL);
+            }
+            Box::new(render_engine) as Box<dyn Renderer>
+        }),
+        RendererKind::Software => {
+            if device.is_some() {
+                warn!("The software renderer draws on the CPU, the selected graphics device is not used");
+            }
+            SoftwarePresenter::new(window_state.window.clone())
+                .map(|presenter| Box::new(presenter) as Box<dyn Renderer>)
+        }
+    };
+    let mut renderer = match renderer {
+        Ok(renderer) => renderer,
+        Err(e) => {
+            game.stop_recording();
+            exit_with_error(e, EXIT_FAILURE);
+        }
+    };
+
+    renderer.set_shading(window_state.shading);
+    let configured = renderer
+        .set_post_processing(
+            options
+                .post_processing
+                .unwrap_or_else(post_processing_from_env),
+        )
+        .and_then(|()| renderer.set_lighting(options.lighting.unwrap_or_else(lighting_from_env)));
+    if let Err(e) = configured {
+        game.stop_recording();
+        exit_with_error(e, EXIT_FAILURE);
+    }
+
+    windowing::run_game_loop(event_loop, window_state, renderer, game);
+}
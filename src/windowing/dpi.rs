@@ -0,0 +1,19 @@
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+use unknown_game::{Resolution, Vec2};
+
+pub fn logical_position(position: PhysicalPosition<f64>, scale_factor: f64) -> Vec2<f64> {
+    let position = position.to_logical::<f64>(scale_factor);
+    Vec2::new(position.x, position.y)
+}
+
+pub fn size(size: PhysicalSize<u32>) -> Vec2<f64> {
+    Vec2::new(size.width as f64, size.height as f64)
+}
+
+pub fn resolution(size: PhysicalSize<u32>) -> Resolution {
+    Resolution {
+        width: size.width as i32,
+        height: size.height as i32,
+    }
+}
@@ -1,20 +1,35 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use log::{error, info, warn};
 use winit::{
-    event::{ElementState, Event, MouseButton, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::ControlFlow,
     window::Window,
 };
 
-use crate::{game::Game, rendering::render_engine::RenderEngine};
+use unknown_game::{
+    internal::{
+        default_profile_path, enable_profiler, end_profiled_frame, export_profile,
+        profiler_enabled, Action, KeyChord,
+    },
+    save_screenshot, Anchor, Game, ResizeMode,
+};
+
+use crate::{
+    display::{render_error::RenderError, renderer::Renderer},
+    logging,
+};
 
-use super::state::{MouseState, WindowState};
+use super::{dpi, keys, state::WindowState};
 
 pub fn handle_event(
     event: Event<()>,
     control_flow: &mut ControlFlow,
     window: Arc<Window>,
-    render_engine: &mut RenderEngine,
+    renderer: &mut dyn Renderer,
     game: &mut Game,
     window_state: &mut WindowState,
 ) {
@@ -23,21 +38,57 @@ pub fn handle_event(
             event: WindowEvent::CloseRequested,
             ..
         } => {
-            println!("User requested window to be closed");
+            info!("User requested window to be closed");
+            game.stop_recording();
+            if let Some(path) = &window_state.profile_path {
+                export_profile(path);
+            }
             control_flow.set_exit();
         }
         Event::WindowEvent {
-            event: WindowEvent::Resized(_),
+            event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. },
             ..
         } => {
-            render_engine.recreate_swapchain_and_resize_window(window.clone());
+            let result = renderer.resize_window(window.clone());
+            exit_on_render_error(result, control_flow, game);
         }
         Event::WindowEvent {
             event: WindowEvent::CursorMoved { position, .. },
             ..
         } => {
+            let scale_factor = window_state.window.scale_factor();
+            let position = dpi::logical_position(position, scale_factor);
+            if window_state.mouse_state.middle {
+                game.pan_camera(
+                    window_state.cursor_position,
+                    position,
+                    scale_factor,
+                    dpi::size(window_state.window.inner_size()),
+                );
+            }
             window_state.cursor_position = position;
         }
+        Event::WindowEvent {
+            event: WindowEvent::ModifiersChanged(modifiers),
+            ..
+        } => {
+            window_state.mouse_state.modifiers = keys::modifiers(modifiers);
+        }
+        Event::WindowEvent {
+            event: WindowEvent::MouseWheel { delta, .. },
+            ..
+        } => {
+            let scrolled_up = match delta {
+                MouseScrollDelta::LineDelta(_, y) => y > 0.,
+                MouseScrollDelta::PixelDelta(position) => position.y > 0.,
+            };
+            game.zoom_camera(
+                if scrolled_up { 1 } else { -1 },
+                window_state.cursor_position,
+                window_state.window.scale_factor(),
+                dpi::size(window_state.window.inner_size()),
+            );
+        }
         Event::WindowEvent {
             event:
                 WindowEvent::MouseInput {
@@ -47,25 +98,182 @@ pub fn handle_event(
                 },
             ..
         } => {
-            if let ElementState::Released = state {
-                window_state.mouse_state = MouseState::Released;
-            } else if let MouseButton::Right = button {
-                window_state.mouse_state = MouseState::RightPressed;
-            } else if let MouseButton::Left = button {
-                window_state.mouse_state = MouseState::LeftPressed;
+            let is_pressed = state == ElementState::Pressed;
+            match button {
+                MouseButton::Left => window_state.mouse_state.left = is_pressed,
+                MouseButton::Right => window_state.mouse_state.right = is_pressed,
+                MouseButton::Middle => window_state.mouse_state.middle = is_pressed,
+                MouseButton::Other(_) => (),
+            }
+        }
+        Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(keycode),
+                            ..
+                        },
+                    ..
+                },
+            ..
+        } => {
+            let Some(key) = keys::key_name(keycode) else {
+                return;
+            };
+            let chord = KeyChord::new(key, window_state.mouse_state.modifiers);
+            if let Some(action) = window_state.bindings.action_for(&chord) {
+                let result = perform_action(action, renderer, game, window_state);
+                exit_on_render_error(result, control_flow, game);
             }
         }
         Event::MainEventsCleared => {
-            game.handle_spawn_dots(
-                &window_state.cursor_position,
-                &window_state.window.inner_size(),
+            let now = Instant::now();
+            if let Some(limiter) = window_state.frame_limiter.as_mut() {
+                if !limiter.frame_due(now) {
+                    return;
+                }
+            }
+
+            game.set_time();
+            let input = game.frame_input(
+                window_state.cursor_position,
+                window_state.window.scale_factor(),
+                dpi::size(window_state.window.inner_size()),
                 &window_state.mouse_state,
             );
-            game.set_time();
-            let delta_time = game.delta_time;
-            game.set_next_frame(delta_time);
-            render_engine.display_next_frame(game, window.clone());
+            game.run_frame(input);
+
+            // the window doesn't know how long the GPU took, only how often frames come around
+            let summary = window_state.frame_stats.record_frame(now, Duration::ZERO);
+            if let Some(summary) = summary {
+                window_state.fps_readout = format!(
+                    "{:.0} FPS ({:.1}ms)",
+                    summary.fps(),
+                    summary.mean_frame_time.as_secs_f64() * 1000.
+                );
+            }
+            let status = game.status();
+            if status != window_state.shown_status || summary.is_some() {
+                let mut title = format!("Unknown Game - {}", status);
+                if !window_state.fps_readout.is_empty() {
+                    title += &format!(" - {}", window_state.fps_readout);
+                }
+                window.set_title(&title);
+                window_state.shown_status = status;
+            }
+
+            let result = renderer.display_next_frame(game, window.clone());
+            exit_on_render_error(result, control_flow, game);
+            // the renderer has picked up this frame's changes
+            game.mark_canvas_clean();
+            end_profiled_frame();
         }
         _ => (),
     }
 }
+
+/// A renderer that failed can't be trusted to draw again, so the game exits
+fn exit_on_render_error(
+    result: Result<(), RenderError>,
+    control_flow: &mut ControlFlow,
+    game: &mut Game,
+) {
+    if let Err(e) = result {
+        error!("{}", e);
+        game.stop_recording();
+        control_flow.set_exit_with_code(crate::EXIT_FAILURE);
+    }
+}
+
+fn perform_action(
+    action: Action,
+    renderer: &mut dyn Renderer,
+    game: &mut Game,
+    window_state: &mut WindowState,
+) -> Result<(), RenderError> {
+    let window_size = dpi::size(window_state.window.inner_size());
+    let scale_factor = window_state.window.scale_factor();
+    let window_center = window_size / 2. / scale_factor;
+    let resize = |game: &mut Game, renderer: &mut dyn Renderer, steps, mode| {
+        game.step_canvas_size(steps, mode);
+        renderer.resize_canvas(game)
+    };
+
+    match action {
+        Action::SelectMaterial(material) => game.material = material,
+        Action::ToggleTool => game.tool = game.tool.toggled(),
+        Action::TogglePause => game.toggle_pause(),
+        Action::Step => game.step(),
+        Action::FasterTime => game.step_time_scale(1),
+        Action::SlowerTime => game.step_time_scale(-1),
+        Action::ZoomIn => game.zoom_camera(1, window_center, scale_factor, window_size),
+        Action::ZoomOut => game.zoom_camera(-1, window_center, scale_factor, window_size),
+        Action::ResetCamera => game.camera.reset(),
+        Action::GrowCanvas => resize(game, renderer, 1, ResizeMode::Anchored(Anchor::Center))?,
+        Action::ShrinkCanvas => resize(game, renderer, -1, ResizeMode::Anchored(Anchor::Center))?,
+        Action::GrowCanvasRescaled => resize(game, renderer, 1, ResizeMode::Rescale)?,
+        Action::ShrinkCanvasRescaled => resize(game, renderer, -1, ResizeMode::Rescale)?,
+        Action::Undo => {
+            game.undo();
+        }
+        Action::Redo => {
+            game.redo();
+        }
+        Action::ClearCanvas => game.clear_canvas(),
+        Action::QuickSave => match game.quick_save() {
+            Ok(path) => info!("Quick saved to '{}'", path.display()),
+            Err(e) => warn!("Could not quick save: {}", e),
+        },
+        Action::QuickLoad => match game.quick_load() {
+            Ok(report) => {
+                renderer.resize_canvas(game)?;
+                info!("Quick loaded {}", report);
+            }
+            Err(e) => warn!("Could not quick load: {}", e),
+        },
+        Action::Screenshot => {
+            match save_screenshot(game.canvas(), ".", window_state.shading) {
+                Ok(path) => info!("Saved screenshot to '{}'", path.display()),
+                Err(e) => warn!("Could not save screenshot: {}", e),
+            }
+        }
+        Action::PlaceLight => {
+            if !game.place_light(window_state.cursor_position, scale_factor, window_size) {
+                warn!("Can't place a light outside of the canvas");
+            }
+        }
+        Action::ClearLights => game.clear_lights(),
+        Action::ReloadConfig => match window_state.config_source.load() {
+            Ok(config) => {
+                if config.canvas_resolution != game.canvas().resolution() {
+                    game.resize_canvas(config.canvas_resolution, ResizeMode::Anchored(Anchor::Center));
+                    renderer.resize_canvas(game)?;
+                }
+                if config != *game.config() {
+                    game.set_config(config);
+                }
+                info!("Reloaded config");
+            }
+            Err(e) => warn!("Keeping the current config, {}", e),
+        },
+        Action::TogglePhysicsLog => {
+            let is_on = logging::toggle_physics_diagnostics();
+            info!("Physics diagnostics {}", if is_on { "on" } else { "off" });
+        }
+        Action::ExportProfile => {
+            if profiler_enabled() {
+                let path = window_state
+                    .profile_path
+                    .clone()
+                    .unwrap_or_else(default_profile_path);
+                export_profile(&path);
+            } else {
+                enable_profiler();
+                info!("Profiling frames, export the profile again to write it out");
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+/// Keeps the game loop from running more than `max_fps` frames per second
+pub struct FrameLimiter {
+    frame_duration: Duration,
+    next_frame: Option<Instant>,
+}
+impl FrameLimiter {
+    pub fn new(max_fps: f64) -> Self {
+        Self {
+            frame_duration: Duration::from_secs_f64(1. / max_fps),
+            next_frame: None,
+        }
+    }
+
+    /// Returns true if a frame should run at `now`, and if so schedules the one after it
+    pub fn frame_due(&mut self, now: Instant) -> bool {
+        match self.next_frame {
+            Some(next_frame) if now < next_frame => false,
+            Some(next_frame) => {
+                // stay on the schedule unless we fell more than a frame behind
+                let scheduled = next_frame + self.frame_duration;
+                self.next_frame = Some(if scheduled < now {
+                    now + self.frame_duration
+                } else {
+                    scheduled
+                });
+                true
+            }
+            None => {
+                self.next_frame = Some(now + self.frame_duration);
+                true
+            }
+        }
+    }
+
+    /// When the loop should wake up for the next frame
+    pub fn next_frame(&self) -> Option<Instant> {
+        self.next_frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::FrameLimiter;
+
+    #[test]
+    fn test_frames_are_spaced_out() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut limiter = FrameLimiter::new(50.);
+
+        assert!(limiter.frame_due(start));
+        assert!(!limiter.frame_due(start + ms(5)));
+        assert_eq!(Some(start + ms(20)), limiter.next_frame());
+        // a late frame doesn't push the schedule back
+        assert!(limiter.frame_due(start + ms(25)));
+        assert_eq!(Some(start + ms(40)), limiter.next_frame());
+    }
+
+    #[test]
+    fn test_no_catching_up_after_stall() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let mut limiter = FrameLimiter::new(50.);
+
+        limiter.frame_due(start);
+        assert!(limiter.frame_due(start + ms(500)));
+        assert!(!limiter.frame_due(start + ms(501)));
+        assert_eq!(Some(start + ms(520)), limiter.next_frame());
+    }
+}
@@ -0,0 +1,109 @@
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+use unknown_game::Modifiers;
+
+/// Name of a key as bindings files write it, `None` for keys that can't be bound
+pub fn key_name(keycode: VirtualKeyCode) -> Option<&'static str> {
+    use VirtualKeyCode::*;
+    let name = match keycode {
+        Key1 => "1",
+        Key2 => "2",
+        Key3 => "3",
+        Key4 => "4",
+        Key5 => "5",
+        Key6 => "6",
+        Key7 => "7",
+        Key8 => "8",
+        Key9 => "9",
+        Key0 => "0",
+        A => "a",
+        B => "b",
+        C => "c",
+        D => "d",
+        E => "e",
+        F => "f",
+        G => "g",
+        H => "h",
+        I => "i",
+        J => "j",
+        K => "k",
+        L => "l",
+        M => "m",
+        N => "n",
+        O => "o",
+        P => "p",
+        Q => "q",
+        R => "r",
+        S => "s",
+        T => "t",
+        U => "u",
+        V => "v",
+        W => "w",
+        X => "x",
+        Y => "y",
+        Z => "z",
+        Escape => "escape",
+        F1 => "f1",
+        F2 => "f2",
+        F3 => "f3",
+        F4 => "f4",
+        F5 => "f5",
+        F6 => "f6",
+        F7 => "f7",
+        F8 => "f8",
+        F9 => "f9",
+        F10 => "f10",
+        F11 => "f11",
+        F12 => "f12",
+        Insert => "insert",
+        Home => "home",
+        Delete => "delete",
+        End => "end",
+        PageDown => "pagedown",
+        PageUp => "pageup",
+        Left => "left",
+        Up => "up",
+        Right => "right",
+        Down => "down",
+        Back => "backspace",
+        Return | NumpadEnter => "enter",
+        Space => "space",
+        Tab => "tab",
+        Numpad0 => "numpad0",
+        Numpad1 => "numpad1",
+        Numpad2 => "numpad2",
+        Numpad3 => "numpad3",
+        Numpad4 => "numpad4",
+        Numpad5 => "numpad5",
+        Numpad6 => "numpad6",
+        Numpad7 => "numpad7",
+        Numpad8 => "numpad8",
+        Numpad9 => "numpad9",
+        NumpadAdd => "numpad+",
+        NumpadSubtract => "numpad-",
+        NumpadMultiply => "numpad*",
+        NumpadDivide => "numpad/",
+        LBracket => "[",
+        RBracket => "]",
+        Comma => ",",
+        Period => ".",
+        Minus => "-",
+        Equals => "=",
+        Plus => "+",
+        Slash => "/",
+        Semicolon => ";",
+        Apostrophe => "'",
+        Grave => "`",
+        Backslash => "\\",
+        _ => return None,
+    };
+    Some(name)
+}
+
+pub fn modifiers(state: ModifiersState) -> Modifiers {
+    Modifiers {
+        ctrl: state.ctrl(),
+        shift: state.shift(),
+        alt: state.alt(),
+    }
+}
@@ -1,27 +1,39 @@
 use winit::event_loop::EventLoop;
 
+pub mod dpi;
 mod event_handler;
+pub mod frame_limiter;
+mod keys;
 pub mod state;
 
-use crate::rendering::render_engine::RenderEngine;
+use unknown_game::Game;
+
+use crate::display::renderer::Renderer;
 
 use self::state::WindowState;
 
-use super::game::Game;
 
 pub fn run_game_loop(
     event_loop: EventLoop<()>,
     mut window_state: WindowState,
-    mut render_engine: RenderEngine,
+    mut renderer: Box<dyn Renderer>,
     mut game: Game,
 ) {
     event_loop.run(move |event, _, control_flow| {
-        control_flow.set_poll();
+        // sleep until the next frame is due when the frame rate is capped
+        match window_state
+            .frame_limiter
+            .as_ref()
+            .and_then(|limiter| limiter.next_frame())
+        {
+            Some(next_frame) => control_flow.set_wait_until(next_frame),
+            None => control_flow.set_poll(),
+        }
         event_handler::handle_event(
             event,
             control_flow,
             window_state.window.clone(),
-            &mut render_engine,
+            renderer.as_mut(),
             &mut game,
             &mut window_state,
         );
@@ -1,25 +1,54 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use winit::{dpi::PhysicalPosition, event_loop::EventLoop, window::Window};
+use winit::{event_loop::EventLoop, window::Window};
 
-pub enum MouseState {
-    LeftPressed,
-    RightPressed,
-    Released,
-}
+use unknown_game::{internal::Bindings, ConfigSource, MouseState, Shading, Vec2};
+
+use crate::display::frame_stats::FrameStats;
+
+use super::frame_limiter::FrameLimiter;
+
+// how often the frame rate in the window title is updated
+const TITLE_FPS_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct WindowState {
     pub window: Arc<Window>,
-    pub cursor_position: PhysicalPosition<f64>,
+    /// Logical window pixels, so the cursor keeps its place when the scale factor changes
+    pub cursor_position: Vec2<f64>,
+    /// Mouse buttons and modifier keys that are currently held down
     pub mouse_state: MouseState,
+    /// Which key does what
+    pub bindings: Bindings,
+    /// Where the game config was loaded from, to load it again on request
+    pub config_source: ConfigSource,
+    /// Game status that is currently shown in the window title
+    pub shown_status: String,
+    /// Caps the frame rate of the game loop when set
+    pub frame_limiter: Option<FrameLimiter>,
+    /// Frame times for the readout in the window title
+    pub frame_stats: FrameStats,
+    /// Frame rate and frame time currently shown in the window title
+    pub fps_readout: String,
+    /// Colour effects the canvas is drawn with, also used for screenshots
+    pub shading: Shading,
+    /// Where the frame profile is written on exit and on request, a timestamped file when not set
+    pub profile_path: Option<PathBuf>,
 }
 impl WindowState {
     pub fn new(event_loop: &EventLoop<()>) -> Self {
         let window = Arc::new(Window::new(event_loop).unwrap());
         Self {
             window,
-            cursor_position: PhysicalPosition::new(0., 0.),
-            mouse_state: MouseState::Released,
+            cursor_position: Vec2::new(0., 0.),
+            mouse_state: MouseState::default(),
+            bindings: Bindings::default(),
+            config_source: ConfigSource::default(),
+            shown_status: String::new(),
+            frame_limiter: None,
+            frame_stats: FrameStats::new(TITLE_FPS_INTERVAL),
+            fps_readout: String::new(),
+            shading: Shading::default(),
+            profile_path: None,
         }
     }
 }
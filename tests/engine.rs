@@ -0,0 +1,96 @@
+//! Uses the engine the way another crate would, through its public API only
+
+use unknown_game::{
+    canvas_from_rgba, Canvas, ColorMapping, GameConfig, Material, Resolution, RgbaImage, Shading,
+    SpawnError, Vec2, SINGLE_STEP_DURATION,
+};
+
+fn cell_material(canvas: &Canvas, x: isize, y: isize) -> Option<Material> {
+    let cell = canvas.grid().get(Vec2::new(x, y)).unwrap();
+    cell.map(|dot| dot.material)
+}
+
+#[test]
+fn test_dot_falls_and_lands_on_floor() {
+    let mut canvas = Canvas::new(Resolution {
+        width: 8,
+        height: 8,
+    });
+    canvas
+        .spawn_dot(Material::Sand, Vec2::new(3., 0.), Vec2::new(0., 0.))
+        .unwrap();
+    canvas.write_dots_to_grid();
+    assert_eq!(Some(Material::Sand), cell_material(&canvas, 3, 0));
+
+    let config = GameConfig::default();
+    for _ in 0..120 {
+        canvas.step(SINGLE_STEP_DURATION, &config);
+    }
+    assert_eq!(None, cell_material(&canvas, 3, 0));
+    assert_eq!(Some(Material::Sand), cell_material(&canvas, 3, 7));
+    assert_eq!(1, canvas.dots().count());
+}
+
+#[test]
+fn test_canvas_survives_image_round_trip() {
+    let mut canvas = Canvas::new(Resolution {
+        width: 4,
+        height: 3,
+    });
+    for (material, x, y) in [(Material::Dirt, 0., 2.), (Material::Blue, 3., 2.), (Material::Orange, 1., 0.)] {
+        canvas
+            .spawn_dot(material, Vec2::new(x, y), Vec2::new(0., 0.))
+            .unwrap();
+    }
+    canvas.write_dots_to_grid();
+
+    let image = RgbaImage::from_canvas(&canvas, Shading::FLAT);
+    let (read_back, report) = canvas_from_rgba(image.resolution, &image.pixels, &ColorMapping::Nearest);
+    assert_eq!(3, report.imported);
+    assert_eq!(canvas.resolution(), read_back.resolution());
+    for y in 0..3 {
+        for x in 0..4 {
+            assert_eq!(cell_material(&canvas, x, y), cell_material(&read_back, x, y));
+        }
+    }
+}
+
+#[test]
+fn test_spawning_reports_why_it_failed() {
+    let mut canvas = Canvas::new(Resolution {
+        width: 4,
+        height: 4,
+    });
+    let zero = Vec2::new(0., 0.);
+    let id = canvas.spawn_dot(Material::Sand, Vec2::new(1., 1.), zero).unwrap();
+    // the pixel is taken before the grid is written
+    assert_eq!(
+        Err(SpawnError::CellOccupied(Vec2::new(1, 1))),
+        canvas.spawn_dot(Material::Dirt, Vec2::new(1.2, 0.9), zero)
+    );
+    assert_eq!(
+        Err(SpawnError::OutOfBounds(Vec2::new(4, 0))),
+        canvas.spawn_dot(Material::Dirt, Vec2::new(4., 0.), zero)
+    );
+
+    assert_eq!(Some(id), canvas.remove_dot(id).map(|dot| dot.id));
+    assert!(canvas.remove_dot(id).is_none());
+    assert!(canvas.spawn_dot(Material::Dirt, Vec2::new(1., 1.), zero).is_ok());
+    assert_eq!(1, canvas.dots().count());
+}
+
+#[test]
+fn test_state_hash_is_pinned() {
+    // replays recorded on any toolchain are checked against this hash, it must never change by accident
+    let mut canvas = Canvas::new(Resolution {
+        width: 4,
+        height: 4,
+    });
+    canvas
+        .spawn_dot(Material::Sand, Vec2::new(1., 2.), Vec2::new(0.5, -3.))
+        .unwrap();
+    canvas
+        .spawn_dot(Material::Blue, Vec2::new(3., 0.), Vec2::new(0., 0.))
+        .unwrap();
+    assert_eq!(0xe8e2_5049_2b17_fe0a, canvas.state_hash());
+}